@@ -1,35 +1,78 @@
+pub mod artist_info;
+pub mod audio;
+pub mod bookmarks;
+pub mod connectivity;
 pub mod cover_art;
+pub mod filter;
+pub mod folders;
+pub mod genres;
+pub mod history;
 pub mod input;
+pub mod logging;
+pub mod lyrics;
+pub mod marks;
 pub mod mpris;
 pub mod navigation;
+pub mod now_playing;
 pub mod playback;
+pub mod playlist;
+pub mod podcasts;
+pub mod prefetch;
 pub mod queue;
+pub mod radio;
+pub mod rating;
+pub mod scrobble;
 pub mod search;
+pub mod servers;
+pub mod sleep;
+pub mod stats;
 use crate::{
-    config::{Config, ConfigError},
+    app::artist_info::ArtistInfoView,
+    config::{Config, ProgressLabel, ReplayGainMode, SearchMode},
+    history::History,
+    lastfm::LastfmClient,
     mpris_handler::MprisPlayer,
+    network::{build_stream_client, is_timeout, is_unreachable},
+    offline::OfflineCache,
     player::{Player, PlayerCommand, PlayerState, SharedPlayerState},
     search::SearchEngine,
-    subsonic::SubsonicClient,
+    session::Session,
+    subsonic::{AlbumListType, SubsonicClient},
 };
 use anyhow::Result;
 use crossterm::terminal::disable_raw_mode;
+use image::DynamicImage;
 use mpris_server::{Metadata, PlaybackStatus, Server, Time};
-use ratatui::widgets::ListState;
-use ratatui_image::protocol::StatefulProtocol;
+use ratatui::{text::Line, widgets::ListState};
+use ratatui_image::{
+    picker::{Picker as ImagePicker, ProtocolType},
+    protocol::StatefulProtocol,
+};
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
     io::{self, Write},
     rc::Rc,
     sync::{Arc, RwLock},
     time::Duration,
 };
-use tokio::sync::{Mutex, mpsc};
+use tokio::{
+    sync::{Mutex, mpsc},
+    task::JoinHandle,
+};
 
 pub struct TerminalGuard; // used to make sure terminal goes back to normal
 impl TerminalGuard {
     pub fn new() -> Self {
         let original_hook = std::panic::take_hook();
         std::panic::set_hook(Box::new(move |info| {
+            // Kept in the log file, the terminal may not show it in one piece
+            log::error!("{}\n{}", info, std::backtrace::Backtrace::force_capture());
+            // A panicking background task leaves the ui running, only the main thread ends it
+            if std::thread::current().name() != Some("main") {
+                return;
+            }
             let _ = disable_raw_mode();
             let _ = crossterm::execute!(
                 io::stdout(),
@@ -37,7 +80,11 @@ impl TerminalGuard {
                 crossterm::event::DisableMouseCapture,
             );
             let _ = io::stdout().flush();
-            original_hook(info)
+            original_hook(info);
+            eprintln!(
+                "The crash was logged to {}",
+                crate::logging::log_path().display()
+            );
         }));
         Self
     }
@@ -56,7 +103,7 @@ impl Drop for TerminalGuard {
 
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
-    #[error("No Track Loaded")]
+    #[error("Nothing is playing")]
     NoTrackLoaded,
     #[error("Queue is empty")]
     EmptyQueue,
@@ -66,15 +113,96 @@ pub enum AppError {
 pub enum VolumeDirection {
     Up,
     Down,
+    FineUp,
+    FineDown,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RepeatMode {
     None,
     One,
     All,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// How the genres chosen for the Songs filter combine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GenreMatch {
+    #[default]
+    Any,
+    All,
+}
+impl GenreMatch {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GenreMatch::Any => "any",
+            GenreMatch::All => "all",
+        }
+    }
+    pub fn toggle(self) -> Self {
+        match self {
+            GenreMatch::Any => GenreMatch::All,
+            GenreMatch::All => GenreMatch::Any,
+        }
+    }
+    /// Whether a track tagged with `genres` passes a filter on `wanted`
+    pub fn matches(&self, wanted: &[String], genres: &[String]) -> bool {
+        let has = |w: &String| genres.iter().any(|g| g.eq_ignore_ascii_case(w));
+        match self {
+            GenreMatch::Any => wanted.iter().any(has),
+            GenreMatch::All => wanted.iter().all(has),
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SongSort {
+    Title,
+    Artist,
+    Album,
+    PlayCount,
+}
+impl SongSort {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SongSort::Title => "title",
+            SongSort::Artist => "artist",
+            SongSort::Album => "album",
+            SongSort::PlayCount => "plays",
+        }
+    }
+    /// Cycle order, starting from the server order
+    pub fn next(sort: Option<SongSort>) -> Option<SongSort> {
+        match sort {
+            None => Some(SongSort::Title),
+            Some(SongSort::Title) => Some(SongSort::Artist),
+            Some(SongSort::Artist) => Some(SongSort::Album),
+            Some(SongSort::Album) => Some(SongSort::PlayCount),
+            Some(SongSort::PlayCount) => Some(SongSort::Title),
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlbumSort {
+    Name,
+    Artist,
+    Year,
+}
+impl AlbumSort {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlbumSort::Name => "name",
+            AlbumSort::Artist => "artist",
+            AlbumSort::Year => "year",
+        }
+    }
+    pub fn next(sort: Option<AlbumSort>) -> Option<AlbumSort> {
+        match sort {
+            None => Some(AlbumSort::Name),
+            Some(AlbumSort::Name) => Some(AlbumSort::Artist),
+            Some(AlbumSort::Artist) => Some(AlbumSort::Year),
+            Some(AlbumSort::Year) => Some(AlbumSort::Name),
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ShuffleMode {
     Off,
     On,
@@ -83,6 +211,15 @@ pub struct TabSelection<T> {
     pub index: usize,
     pub state: ListState,
     pub data: Vec<T>,
+    // Rows marked in visual mode, cleared whenever `data` is rebuilt
+    pub marked: HashSet<usize>,
+    // Set while `data` only holds the rows matching a filter
+    pub filter: Option<ListFilter<T>>,
+}
+/// The whole list behind a filtered view, and where each shown row sits in it
+pub struct ListFilter<T> {
+    pub full: Vec<T>,
+    pub origin: Vec<usize>,
 }
 impl<T> TabSelection<T> {
     pub fn new() -> Self {
@@ -90,8 +227,23 @@ impl<T> TabSelection<T> {
             index: 0,
             state: ListState::default(),
             data: Vec::new(),
+            marked: HashSet::new(),
+            filter: None,
+        }
+    }
+    /// Put the whole list back, keeping the selected row selected
+    pub fn clear_filter(&mut self) {
+        if let Some(ListFilter { full, origin }) = self.filter.take() {
+            self.index = origin.get(self.index).copied().unwrap_or(0);
+            self.data = full;
+            self.marked.clear();
         }
     }
+    /// Every row, including the ones a filter hides
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let hidden = self.filter.iter_mut().flat_map(|f| f.full.iter_mut());
+        self.data.iter_mut().chain(hidden)
+    }
     pub fn select(&mut self, idx: usize) {
         self.index = idx;
         self.state.select(Some(idx));
@@ -108,6 +260,45 @@ impl<T> TabSelection<T> {
     pub fn len(&self) -> usize {
         self.data.len()
     }
+    /// The whole list, also while a filter shows part of it
+    pub fn all_rows(&self) -> &[T] {
+        self.filter.as_ref().map_or(&self.data, |f| &f.full)
+    }
+    /// Keys of the marked rows, to mark the same rows again once `data` was rebuilt
+    pub fn marked_keys<K: Eq + Hash>(&self, key: impl Fn(&T) -> K) -> HashSet<K> {
+        self.marked
+            .iter()
+            .filter_map(|&i| self.data.get(i))
+            .map(key)
+            .collect()
+    }
+    /// Mark the shown rows whose key is in `keys`, see `marked_keys`
+    pub fn mark_keys<K: Eq + Hash>(&mut self, keys: &HashSet<K>, key: impl Fn(&T) -> K) {
+        self.marked = self
+            .data
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| keys.contains(&key(row)))
+            .map(|(i, _)| i)
+            .collect();
+    }
+}
+impl<T: Clone> TabSelection<T> {
+    /// Show only the rows at `positions` of the whole list
+    pub fn filter_to(&mut self, positions: Vec<usize>) {
+        self.clear_filter();
+        let full = std::mem::take(&mut self.data);
+        self.data = positions
+            .iter()
+            .filter_map(|&i| full.get(i).cloned())
+            .collect();
+        self.filter = Some(ListFilter {
+            full,
+            origin: positions,
+        });
+        self.marked.clear();
+        self.index = 0;
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -115,19 +306,153 @@ pub enum InputMode {
     Normal,
     Search,
     InlineSearch, // search in current tab
+    Filter,       // narrow the current tab to the rows matching what is typed
+    Prompt,       // single line text input in a popup
+    Picker,       // choose one entry from a popup list
+    Confirm,      // yes/no question before a destructive action
 }
-#[derive(Clone, Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptKind {
+    PlaylistName,
+    SleepTimer,
+    Volume,
+}
+impl PromptKind {
+    pub fn title(&self) -> &'static str {
+        match self {
+            PromptKind::PlaylistName => "Save queue as playlist",
+            PromptKind::SleepTimer => "Sleep timer",
+            PromptKind::Volume => "Set volume",
+        }
+    }
+    pub fn label(&self) -> &'static str {
+        match self {
+            PromptKind::PlaylistName => "Playlist name",
+            PromptKind::SleepTimer => "Stop after minutes (0 cancels)",
+            PromptKind::Volume => "Volume % (0-100)",
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmAction {
+    DeletePlaylist { id: String, name: String },
+    ClearCoverArtCache,
+}
+impl ConfirmAction {
+    pub fn message(&self) -> String {
+        match self {
+            ConfirmAction::DeletePlaylist { name, .. } => {
+                format!("Delete playlist '{}'?", name)
+            }
+            ConfirmAction::ClearCoverArtCache => "Remove all cached cover art?".to_string(),
+        }
+    }
+}
+pub enum PickerKind {
+    AddToPlaylist {
+        track_ids: Vec<String>,
+        playlist_ids: Vec<String>,
+    },
+    SwitchServer {
+        names: Vec<String>,
+    },
+    ResumeBookmark {
+        bookmarks: Vec<Bookmark>,
+    },
+    OutputDevice {
+        // None is the system default
+        names: Vec<Option<String>>,
+    },
+    History {
+        tracks: Vec<Track>,
+    },
+    RadioStation {
+        stations: Vec<RadioStation>,
+    },
+    Genre {
+        genres: Vec<String>,
+    },
+}
+pub struct Picker {
+    pub kind: PickerKind,
+    pub title: String,
+    pub list: TabSelection<String>,
+}
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Track {
     pub id: String,
     pub title: String,
     pub artist: String,
     pub album_artist: Option<String>,
     pub album: String,
+    // Missing on servers that leave it out and in sessions saved before it was kept
+    #[serde(default)]
+    pub album_id: Option<String>,
+    #[serde(default)]
+    pub artist_id: Option<String>,
+    // The url carries auth params, so it is rebuilt instead of saved
+    #[serde(skip)]
     pub cover_art: Option<String>,
     pub duration: i64,
     pub track_number: Option<i32>,
     pub play_count: Option<i32>,
     pub genres: Vec<String>,
+    #[serde(default)]
+    pub year: Option<i32>,
+    // File type and kbps as stored on the server, or as transcoded for the stream playing
+    #[serde(default)]
+    pub suffix: Option<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub bit_rate: Option<i32>,
+    pub replay_gain: Option<ReplayGain>,
+    pub rating: Option<i32>, // 1-5 stars, None when unrated
+    pub starred: bool,
+}
+impl Track {
+    /// Whether both are on the same album. Names alone mix up e.g. every "Greatest Hits", so
+    /// the album id decides when both have one
+    pub fn same_album(&self, other: &Track) -> bool {
+        match (&self.album_id, &other.album_id) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.album == other.album && self.album_artist == other.album_artist,
+        }
+    }
+}
+
+/// Loudness tags reported by OpenSubsonic servers, gains in dB
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+impl ReplayGain {
+    /// Linear volume factor for the preferred gain, falling back to the other one.
+    /// The factor is capped by the peak so the adjustment never clips
+    pub fn factor(&self, mode: &ReplayGainMode) -> f32 {
+        let (gain, peak) = match mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (
+                self.track_gain.or(self.album_gain),
+                self.track_peak.or(self.album_peak),
+            ),
+            ReplayGainMode::Album => (
+                self.album_gain.or(self.track_gain),
+                self.album_peak.or(self.track_peak),
+            ),
+        };
+        let Some(gain) = gain else {
+            return 1.0;
+        };
+        let factor = 10f32.powf(gain / 20.0);
+        match peak {
+            Some(peak) if peak > 0.0 => factor.min(1.0 / peak),
+            _ => factor,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -135,6 +460,10 @@ pub struct Album {
     pub id: String,
     pub name: String,
     pub artist: String,
+    pub song_count: Option<i32>,
+    pub year: Option<i32>,
+    pub duration: Option<i64>,     // seconds
+    pub cover_art: Option<String>, // cover id, not a url
 }
 #[derive(Clone, Debug)]
 pub struct Artist {
@@ -142,6 +471,103 @@ pub struct Artist {
     pub name: String,
     pub album_count: i32,
 }
+#[derive(Clone, Debug)]
+pub struct Bookmark {
+    pub track: Track,
+    pub position_ms: i64,
+}
+/// A track another client on the same server is playing
+#[derive(Clone, Debug)]
+pub struct NowPlaying {
+    pub track: Track,
+    pub username: String,
+    pub player_name: Option<String>,
+}
+/// What getArtistInfo2 knows about an artist
+#[derive(Clone, Debug, Default)]
+pub struct ArtistDetails {
+    pub biography: Option<String>,
+    pub similar: Vec<SimilarArtist>,
+}
+/// A similar artist, `id` is only set when the server has them
+#[derive(Clone, Debug)]
+pub struct SimilarArtist {
+    pub id: Option<String>,
+    pub name: String,
+}
+/// Lyrics of a track. Lines of synced lyrics start at `start_ms`, unsynced ones are all at 0
+#[derive(Clone, Debug)]
+pub struct Lyrics {
+    pub synced: bool,
+    pub lines: Vec<LyricLine>,
+}
+#[derive(Clone, Debug)]
+pub struct LyricLine {
+    pub start_ms: i64,
+    pub text: String,
+}
+impl Lyrics {
+    /// The line being sung at a position, None before the first one starts
+    pub fn current_line(&self, position_ms: i64) -> Option<usize> {
+        if !self.synced {
+            return None;
+        }
+        self.lines
+            .iter()
+            .rposition(|line| line.start_ms <= position_ms)
+    }
+}
+/// A row of the Folders tab, which mirrors the server's directory tree
+#[derive(Clone, Debug)]
+pub enum FolderEntry {
+    Directory { id: String, name: String },
+    Song(Box<Track>),
+}
+/// A row of the Podcasts tab, the channels at the top and a channel's episodes once entered
+#[derive(Clone, Debug)]
+pub enum PodcastEntry {
+    // The latest episodes of every channel, listed above the channels
+    Newest,
+    Channel { id: String, title: String },
+    Episode(Box<PodcastEpisode>),
+}
+#[derive(Clone, Debug)]
+pub struct PodcastEpisode {
+    // The channel is the artist and album, the id is the stream id once downloaded
+    pub track: Track,
+    pub stream_id: Option<String>,
+    pub status: String, // new, downloading, completed, error, deleted or skipped
+    pub publish_date: Option<String>,
+}
+impl PodcastEpisode {
+    /// The server can only stream episodes it finished downloading
+    pub fn is_downloaded(&self) -> bool {
+        self.stream_id.is_some() && self.status == "completed"
+    }
+    pub fn status_label(&self) -> &str {
+        match self.status.as_str() {
+            _ if self.is_downloaded() => "downloaded",
+            "completed" | "new" | "skipped" => "not downloaded",
+            "error" => "download failed",
+            other => other,
+        }
+    }
+}
+/// An internet radio station saved on the server
+#[derive(Clone, Debug)]
+pub struct RadioStation {
+    pub id: String,
+    pub name: String,
+    pub stream_url: String, // absolute, played as is rather than through the stream endpoint
+}
+/// A directory entered in the Folders tab, with the row it was entered from
+#[derive(Clone, Debug)]
+pub struct FolderLevel {
+    pub id: String,
+    pub name: String,
+    pub parent_index: usize,
+}
+#[derive(Clone, Debug)]
 pub struct Playlists {
     pub id: String,
     pub name: String,
@@ -162,6 +588,23 @@ pub enum ActiveTab {
     Songs,
     Search,
     Favorites,
+    Folders,
+    Podcasts,
+}
+impl ActiveTab {
+    /// Tab named by the `default_tab` setting
+    pub fn from_name(name: &str) -> Option<ActiveTab> {
+        match name.to_lowercase().as_str() {
+            "songs" => Some(ActiveTab::Songs),
+            "artists" => Some(ActiveTab::Artists),
+            "albums" => Some(ActiveTab::Albums),
+            "playlists" => Some(ActiveTab::Playlist),
+            "favorites" => Some(ActiveTab::Favorites),
+            "folders" => Some(ActiveTab::Folders),
+            "podcasts" => Some(ActiveTab::Podcasts),
+            _ => None,
+        }
+    }
 }
 
 pub enum LibraryMessage {
@@ -174,17 +617,74 @@ pub enum LibraryMessage {
     },
     SongsAppended(Vec<Track>),
     Error(String),
+    // The server could not be reached, so the app should go offline
+    Unreachable(String),
+}
+
+pub enum DownloadMessage {
+    Finished {
+        server: String,
+        track: Track,
+        data: Vec<u8>,
+    },
+    Failed {
+        track: Track,
+        error: String,
+    },
 }
 
 pub struct App {
     pub config: Config,
     pub subsonic_client: Arc<SubsonicClient>,
     pub needs_initial_load: bool,
+    pub refreshing: bool,
+    pub spinner_frame: usize,
     pub library_rx: Option<mpsc::Receiver<LibraryMessage>>,
+    // Offline mode, entered when the server can't be reached
+    pub offline: bool,
+    pub last_ping: Option<std::time::Instant>,
+    pub ping_rx: Option<mpsc::Receiver<bool>>,
+    // Other sessions on the account
+    pub now_playing: Vec<NowPlaying>,
+    pub now_playing_rx: Option<mpsc::Receiver<Vec<NowPlaying>>>,
+    pub last_now_playing_check: Option<std::time::Instant>,
     pub player: Rc<Mutex<Player>>,
     pub is_playing: bool,
+    pub loading_track: bool,
+    pub pending_playback: Option<(Track, usize)>,
+    pub radio: Option<RadioStation>, // the station playing instead of the queue
+    pub pending_seek: Option<u64>,   // seconds, applied after the pending track loads
+    pub resuming: bool, // playing the saved session, a track that fails to load is skipped
+    pub resume_paused: bool, // the saved session's track loads paused, without resume_on_start
+    // Stream urls already checked against the server, by track id
+    pub stream_urls: HashMap<String, String>,
+    pub stream_url_rx: Option<mpsc::Receiver<(String, String)>>,
+    // Songs of recently selected artists by artist id, oldest first, fetched once the
+    // Artists selection settles so Enter doesn't wait on the server
+    pub artist_songs: Vec<(String, Vec<Track>)>,
+    pub artist_selection: Option<(String, std::time::Instant)>, // artist id and when selected
+    pub artist_songs_task: Option<JoinHandle<()>>,
+    pub artist_songs_rx: Option<mpsc::Receiver<(String, Vec<Track>)>>,
+    pub track_started: Option<std::time::Instant>,
+    pub scrobbled_current: bool, // the playing track crossed the scrobble threshold
+    pub deferred_finish: Option<u64>, // end reported inside the start grace period
+    pub sleep_deadline: Option<std::time::Instant>,
+    pub stop_after_current_group: bool, // stop once the playing album ends
+    pub compact: bool,                  // one line header and no controls bar
+    pub visual_mode: bool,              // Space marks tracks instead of pausing
+    pub progress_label: ProgressLabel,
+    pub artist_info: Option<ArtistInfoView>, // biography and similar artists shown over the lists
+    pub stats_view: bool,                    // library totals shown over the lists
+    pub verbose_logging: bool,               // debug output goes to the log file
+    // Full screen lyrics of the playing track
+    pub lyrics_view: bool,
+    pub lyrics: Option<Lyrics>,
+    pub lyrics_track_id: Option<String>, // track the lyrics, or the fetch in flight, belong to
+    pub lyrics_rx: Option<mpsc::Receiver<Option<Lyrics>>>,
+    pub lyrics_scroll: u16, // for unsynced lyrics
     pub current_track: Option<Track>,
     pub current_volume: f64,
+    pub boost_db: f32, // manual boost, reset when the album changes
     pub playing_index: usize,
     pub mpris: Server<MprisPlayer>,
     pub shared_state: SharedPlayerState,
@@ -192,64 +692,113 @@ pub struct App {
     pub metadata: Metadata,
     pub widget_notification: Option<(String, std::time::Instant)>,
     pub w_notification_duration: std::time::Duration,
+    pub needs_redraw: bool, // set by anything that changes what is on screen
     pub last_search_keystroke: Option<std::time::Instant>,
     // Shuffle and repeat
     pub on_repeat: RepeatMode,
+    pub repeat_before_one: Option<RepeatMode>, // restored when the repeat one toggle goes off
     pub shuffle_mode: ShuffleMode,
     pub shuffle_order: Vec<usize>,
     pub shuffle_position: usize,
     // TabSelection
     pub queue_tab: TabSelection<Track>,
     pub tracks_tab: TabSelection<Track>,
+    pub songs_grouped: bool,
+    pub song_sort: Option<SongSort>,
+    pub album_sort: Option<AlbumSort>,
+    pub album_list_type: AlbumListType,
+    pub grouped_songs_state: ListState, // display rows, album headers included
     pub artist_tab: TabSelection<Artist>,
     pub album_tab: TabSelection<Album>,
     pub playlist_tab: TabSelection<Playlists>,
     pub search_tab: TabSelection<Track>,
+    pub search_label: Option<String>, // set when the Search tab lists something other than a search
+    pub search_albums: Vec<Album>,    // albums and artists a local search also matched
+    pub search_artists: Vec<Artist>,
     pub favorite_tab: TabSelection<Track>,
+    pub folder_tab: TabSelection<FolderEntry>,
+    pub folder_path: Vec<FolderLevel>, // empty at the top of the tree
+    pub podcast_tab: TabSelection<PodcastEntry>,
+    pub podcast_channel: Option<FolderLevel>, // the channel or Newest list being shown
     pub active_tab: ActiveTab,
     pub active_section: ActiveSection,
     // Search fields
     pub input_mode: InputMode,
     pub search_query: String,
+    pub inline_matches: Vec<usize>, // rows of the active list matching the find query
+    pub filter_query: String,
+    pub genre_filter: Vec<String>, // genres the Songs tab is narrowed to, in the order chosen
+    pub genre_match: GenreMatch,
     pub search_engine: SearchEngine,
-    pub is_searching: bool,
+    pub search_mode: SearchMode, // starts as configured, Ctrl+t flips it while searching
+    pub is_searching: bool,      // also while a server search is in flight
+    pub search_generation: u64,  // bumped per server search, older responses are dropped
+    pub search_task: Option<JoinHandle<()>>,
+    pub search_tx: mpsc::Sender<(u64, Result<Vec<Track>>)>,
+    pub search_rx: mpsc::Receiver<(u64, Result<Vec<Track>>)>,
+    pub search_cover_task: Option<JoinHandle<()>>, // covers of the results, with prefetch_covers
+    // Prompt fields
+    pub prompt: Option<PromptKind>,
+    pub prompt_input: String,
+    pub picker: Option<Picker>,
+    // Tracks started on this machine, see `H`
+    pub history: History,
+    pub confirm: Option<ConfirmAction>,
     pub cover_art_protocol: Option<StatefulProtocol>,
+    // Block art fallback when there is no graphics protocol, rebuilt only when the cell resizes
+    pub cover_art_image: Option<DynamicImage>,
+    pub cover_art_blocks: Option<((u16, u16), Vec<Line<'static>>)>,
+    pub cover_art_failed: bool, // the last load of the playing track's cover went wrong
+    // Probed once at startup, None when the terminal has no graphics protocol
+    pub image_picker: Option<ImagePicker>,
+    pub images_supported: bool,
+    // Album thumbnails, keyed by cover id
+    pub album_thumbnails: bool,
+    pub thumbnail_cache: HashMap<String, StatefulProtocol>,
+    pub thumbnail_requested: HashSet<String>,
+    pub thumbnail_tx: mpsc::Sender<(String, Option<DynamicImage>)>,
+    pub thumbnail_rx: mpsc::Receiver<(String, Option<DynamicImage>)>,
+    // Offline downloads
+    pub offline_cache: OfflineCache,
+    pub download_tx: mpsc::Sender<DownloadMessage>,
+    pub download_rx: mpsc::Receiver<DownloadMessage>,
+    // Tracks refetched in the background, e.g. for the play count after they finished
+    pub refresh_tx: mpsc::Sender<Track>,
+    pub refresh_rx: mpsc::Receiver<Track>,
+    // Direct Last.fm scrobbling, None unless configured. Failures come back as messages
+    pub lastfm: Option<LastfmClient>,
+    pub lastfm_tx: mpsc::Sender<String>,
+    pub lastfm_rx: mpsc::Receiver<String>,
+}
+
+/// Ask the terminal which graphics protocol it speaks. Halfblocks is what the picker falls back
+/// to without one, so that counts as no image support
+fn probe_image_support() -> Option<ImagePicker> {
+    match ImagePicker::from_query_stdio() {
+        Ok(picker) if picker.protocol_type() != ProtocolType::Halfblocks => Some(picker),
+        Ok(_) => {
+            log::debug!("Terminal has no graphics protocol, cover art disabled");
+            None
+        }
+        Err(e) => {
+            log::debug!("Failed to query terminal graphics support: {}", e);
+            None
+        }
+    }
 }
 
 impl App {
-    pub async fn new() -> Result<Self> {
-        let config = match Config::load() {
-            Ok(c) => c,
-            Err(ConfigError::NotFound { path }) => {
-                eprintln!(
-                    "No config found. A default config has been created at: {}\nPlease edit it and restart",
-                    path.display()
-                );
-                std::process::exit(1);
-            }
-            Err(ConfigError::ParseError { path, reason }) => {
-                eprintln!(
-                    "Failed to parse config at {}:\n {}\nPlease fix the config and restart.",
-                    path.display(),
-                    reason
-                );
-                std::process::exit(1);
-            }
-            Err(ConfigError::ValidationError(msg)) => {
-                eprintln!(
-                    "Invalid config value:\n {}\nPlease fix the config and restart.",
-                    msg
-                );
-                std::process::exit(1);
-            }
-            Err(e) => {
-                eprintln!("Config error: {}", e);
-                std::process::exit(1);
-            }
-        };
+    pub async fn new(config: Config) -> Result<Self> {
         let subsonic_client = Arc::new(SubsonicClient::new(&config)?);
-        let player = Rc::new(Mutex::new(Player::new()));
         let (tx, rx) = mpsc::channel::<PlayerCommand>(32);
+        let player = Rc::new(Mutex::new(Player::new(
+            build_stream_client(&config.network)?,
+            config.network.retries,
+            tx.clone(),
+            config.playback.output_device.clone(),
+            Duration::from_millis(config.playback.fade_ms),
+            config.playback.decoder,
+        )));
         let shared_state = Arc::new(RwLock::new(PlayerState {
             status: PlaybackStatus::Stopped,
             metadata: Metadata::default(),
@@ -257,17 +806,18 @@ impl App {
             can_go_next: false,
             can_go_previous: false,
             position: Time::ZERO,
+            track_list: Vec::new(),
         }));
         let mprisserver = {
             let mut result = None;
             for i in 0..10u32 {
-                let iface = MprisPlayer::new(tx.clone(), shared_state.clone());
+                let iface = MprisPlayer::new(tx.clone(), shared_state.clone(), config.mpris.raise);
                 let name = if i == 0 {
                     "sonicrust".to_string()
                 } else {
                     format!("sonicrust.instance{}", i)
                 };
-                match Server::new(&name, iface).await {
+                match Server::new_with_track_list(&name, iface).await {
                     Ok(s) => {
                         result = Some(s);
                         break;
@@ -281,53 +831,170 @@ impl App {
                 anyhow::anyhow!("Failed to register any MPRIS name after 10 attempts")
             })?
         };
-        let search_engine = SearchEngine::new(config.search.fuzzy_threshold, 30);
+        let search_engine =
+            SearchEngine::new(config.search.fuzzy_threshold, config.search.max_results);
+        let offline_cache = OfflineCache::load(&config.active_server);
+        let (download_tx, download_rx) = mpsc::channel(8);
+        let (refresh_tx, refresh_rx) = mpsc::channel(8);
+        let lastfm = LastfmClient::from_config(&config.lastfm, &config.network)?;
+        let (lastfm_tx, lastfm_rx) = mpsc::channel(8);
+        let (thumbnail_tx, thumbnail_rx) = mpsc::channel(32);
+        let (search_tx, search_rx) = mpsc::channel(4);
+        let progress_label = config.ui.progress_label;
+        let search_mode = config.search.mode;
+        let history = if config.playback.save_history {
+            History::load().unwrap_or_default()
+        } else {
+            History::default()
+        };
+        let image_picker = if config.ui.images {
+            probe_image_support()
+        } else {
+            None
+        };
 
-        let app = Self {
+        let active_tab = ActiveTab::from_name(&config.ui.default_tab).unwrap_or(ActiveTab::Songs);
+        let mut app = Self {
             config,
             needs_initial_load: true,
+            refreshing: false,
+            spinner_frame: 0,
             subsonic_client: subsonic_client.clone(),
             player,
             metadata: Metadata::default(),
             playing_index: 0,
             is_playing: false,
+            loading_track: false,
+            pending_playback: None,
+            radio: None,
+            pending_seek: None,
+            resuming: false,
+            resume_paused: false,
+            stream_urls: HashMap::new(),
+            stream_url_rx: None,
+            artist_songs: Vec::new(),
+            artist_selection: None,
+            artist_songs_task: None,
+            artist_songs_rx: None,
+            track_started: None,
+            scrobbled_current: false,
+            deferred_finish: None,
+            sleep_deadline: None,
+            stop_after_current_group: false,
+            compact: false,
+            visual_mode: false,
+            progress_label,
+            lyrics_view: false,
+            artist_info: None,
+            stats_view: false,
+            verbose_logging: false,
+            lyrics: None,
+            lyrics_track_id: None,
+            lyrics_rx: None,
+            lyrics_scroll: 0,
             current_track: None,
             current_volume: 1.0,
+            boost_db: 0.0,
             shared_state,
             last_search_keystroke: None,
             widget_notification: None,
             w_notification_duration: std::time::Duration::from_secs(3),
+            needs_redraw: true,
             tracks_tab: TabSelection::new(),
+            songs_grouped: false,
+            song_sort: None,
+            album_sort: None,
+            album_list_type: AlbumListType::default(),
+            grouped_songs_state: ListState::default(),
             queue_tab: TabSelection::new(),
             artist_tab: TabSelection::new(),
             album_tab: TabSelection::new(),
             search_tab: TabSelection::new(),
+            search_label: None,
+            search_albums: Vec::new(),
+            search_artists: Vec::new(),
             playlist_tab: TabSelection::new(),
             favorite_tab: TabSelection::new(),
+            folder_tab: TabSelection::new(),
+            folder_path: Vec::new(),
+            podcast_tab: TabSelection::new(),
+            podcast_channel: None,
             mpris: mprisserver,
             command_receiver: rx,
             library_rx: None,
-            active_tab: ActiveTab::Songs,
+            offline: false,
+            last_ping: None,
+            ping_rx: None,
+            now_playing: Vec::new(),
+            now_playing_rx: None,
+            last_now_playing_check: None,
+            active_tab,
             active_section: ActiveSection::Others,
             input_mode: InputMode::Normal,
             search_query: String::new(),
+            inline_matches: Vec::new(),
+            filter_query: String::new(),
+            genre_filter: Vec::new(),
+            genre_match: GenreMatch::default(),
             search_engine,
+            search_mode,
             is_searching: false,
+            search_generation: 0,
+            search_task: None,
+            search_tx,
+            search_rx,
+            search_cover_task: None,
+            prompt: None,
+            prompt_input: String::new(),
+            picker: None,
+            history,
+            confirm: None,
             on_repeat: RepeatMode::None,
+            repeat_before_one: None,
             shuffle_mode: ShuffleMode::Off,
             shuffle_order: Vec::new(),
             shuffle_position: 0,
             cover_art_protocol: None,
+            cover_art_image: None,
+            cover_art_blocks: None,
+            cover_art_failed: false,
+            images_supported: image_picker.is_some(),
+            image_picker,
+            album_thumbnails: false,
+            thumbnail_cache: HashMap::new(),
+            thumbnail_requested: HashSet::new(),
+            thumbnail_tx,
+            thumbnail_rx,
+            offline_cache,
+            download_tx,
+            download_rx,
+            refresh_tx,
+            refresh_rx,
+            lastfm,
+            lastfm_tx,
+            lastfm_rx,
         };
 
         // app.refresh_library().await?;
+        match app.active_tab {
+            ActiveTab::Folders => app.open_folders().await,
+            ActiveTab::Podcasts => app.open_podcasts().await,
+            _ => {}
+        }
+        if let Some(session) = Session::load() {
+            app.restore_session(session).await?;
+        }
         Ok(app)
     }
     pub async fn update(&mut self) -> Result<()> {
+        self.load_pending_playback().await?;
+        self.sync_track_list().await;
         if self.needs_initial_load {
             self.needs_initial_load = false;
             self.start_background_load();
             self.set_notification("Loading Library...");
+            // After the loading message so a missing output isn't immediately hidden
+            self.check_output_device();
             // self.refresh_library().await?;
             // self.set_notification("Library loaded");
         }
@@ -340,28 +1007,92 @@ impl App {
                     playlists,
                     favorites,
                 }) => {
+                    self.clear_filters();
                     self.tracks_tab.data = songs;
+                    self.clear_marks();
                     self.artist_tab.data = artists;
                     self.album_tab.data = albums;
+                    self.album_list_type = AlbumListType::default();
+                    self.apply_song_order();
+                    self.apply_album_sort();
                     self.playlist_tab.data = playlists;
                     self.favorite_tab.data = favorites;
+                    self.clear_artist_songs();
+                    // Highlight the row of the tab shown at startup
+                    if self.active_section == ActiveSection::Others
+                        && self.active_tab != ActiveTab::Search
+                    {
+                        self.select_tab(self.active_tab.clone());
+                    }
                     // self.library_rx = None;
                     self.set_notification("Library Loaded");
                 }
                 Ok(LibraryMessage::SongsAppended(songs)) => {
-                    self.tracks_tab.data.extend(songs);
+                    self.append_songs(songs);
+                    self.needs_redraw = true;
                 }
                 Ok(LibraryMessage::Error(e)) => {
                     self.set_notification(format!("Load Error: {}", e));
                     self.library_rx = None;
+                    self.refreshing = false;
+                }
+                Ok(LibraryMessage::Unreachable(e)) => {
+                    self.library_rx = None;
+                    self.refreshing = false;
+                    self.enter_offline_mode(&e);
                 }
                 Err(mpsc::error::TryRecvError::Empty) => {} // This means it is still loading
                 Err(_) => {
+                    // The loader finished sending every page
                     self.library_rx = None;
+                    self.refreshing = false;
+                    self.needs_redraw = true;
                 }
             }
         }
+        self.check_connectivity();
+        self.refresh_now_playing();
+        self.load_album_thumbnails();
+        self.receive_stream_urls();
+        self.prefetch_artist_songs();
+        self.receive_artist_songs();
+        self.receive_search_results();
+        self.receive_refreshed_tracks();
+        self.load_lyrics();
+        if self.refreshing {
+            self.spinner_frame = self.spinner_frame.wrapping_add(1);
+        }
+        while let Ok(msg) = self.download_rx.try_recv() {
+            match msg {
+                DownloadMessage::Finished {
+                    server,
+                    track,
+                    data,
+                } => {
+                    // The server may have been switched while the download ran
+                    let stored = if server == self.config.active_server {
+                        self.offline_cache.store(&track.id, &data)
+                    } else {
+                        OfflineCache::load(&server).store(&track.id, &data)
+                    };
+                    match stored {
+                        Ok(_) => self.set_notification(format!("Downloaded '{}'", track.title)),
+                        Err(e) => self.set_notification(format!("Failed to save download: {}", e)),
+                    }
+                }
+                DownloadMessage::Failed { track, error } => {
+                    self.set_notification(format!(
+                        "Download of '{}' failed: {}",
+                        track.title, error
+                    ));
+                }
+            }
+        }
+        while let Ok(error) = self.lastfm_rx.try_recv() {
+            self.set_notification(format!("Last.fm: {}", error));
+        }
         while let Ok(cmd) = self.command_receiver.try_recv() {
+            self.needs_redraw = true;
             match cmd {
                 PlayerCommand::Play => {
                     if self.current_track.is_some() {
@@ -389,22 +1120,35 @@ impl App {
                     self.stop_playback().await?;
                 }
                 PlayerCommand::TogglePlayPause => {
-                    self.toggle_playback().await?;
+                    let result = self.toggle_playback().await;
+                    self.notify_boundary(result)?;
                 }
                 PlayerCommand::SetVolume(v) => {
                     self.set_volume(v).await?;
                 }
                 PlayerCommand::Next => {
-                    self.play_next().await?;
+                    let result = self.play_next().await;
+                    self.notify_boundary(result)?;
                 }
                 PlayerCommand::Previous => {
-                    self.play_previous().await?;
+                    let result = self.play_previous().await;
+                    self.notify_boundary(result)?;
+                }
+                PlayerCommand::TrackFinished(generation) => {
+                    self.handle_track_finished(generation).await?;
                 }
-                // PlayerCommand::TrackFinished => {
-                //     self.on_track_finished().await?;
-                // }
+                PlayerCommand::DeviceLost => {
+                    self.on_device_lost().await;
+                }
+                PlayerCommand::GoTo(id) => self.mpris_go_to(&id).await?,
+                PlayerCommand::RemoveTrack(id) => self.mpris_remove_track(&id),
+                PlayerCommand::AddTrack {
+                    song_id,
+                    after,
+                    play,
+                } => self.mpris_add_track(&song_id, &after, play).await?,
                 PlayerCommand::SeekRelative(secs) => {
-                    let player = self.player.lock().await;
+                    let mut player = self.player.lock().await;
                     player.seek_relative(secs)?;
                     let new_pos = player.get_position();
                     if let Ok(mut state) = self.shared_state.write() {
@@ -412,7 +1156,7 @@ impl App {
                     }
                 }
                 PlayerCommand::SeekAbsolute(secs) => {
-                    let player = self.player.lock().await;
+                    let mut player = self.player.lock().await;
                     player.seek_absolute(secs)?;
                     let new_pos = player.get_position();
                     if let Ok(mut state) = self.shared_state.write() {
@@ -427,30 +1171,107 @@ impl App {
         {
             self.last_search_keystroke = None;
             self.perform_search().await?;
+            self.needs_redraw = true;
         }
 
-        self.check_track_finished().await?;
+        self.check_deferred_finish().await?;
+        self.check_crossfade().await?;
+        self.check_sleep_timer().await?;
+        self.check_scrobble().await;
         self.update_mpris_position().await?;
         self.tick_notification();
         Ok(())
     }
+    /// Write the queue and the volume, repeat and shuffle settings for the next start
+    pub async fn save_session(&self) -> Result<()> {
+        let position_secs = match (&self.current_track, &self.radio) {
+            (Some(_), None) => self.player.lock().await.get_position().as_secs().max(0) as u64,
+            _ => 0,
+        };
+        Session {
+            server: self.config.active_server.clone(),
+            // Cover urls carry the credentials, restore_session builds them again
+            queue: self
+                .queue_tab
+                .data
+                .iter()
+                .map(|track| Track {
+                    cover_art: None,
+                    ..track.clone()
+                })
+                .collect(),
+            playing_index: self.playing_index,
+            position_secs,
+            volume: self.current_volume,
+            repeat: self.on_repeat.clone(),
+            shuffle: self.shuffle_mode.clone(),
+        }
+        .save()
+    }
+    async fn restore_session(&mut self, session: Session) -> Result<()> {
+        if session.server != self.config.active_server {
+            return Ok(());
+        }
+        self.queue_tab.data = session
+            .queue
+            .into_iter()
+            .map(|mut track| {
+                track.cover_art = self.subsonic_client.build_cover_art_url(&track.id).ok();
+                track
+            })
+            .collect();
+        self.playing_index = session
+            .playing_index
+            .min(self.queue_tab.len().saturating_sub(1));
+        self.queue_tab.index = self.playing_index;
+        self.on_repeat = session.repeat;
+        if session.shuffle == ShuffleMode::On {
+            self.enable_shuffle();
+        }
+        self.set_volume(session.volume).await?;
+        if let Some(track) = self.queue_tab.data.get(self.playing_index).cloned() {
+            // The track loads on the first update, `load_pending_playback` seeks once it has
+            self.resuming = true;
+            self.resume_paused = !self.config.playback.resume_on_start;
+            self.pending_seek = Some(session.position_secs);
+            if let Err(e) = self.start_playback(track, self.playing_index).await {
+                log::warn!("Could not resume the session: {}", e);
+                self.resuming = false;
+                self.resume_paused = false;
+                self.pending_seek = None;
+            }
+        }
+        Ok(())
+    }
     pub fn set_notification(&mut self, msg: impl Into<String>) {
         self.widget_notification = Some((msg.into(), std::time::Instant::now()));
+        self.needs_redraw = true;
     }
     pub fn tick_notification(&mut self) {
         if let Some((_, created)) = &self.widget_notification
             && created.elapsed() >= self.w_notification_duration
         {
             self.widget_notification = None;
+            self.needs_redraw = true;
         }
     }
+    /// Whether the next loop iteration should draw. Playback progress, the loading spinner and
+    /// the sleep countdown change on their own, everything else marks `needs_redraw`
+    pub fn wants_redraw(&self) -> bool {
+        self.needs_redraw
+            || self.is_playing
+            || self.refreshing
+            || self.loading_track
+            || self.sleep_deadline.is_some()
+    }
     pub fn start_background_load(&mut self) {
         let (tx, rx) = mpsc::channel(4);
         self.library_rx = Some(rx);
+        self.refreshing = true;
         let client = self.subsonic_client.clone();
         tokio::spawn(async move {
             let (first_page, artists, albums, playlists, favorites) = match tokio::try_join!(
-                client.get_album_page(0, 10),
+                client.get_album_list(AlbumListType::default(), 0, 10),
                 // client.get_all_songs(),
                 client.get_all_artists(),
                 client.get_all_albums(),
@@ -459,7 +1280,17 @@ impl App {
             ) {
                 Ok(r) => r,
                 Err(e) => {
-                    let _ = tx.send(LibraryMessage::Error(e.to_string())).await;
+                    let msg = if is_timeout(&e) {
+                        "server did not respond in time".to_string()
+                    } else {
+                        e.to_string()
+                    };
+                    let msg = if is_unreachable(&e) {
+                        LibraryMessage::Unreachable(msg)
+                    } else {
+                        LibraryMessage::Error(msg)
+                    };
+                    let _ = tx.send(msg).await;
                     return;
                 }
             };
@@ -495,22 +1326,41 @@ impl App {
             }
         });
     }
-    pub async fn refresh_library(&mut self) -> Result<()> {
+    pub fn download_selected(&mut self) {
+        let Some(track) = self.selected_track() else {
+            self.set_notification("Select a track to download");
+            return;
+        };
+        if self.offline_cache.contains(&track.id) {
+            self.set_notification(format!("'{}' is already available offline", track.title));
+            return;
+        }
+        self.set_notification(format!("Downloading '{}'...", track.title));
         let client = self.subsonic_client.clone();
+        let tx = self.download_tx.clone();
+        let server = self.config.active_server.clone();
+        tokio::spawn(async move {
+            let msg = match client.download(&track.id).await {
+                Ok(data) => DownloadMessage::Finished {
+                    server,
+                    track,
+                    data,
+                },
+                Err(e) => DownloadMessage::Failed {
+                    track,
+                    error: e.to_string(),
+                },
+            };
+            let _ = tx.send(msg).await;
+        });
+    }
+    /// Reload the whole library in the background so the UI keeps drawing meanwhile
+    pub fn refresh_library(&mut self) {
+        if self.refreshing {
+            self.set_notification("Library refresh already in progress");
+            return;
+        }
         self.set_notification("Loading Library...");
-        let (songs, artist, albums, playlists, favorites) = tokio::try_join!(
-            client.get_all_songs(),
-            client.get_all_artists(),
-            client.get_all_albums(),
-            client.get_playlists(),
-            client.get_all_favorites(),
-        )?;
-        self.tracks_tab.data = songs;
-        self.artist_tab.data = artist;
-        self.album_tab.data = albums;
-        self.playlist_tab.data = playlists;
-        self.favorite_tab.data = favorites;
-
-        Ok(())
+        self.start_background_load();
     }
 }
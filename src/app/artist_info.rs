@@ -0,0 +1,99 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::{ActiveSection, ActiveTab, SimilarArtist, TabSelection};
+
+use super::App;
+
+/// The artist overlay: their biography and similar artists to jump to or search for
+pub struct ArtistInfoView {
+    pub name: String,
+    pub biography: Option<String>,
+    pub similar: TabSelection<SimilarArtist>,
+}
+
+impl App {
+    /// Look up the selected artist's biography and similar artists
+    pub async fn open_artist_info(&mut self) {
+        if self.active_section != ActiveSection::Others || self.active_tab != ActiveTab::Artists {
+            self.set_notification("Select an artist in the Artists tab for their info");
+            return;
+        }
+        let Some(artist) = self.artist_tab.get().cloned() else {
+            return;
+        };
+        let details = match self.subsonic_client.get_artist_info(&artist.id).await {
+            Ok(details) => details,
+            Err(e) => {
+                self.set_notification(format!("Failed to load info for {}: {}", artist.name, e));
+                return;
+            }
+        };
+        if details.biography.is_none() && details.similar.is_empty() {
+            self.set_notification(format!("The server has no info on {}", artist.name));
+            return;
+        }
+        let mut similar = TabSelection::new();
+        similar.data = details.similar;
+        if !similar.data.is_empty() {
+            similar.select(0);
+        }
+        self.artist_info = Some(ArtistInfoView {
+            name: artist.name,
+            biography: details.biography,
+            similar,
+        });
+    }
+    /// Keys of the artist overlay, returns false for the ones it leaves to the normal bindings
+    /// so playback can still be controlled from it
+    pub async fn handle_artist_info_key(&mut self, key: KeyEvent) -> bool {
+        let Some(view) = self.artist_info.as_mut() else {
+            return false;
+        };
+        let len = view.similar.len();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('w') => self.artist_info = None,
+            KeyCode::Char('j') | KeyCode::Down if len > 0 => {
+                view.similar.select((view.similar.index + 1) % len)
+            }
+            KeyCode::Char('k') | KeyCode::Up if len > 0 => {
+                view.similar.select((view.similar.index + len - 1) % len)
+            }
+            KeyCode::Enter => {
+                if let Some(artist) = view.similar.get().cloned() {
+                    self.artist_info = None;
+                    self.show_similar_artist(&artist).await;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+    /// Where a similar artist is in the Artists tab, by id and else by name
+    pub fn library_artist_position(&self, artist: &SimilarArtist) -> Option<usize> {
+        let artists = self.artist_tab.all_rows();
+        artist
+            .id
+            .as_ref()
+            .and_then(|id| artists.iter().position(|a| &a.id == id))
+            .or_else(|| {
+                artists
+                    .iter()
+                    .position(|a| a.name.eq_ignore_ascii_case(&artist.name))
+            })
+    }
+    /// Select the artist in the Artists tab, or search for them when they aren't in the library
+    async fn show_similar_artist(&mut self, artist: &SimilarArtist) {
+        self.artist_tab.clear_filter();
+        if let Some(idx) = self.library_artist_position(artist) {
+            self.active_section = ActiveSection::Others;
+            self.select_tab(ActiveTab::Artists);
+            self.artist_tab.select(idx);
+            return;
+        }
+        self.enter_search_mode();
+        self.search_query = artist.name.clone();
+        if let Err(e) = self.perform_search().await {
+            self.set_notification(format!("Search for {} failed: {}", artist.name, e));
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{app::PickerKind, config::Config, player::Player};
+
+use super::App;
+impl App {
+    /// Move playback to the current default output, keeping the position
+    pub async fn reconnect_audio(&mut self) {
+        let result = self.player.lock().await.reinit_output();
+        match result {
+            Ok(()) => self.set_notification("Reconnected to the default audio output"),
+            Err(e) => self.set_notification(format!("Failed to reconnect audio: {}", e)),
+        }
+    }
+    /// The output went away, try the new default right away and point at `O` if that fails
+    pub async fn on_device_lost(&mut self) {
+        let result = self.player.lock().await.reinit_output();
+        match result {
+            Ok(()) => self.set_notification("Audio device changed, switched to the new default"),
+            Err(e) => {
+                self.set_notification(format!("Audio device lost ({}), press 'O' to reconnect", e))
+            }
+        }
+    }
+    pub async fn start_pick_output_device(&mut self) {
+        let devices = match Player::output_devices() {
+            Ok(devices) => devices,
+            Err(e) => {
+                self.set_notification(format!("Cannot list audio outputs: {}", e));
+                return;
+            }
+        };
+        let current = self.player.lock().await.output_device().map(str::to_string);
+        let mut names = vec![None];
+        names.extend(devices.into_iter().map(Some));
+        let items = names
+            .iter()
+            .map(|name| {
+                let marker = if *name == current { "● " } else { "  " };
+                format!("{}{}", marker, name.as_deref().unwrap_or("System default"))
+            })
+            .collect();
+        self.open_picker(PickerKind::OutputDevice { names }, "Audio output", items);
+    }
+    /// Switch outputs, resuming the track where it was, and save the choice for the next start
+    pub async fn set_output_device(&mut self, name: Option<String>) {
+        let result = self.player.lock().await.set_output_device(name.as_deref());
+        if let Err(e) = result {
+            self.set_notification(format!("Failed to switch audio output: {}", e));
+            return;
+        }
+        let label = name.as_deref().unwrap_or("the system default").to_string();
+        self.config.playback.output_device = name;
+        match Config::save_output_device(self.config.playback.output_device.as_deref()) {
+            Ok(()) => self.set_notification(format!("Playing through {}", label)),
+            Err(e) => self.set_notification(format!(
+                "Playing through {}, but it could not be saved: {}",
+                label, e
+            )),
+        }
+    }
+    /// Tell the user when the saved output is gone, the player already fell back to the default
+    pub fn check_output_device(&mut self) {
+        let Some(name) = self.config.playback.output_device.clone() else {
+            return;
+        };
+        if Player::output_devices().is_ok_and(|devices| !devices.contains(&name)) {
+            self.set_notification(format!(
+                "Audio output '{}' not found, using the default",
+                name
+            ));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+
+use crate::app::{Bookmark, PickerKind};
+
+use super::App;
+impl App {
+    pub async fn bookmark_current_position(&mut self) {
+        let Some(track) = self.current_track.clone() else {
+            self.set_notification("Nothing is playing, nothing to bookmark");
+            return;
+        };
+        let position_ms = self.player.lock().await.get_position().as_millis();
+        match self
+            .subsonic_client
+            .create_bookmark(&track.id, position_ms)
+            .await
+        {
+            Ok(()) => self.set_notification(format!(
+                "Bookmarked '{}' at {}",
+                track.title,
+                format_position(position_ms)
+            )),
+            Err(e) => self.set_notification(format!("Failed to create bookmark: {}", e)),
+        }
+    }
+    pub async fn start_resume_bookmark(&mut self) {
+        let bookmarks = match self.subsonic_client.get_bookmarks().await {
+            Ok(bookmarks) => bookmarks,
+            Err(e) => {
+                self.set_notification(format!("Failed to load bookmarks: {}", e));
+                return;
+            }
+        };
+        if bookmarks.is_empty() {
+            self.set_notification("No bookmarks yet, press 'b' while a track plays");
+            return;
+        }
+        let items = bookmarks
+            .iter()
+            .map(|b| {
+                format!(
+                    "{} - {} @ {}",
+                    b.track.title,
+                    b.track.artist,
+                    format_position(b.position_ms)
+                )
+            })
+            .collect();
+        self.open_picker(
+            PickerKind::ResumeBookmark { bookmarks },
+            "Resume bookmark",
+            items,
+        );
+    }
+    /// Play the bookmarked track from where it was left, right after the playing track so the
+    /// rest of the queue stays as it is
+    pub async fn resume_bookmark(&mut self, bookmark: Bookmark) -> Result<()> {
+        let index = if self.current_track.is_some() {
+            self.playing_index + 1
+        } else {
+            self.playing_index
+        }
+        .min(self.queue_tab.len());
+        self.queue_tab.data.insert(index, bookmark.track.clone());
+        self.queue_tab.select(index);
+        self.playing_index = index;
+        self.queue_edited();
+        // Applied once the track has loaded
+        self.pending_seek = Some((bookmark.position_ms / 1000) as u64);
+        self.start_playback(bookmark.track, index).await?;
+        Ok(())
+    }
+}
+
+fn format_position(ms: i64) -> String {
+    let secs = ms / 1000;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
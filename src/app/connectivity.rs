@@ -0,0 +1,53 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+
+use super::App;
+
+/// How often to check whether the server is back while offline
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+impl App {
+    /// Keep using whatever is already loaded and downloaded until the server answers again
+    pub fn enter_offline_mode(&mut self, reason: &str) {
+        log::warn!("Server unreachable, going offline: {}", reason);
+        self.offline = true;
+        self.last_ping = Some(Instant::now());
+        self.set_notification("Server unreachable, working offline");
+    }
+    pub fn exit_offline_mode(&mut self) {
+        self.offline = false;
+        self.last_ping = None;
+        self.ping_rx = None;
+    }
+    /// Ping the server in the background while offline and reload the library once it answers
+    pub fn check_connectivity(&mut self) {
+        if !self.offline {
+            return;
+        }
+        if let Some(rx) = &mut self.ping_rx {
+            match rx.try_recv() {
+                Ok(true) => {
+                    self.exit_offline_mode();
+                    self.set_notification("Server reachable again, reloading library...");
+                    self.start_background_load();
+                }
+                Ok(false) | Err(mpsc::error::TryRecvError::Disconnected) => {
+                    self.ping_rx = None;
+                    self.last_ping = Some(Instant::now());
+                }
+                Err(mpsc::error::TryRecvError::Empty) => {}
+            }
+            return;
+        }
+        if self.last_ping.is_some_and(|t| t.elapsed() < PING_INTERVAL) {
+            return;
+        }
+        let (tx, rx) = mpsc::channel(1);
+        self.ping_rx = Some(rx);
+        let client = self.subsonic_client.clone();
+        tokio::spawn(async move {
+            let _ = tx.send(client.ping().await.is_ok()).await;
+        });
+    }
+}
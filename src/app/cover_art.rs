@@ -1,12 +1,21 @@
+use std::collections::HashSet;
 use std::io::Cursor;
+use std::path::PathBuf;
 
 use anyhow::Result;
 use image::DynamicImage;
-use ratatui_image::picker::Picker;
 
-use crate::app::Track;
+use crate::app::{ConfirmAction, Track};
+use crate::config::NetworkConfig;
+use crate::network::build_blocking_client;
 
 use super::App;
+
+/// Albums either side of the selection whose thumbnails are kept loaded
+const THUMBNAIL_WINDOW: usize = 30;
+/// Thumbnails are shrunk to this size before being turned into a protocol
+const THUMBNAIL_PIXELS: u32 = 64;
+
 impl App {
     pub fn sanitize_album_name(&self, name: &str) -> String {
         name.replace(|c: char| !c.is_alphanumeric() && c != '-', "_")
@@ -21,17 +30,64 @@ impl App {
                 }
             })
     }
+    /// Name the track's cover is cached under. The album id when the server gave one, since
+    /// two albums can share a name, the album name otherwise
+    pub fn cover_cache_key(&self, track: &Track) -> String {
+        match &track.album_id {
+            Some(id) if !id.is_empty() => format!("id_{}", self.sanitize_album_name(id)),
+            _ => self.sanitize_album_name(&track.album),
+        }
+    }
+    /// The track's cover url, built from its id when the track came without one
+    pub fn cover_art_url(&self, track: &Track) -> Option<String> {
+        track
+            .cover_art
+            .clone()
+            .or_else(|| self.subsonic_client.build_cover_art_url(&track.id).ok())
+    }
+    /// Download the covers of the search results into the cover cache in the background, one
+    /// per album, when `prefetch_covers` is set. A new search drops what is left of the last one
+    pub fn prefetch_search_covers(&mut self) {
+        if let Some(task) = self.search_cover_task.take() {
+            task.abort();
+        }
+        if !self.config.search.prefetch_covers || self.offline {
+            return;
+        }
+        let mut albums = HashSet::new();
+        let covers: Vec<(String, String)> = self
+            .search_tab
+            .data
+            .iter()
+            .filter_map(|track| {
+                let album = self.cover_cache_key(track);
+                if !albums.insert(album.clone()) {
+                    return None;
+                }
+                Some((self.cover_art_url(track)?, album))
+            })
+            .collect();
+        let network = self.config.network.clone();
+        self.search_cover_task = Some(tokio::spawn(async move {
+            for (url, album) in covers {
+                if let Err(e) = fetch_and_cache_image(url, album, network.clone()).await {
+                    log::debug!("Failed to prefetch a search result cover: {}", e);
+                }
+            }
+        }));
+    }
     pub async fn load_cover_art_for_track(&mut self, track: &Track) {
         self.cover_art_protocol = None;
-        let album = self.sanitize_album_name(&track.album);
+        self.cover_art_image = None;
+        self.cover_art_blocks = None;
+        self.cover_art_failed = false;
+        let album = self.cover_cache_key(track);
 
         let url = match &track.cover_art {
             Some(url) if !url.is_empty() => url,
             _ => return,
         };
-        let mut cache_path = std::env::temp_dir();
-        cache_path.push("sonicrust");
-        cache_path.push(format!("cover_{}.jpg", album));
+        let cache_path = cover_cache_path(&album);
         let img_result = if cache_path.exists() {
             log::debug!("Using cached cover_art for {}", album);
             image::open(&cache_path)
@@ -42,62 +98,207 @@ impl App {
                 let _ = img.save(&cache_path);
             })
         };
-        match img_result {
-            Ok(img) => match Picker::from_query_stdio() {
-                Ok(picker) => {
-                    self.cover_art_protocol = Some(picker.new_resize_protocol(img));
-                }
-                Err(e) => log::debug!("Failed ot create image picker: {}", e),
-            },
-            Err(e) => eprintln!("failed to load cover art: {}", e),
-        }
-    }
-    pub async fn fetch_and_cache_image(&self, url: &str, track_album: &str) -> Result<String> {
-        log::debug!("Currently fetching cover are for album: {}", track_album,);
-        let mut path = std::env::temp_dir();
-        path.push("sonicrust");
-        std::fs::create_dir_all(&path).map_err(|e| anyhow::anyhow!(e))?;
-        path.push(format!("cover_{}.jpg", track_album));
-        if path.exists() {
-            log::debug!("Using cached cover art for {}", track_album,);
-            return Ok(path.to_string_lossy().to_string());
-        }
-        log::debug!("fetching cover art for {}", track_album);
-        let img = self
-            .fetch_cover_art(url)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to fetch cover art: {}", e))?;
-        img.save(&path).map_err(|e| anyhow::anyhow!(e))?;
-
-        Ok(path.to_string_lossy().to_string())
+        match (img_result, &self.image_picker) {
+            (Ok(img), Some(picker)) => {
+                self.cover_art_protocol = Some(picker.new_resize_protocol(img));
+            }
+            // Kept small for the block art fallback, which only needs a few pixels
+            (Ok(img), None) => {
+                self.cover_art_image = Some(img.thumbnail(THUMBNAIL_PIXELS, THUMBNAIL_PIXELS));
+            }
+            (Err(e), _) => {
+                log::debug!("failed to load cover art: {}", e);
+                self.cover_art_failed = true;
+            }
+        }
+        self.needs_redraw = true;
+    }
+    /// Load the playing track's cover again after it failed, keeping what is cached
+    pub async fn retry_cover_art(&mut self) {
+        let Some(track) = self.current_track.clone() else {
+            self.set_notification("Nothing is playing");
+            return;
+        };
+        self.load_cover_art_for_track(&track).await;
+        if self.cover_art_failed {
+            self.set_notification("Cover art still failed to load");
+        } else {
+            self.set_notification("Cover art loaded");
+        }
     }
-
     async fn fetch_cover_art(
         &self,
         url: &str,
     ) -> Result<DynamicImage, Box<dyn std::error::Error + Send + Sync>> {
-        let url = url.to_string();
-        tokio::task::spawn_blocking(move || {
-            let res = reqwest::blocking::get(&url)?;
-            if !res.status().is_success() {
-                return Err(format!("HTTP error: {}", res.status()).into());
+        fetch_image(url.to_string(), self.config.network.clone()).await
+    }
+    pub fn toggle_album_thumbnails(&mut self) {
+        if self.album_thumbnails {
+            self.album_thumbnails = false;
+            self.thumbnail_cache.clear();
+            self.thumbnail_requested.clear();
+            self.set_notification("Album thumbnails off");
+            return;
+        }
+        if !self.images_supported {
+            self.set_notification("Images are off or not supported by this terminal");
+            return;
+        }
+        self.album_thumbnails = true;
+        self.set_notification("Album thumbnails on");
+    }
+    /// Fetch thumbnails for albums near the selection and drop the ones that scrolled far away.
+    /// Failed covers are not retried until they leave the window and come back
+    pub fn load_album_thumbnails(&mut self) {
+        if !self.album_thumbnails {
+            return;
+        }
+        while let Ok((id, img)) = self.thumbnail_rx.try_recv() {
+            if let (Some(img), Some(picker)) = (img, &self.image_picker)
+                && self.thumbnail_requested.contains(&id)
+            {
+                self.thumbnail_cache
+                    .insert(id, picker.new_resize_protocol(img));
+                self.needs_redraw = true;
+            }
+        }
+        let start = self.album_tab.index.saturating_sub(THUMBNAIL_WINDOW);
+        let wanted: HashSet<String> = self
+            .album_tab
+            .data
+            .iter()
+            .skip(start)
+            .take(THUMBNAIL_WINDOW * 2 + 1)
+            .filter_map(|a| a.cover_art.clone())
+            .collect();
+        self.thumbnail_cache.retain(|id, _| wanted.contains(id));
+        self.thumbnail_requested.retain(|id| wanted.contains(id));
+        for id in wanted {
+            if self.thumbnail_requested.contains(&id) {
+                continue;
+            }
+            let Ok(url) = self.subsonic_client.build_cover_art_url(&id) else {
+                continue;
+            };
+            self.thumbnail_requested.insert(id.clone());
+            let tx = self.thumbnail_tx.clone();
+            let network = self.config.network.clone();
+            tokio::spawn(async move {
+                let img = fetch_image(url, network)
+                    .await
+                    .inspect_err(|e| log::debug!("Failed to fetch thumbnail {}: {}", id, e))
+                    .ok()
+                    .map(|img| img.thumbnail(THUMBNAIL_PIXELS, THUMBNAIL_PIXELS));
+                let _ = tx.send((id, img)).await;
+            });
+        }
+    }
+    /// Drop the playing track's cached cover and download it again
+    pub async fn reload_cover_art(&mut self) {
+        let Some(track) = self.current_track.clone() else {
+            self.set_notification("Nothing is playing");
+            return;
+        };
+        let path = cover_cache_path(&self.cover_cache_key(&track));
+        if let Err(e) = std::fs::remove_file(&path)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            self.set_notification(format!("Could not remove cached cover: {}", e));
+            return;
+        }
+        self.load_cover_art_for_track(&track).await;
+        self.set_notification("Cover art reloaded");
+    }
+    pub fn start_clear_cover_art_cache(&mut self) {
+        self.open_confirm(ConfirmAction::ClearCoverArtCache);
+    }
+    /// Remove every cached cover, then fetch the playing track's one again
+    pub async fn clear_cover_art_cache(&mut self) -> Result<()> {
+        match clear_cover_cache_files() {
+            Ok(removed) => {
+                self.set_notification(format!("Removed {} cached covers", removed));
             }
-            let bytes = res.bytes()?;
-            if bytes.is_empty() {
-                return Err("Empty response when fetching cover art".into());
+            Err(e) => {
+                self.set_notification(format!("Could not clear cover cache: {}", e));
+                return Ok(());
             }
-            let format = image::guess_format(&bytes).unwrap_or(image::ImageFormat::Jpeg);
-            let img = image::load(Cursor::new(bytes), format)?;
-            Ok(img)
-        })
-        .await?
-    }
-    pub fn _clear_cover_art_cache() -> Result<()> {
-        let mut path = std::env::temp_dir();
-        path.push("sonicrust");
-        if path.exists() {
-            std::fs::remove_dir_all(&path)?;
+        }
+        if let Some(track) = self.current_track.clone() {
+            self.load_cover_art_for_track(&track).await;
         }
         Ok(())
     }
 }
+
+/// Where the cover cached under `key` lives
+fn cover_cache_path(key: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join("sonicrust")
+        .join(format!("cover_{}.jpg", key))
+}
+
+/// Only the cover files go, offline tracks can share the directory when there is no cache dir
+fn clear_cover_cache_files() -> Result<usize> {
+    let dir = std::env::temp_dir().join("sonicrust");
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut removed = 0;
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let is_cover = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("cover_") && name.ends_with(".jpg"));
+        if is_cover && path.is_file() {
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Path of the album's cover in the temp dir, downloading it first if it isn't there yet.
+/// `track_album` is the key from `cover_cache_key`. Takes owned values so it can run on a
+/// spawned task
+pub async fn fetch_and_cache_image(
+    url: String,
+    track_album: String,
+    network: NetworkConfig,
+) -> Result<String> {
+    let path = cover_cache_path(&track_album);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!(e))?;
+    }
+    if path.exists() {
+        log::debug!("Using cached cover art for {}", track_album,);
+        return Ok(path.to_string_lossy().to_string());
+    }
+    log::debug!("fetching cover art for {}", track_album);
+    let img = fetch_image(url, network)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to fetch cover art: {}", e))?;
+    img.save(&path).map_err(|e| anyhow::anyhow!(e))?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+async fn fetch_image(
+    url: String,
+    network: NetworkConfig,
+) -> Result<DynamicImage, Box<dyn std::error::Error + Send + Sync>> {
+    tokio::task::spawn_blocking(move || {
+        let res = build_blocking_client(&network)?.get(&url).send()?;
+        if !res.status().is_success() {
+            return Err(format!("HTTP error: {}", res.status()).into());
+        }
+        let bytes = res.bytes()?;
+        if bytes.is_empty() {
+            return Err("Empty response when fetching cover art".into());
+        }
+        let format = image::guess_format(&bytes).unwrap_or(image::ImageFormat::Jpeg);
+        let img = image::load(Cursor::new(bytes), format)?;
+        Ok(img)
+    })
+    .await?
+}
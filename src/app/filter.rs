@@ -0,0 +1,164 @@
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::{ActiveSection, ActiveTab, InputMode};
+
+use super::App;
+
+/// Run `$body` with `$tab` bound to the active library tab, unless that is Search
+macro_rules! with_filterable_tab {
+    ($app:expr, |$tab:ident| $body:expr) => {
+        match $app.active_tab {
+            ActiveTab::Songs => {
+                let $tab = &mut $app.tracks_tab;
+                $body
+            }
+            ActiveTab::Artists => {
+                let $tab = &mut $app.artist_tab;
+                $body
+            }
+            ActiveTab::Albums => {
+                let $tab = &mut $app.album_tab;
+                $body
+            }
+            ActiveTab::Playlist => {
+                let $tab = &mut $app.playlist_tab;
+                $body
+            }
+            ActiveTab::Favorites => {
+                let $tab = &mut $app.favorite_tab;
+                $body
+            }
+            ActiveTab::Folders => {
+                let $tab = &mut $app.folder_tab;
+                $body
+            }
+            ActiveTab::Podcasts => {
+                let $tab = &mut $app.podcast_tab;
+                $body
+            }
+            ActiveTab::Search => {}
+        }
+    };
+}
+
+impl App {
+    /// Start narrowing the active library tab to the rows matching what is typed next
+    pub fn start_filter(&mut self) {
+        if self.active_section != ActiveSection::Others || self.active_tab == ActiveTab::Search {
+            self.set_notification("Only the library tabs can be filtered");
+            return;
+        }
+        self.input_mode = InputMode::Filter;
+        self.filter_query.clear();
+        self.clear_active_filter();
+    }
+    pub fn is_filtered(&self) -> bool {
+        match self.active_tab {
+            ActiveTab::Songs => self.tracks_tab.filter.is_some(),
+            ActiveTab::Artists => self.artist_tab.filter.is_some(),
+            ActiveTab::Albums => self.album_tab.filter.is_some(),
+            ActiveTab::Playlist => self.playlist_tab.filter.is_some(),
+            ActiveTab::Favorites => self.favorite_tab.filter.is_some(),
+            ActiveTab::Folders => self.folder_tab.filter.is_some(),
+            ActiveTab::Podcasts => self.podcast_tab.filter.is_some(),
+            ActiveTab::Search => false,
+        }
+    }
+    /// Show the active tab's whole list again
+    pub fn clear_active_filter(&mut self) {
+        self.filter_query.clear();
+        if self.active_tab == ActiveTab::Songs {
+            self.genre_filter.clear();
+        }
+        with_filterable_tab!(self, |tab| {
+            tab.clear_filter();
+            if !tab.data.is_empty() {
+                tab.current();
+            }
+        });
+    }
+    /// Drop the filters of every tab, for when a list is switched away from or rebuilt
+    pub fn clear_filters(&mut self) {
+        self.filter_query.clear();
+        self.genre_filter.clear();
+        self.tracks_tab.clear_filter();
+        self.artist_tab.clear_filter();
+        self.album_tab.clear_filter();
+        self.playlist_tab.clear_filter();
+        self.favorite_tab.clear_filter();
+        self.folder_tab.clear_filter();
+        self.podcast_tab.clear_filter();
+    }
+    fn refilter(&mut self) {
+        if self.filter_query.is_empty() {
+            self.clear_active_filter();
+            return;
+        }
+        // Match against the whole list, not what the previous query left
+        with_filterable_tab!(self, |tab| tab.clear_filter());
+        let positions = self.list_matches(&self.filter_query);
+        with_filterable_tab!(self, |tab| {
+            tab.filter_to(positions);
+            if tab.data.is_empty() {
+                tab.state.select(None);
+            } else {
+                tab.select(0);
+            }
+        });
+    }
+    /// Filter the Songs tab again after its rows changed underneath, e.g. while the library
+    /// loads, keeping the selected song selected when it still matches
+    pub fn reapply_song_filter(&mut self) {
+        if self.active_tab != ActiveTab::Songs || self.filter_query.is_empty() {
+            return;
+        }
+        let selected_id = self.tracks_tab.get().map(|t| t.id.clone());
+        self.refilter();
+        if let Some(idx) =
+            selected_id.and_then(|id| self.tracks_tab.data.iter().position(|t| t.id == id))
+        {
+            self.tracks_tab.select(idx);
+        }
+    }
+    /// Shown rows and the length of the whole list while filtering
+    pub fn filter_counts(&self) -> Option<(usize, usize)> {
+        fn counts<T>(tab: &crate::app::TabSelection<T>) -> Option<(usize, usize)> {
+            tab.filter.as_ref().map(|f| (tab.data.len(), f.full.len()))
+        }
+        match self.active_tab {
+            ActiveTab::Songs => counts(&self.tracks_tab),
+            ActiveTab::Artists => counts(&self.artist_tab),
+            ActiveTab::Albums => counts(&self.album_tab),
+            ActiveTab::Playlist => counts(&self.playlist_tab),
+            ActiveTab::Favorites => counts(&self.favorite_tab),
+            ActiveTab::Folders => counts(&self.folder_tab),
+            ActiveTab::Podcasts => counts(&self.podcast_tab),
+            ActiveTab::Search => None,
+        }
+    }
+    /// Enter keeps the filtered list to browse and play from, Esc puts the whole list back
+    pub async fn handle_filter_input(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.clear_active_filter();
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.filter_query.pop();
+                self.refilter();
+            }
+            KeyCode::Down => self.next_item_in_tab(),
+            KeyCode::Up => self.previous_item_in_tab(),
+            KeyCode::Char(c) => {
+                self.filter_query.push(c);
+                self.refilter();
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+}
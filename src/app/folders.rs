@@ -0,0 +1,109 @@
+use anyhow::Result;
+
+use crate::app::{ActiveSection, ActiveTab, FolderEntry, FolderLevel, Track};
+
+use super::App;
+impl App {
+    /// Load the top of the folder tree the first time the Folders tab is opened
+    pub async fn open_folders(&mut self) {
+        if self.folder_path.is_empty() && self.folder_tab.data.is_empty() {
+            self.load_folder(None, 0).await;
+        }
+    }
+    /// List a directory, or the music folders when `id` is None. Returns whether it loaded
+    async fn load_folder(&mut self, id: Option<&str>, select: usize) -> bool {
+        let entries = match id {
+            Some(id) => self.subsonic_client.get_music_directory(id).await,
+            None => self.subsonic_client.get_indexes().await,
+        };
+        match entries {
+            Ok(entries) => {
+                self.folder_tab.clear_filter();
+                self.folder_tab.data = entries;
+                self.folder_tab.index = select.min(self.folder_tab.len().saturating_sub(1));
+                if self.active_tab == ActiveTab::Folders && !self.folder_tab.data.is_empty() {
+                    self.folder_tab.current();
+                } else {
+                    self.folder_tab.clear();
+                }
+                true
+            }
+            Err(e) => {
+                self.set_notification(format!("Failed to load folder: {}", e));
+                false
+            }
+        }
+    }
+    /// Enter the selected directory, or play the directory's songs from the selected one
+    pub async fn activate_folder_entry(&mut self) -> Result<()> {
+        let index = self.folder_tab.index;
+        match self.folder_tab.get().cloned() {
+            Some(FolderEntry::Directory { id, name }) => {
+                self.enter_directory(id, name, index).await;
+            }
+            Some(FolderEntry::Song(track)) => {
+                let songs = self.folder_songs();
+                let start = songs.iter().position(|t| t.id == track.id).unwrap_or(0);
+                self.queue_tab.data = songs;
+                self.queue_tab.index = start;
+                self.disable_shuffle();
+                self.start_playback(*track, start).await?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+    async fn enter_directory(&mut self, id: String, name: String, parent_index: usize) {
+        if self.load_folder(Some(&id), 0).await {
+            self.folder_path.push(FolderLevel {
+                id,
+                name,
+                parent_index,
+            });
+        }
+    }
+    /// Go back up to the parent directory, keeping the folder we came from selected
+    pub async fn leave_folder(&mut self) {
+        if self.active_section != ActiveSection::Others || self.active_tab != ActiveTab::Folders {
+            return;
+        }
+        let Some(level) = self.folder_path.pop() else {
+            return;
+        };
+        let parent = self.folder_path.last().map(|l| l.id.clone());
+        if !self
+            .load_folder(parent.as_deref(), level.parent_index)
+            .await
+        {
+            self.folder_path.push(level);
+        }
+    }
+    /// The songs of the listed directory, subdirectories left out
+    pub fn folder_songs(&self) -> Vec<Track> {
+        self.folder_tab
+            .data
+            .iter()
+            .filter_map(|e| match e {
+                FolderEntry::Song(track) => Some(track.as_ref().clone()),
+                FolderEntry::Directory { .. } => None,
+            })
+            .collect()
+    }
+    /// Songs behind the selected entry, for a directory only the ones directly inside it
+    pub async fn selected_folder_tracks(&mut self) -> Result<Vec<Track>> {
+        match self.folder_tab.get().cloned() {
+            Some(FolderEntry::Directory { id, .. }) => Ok(self
+                .subsonic_client
+                .get_music_directory(&id)
+                .await?
+                .into_iter()
+                .filter_map(|e| match e {
+                    FolderEntry::Song(track) => Some(*track),
+                    FolderEntry::Directory { .. } => None,
+                })
+                .collect()),
+            Some(FolderEntry::Song(track)) => Ok(vec![*track]),
+            None => Ok(Vec::new()),
+        }
+    }
+}
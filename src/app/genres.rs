@@ -0,0 +1,119 @@
+use crate::app::{ActiveSection, ActiveTab, GenreMatch, PickerKind};
+
+use super::App;
+
+impl App {
+    /// List the library's genres to add to or take out of the Songs filter
+    pub fn open_genre_picker(&mut self) {
+        if self.active_section != ActiveSection::Others || self.active_tab != ActiveTab::Songs {
+            self.set_notification("Genres filter the Songs tab");
+            return;
+        }
+        let mut genres: Vec<String> = self
+            .tracks_tab
+            .all_rows()
+            .iter()
+            .flat_map(|t| t.genres.iter().cloned())
+            .collect();
+        genres.sort_by_key(|g| g.to_lowercase());
+        // Servers spell the same genre differently, matching ignores the case as well
+        genres.dedup_by_key(|g| g.to_lowercase());
+        if genres.is_empty() {
+            self.set_notification("The loaded songs have no genres");
+            return;
+        }
+        let items = self.genre_picker_items(&genres);
+        let title = self.genre_picker_title();
+        self.open_picker(PickerKind::Genre { genres }, title, items);
+    }
+    fn genre_picker_items(&self, genres: &[String]) -> Vec<String> {
+        genres
+            .iter()
+            .map(|genre| {
+                let chosen = self.genre_filter.contains(genre);
+                format!("[{}] {}", if chosen { "x" } else { " " }, genre)
+            })
+            .collect()
+    }
+    fn genre_picker_title(&self) -> String {
+        format!(
+            "Genres, songs matching {} (Space toggles, Tab any/all, Backspace clears)",
+            self.genre_match.label()
+        )
+    }
+    /// Show the picker's marks and title as the filter is now, keeping the cursor where it is
+    pub fn refresh_genre_picker(&mut self) {
+        let title = self.genre_picker_title();
+        let items = match self.picker.as_ref().map(|p| &p.kind) {
+            Some(PickerKind::Genre { genres }) => self.genre_picker_items(genres),
+            _ => return,
+        };
+        if let Some(picker) = self.picker.as_mut() {
+            picker.list.data = items;
+            picker.title = title;
+        }
+    }
+    /// Add `genre` to the filter, or take it out if it is in already
+    pub fn toggle_genre(&mut self, genre: &str) {
+        match self.genre_filter.iter().position(|g| g == genre) {
+            Some(pos) => {
+                self.genre_filter.remove(pos);
+            }
+            None => self.genre_filter.push(genre.to_string()),
+        }
+        self.apply_genre_filter();
+    }
+    /// Switch between songs having any of the chosen genres and songs having all of them
+    pub fn toggle_genre_match(&mut self) {
+        self.genre_match = self.genre_match.toggle();
+        self.apply_genre_filter();
+    }
+    pub fn clear_genre_filter(&mut self) {
+        self.genre_filter.clear();
+        self.apply_genre_filter();
+    }
+    /// Narrow the Songs tab to the chosen genres, or show every song when none are chosen
+    pub fn apply_genre_filter(&mut self) {
+        self.tracks_tab.clear_filter();
+        let selected_id = self
+            .tracks_tab
+            .data
+            .get(self.tracks_tab.index)
+            .map(|t| t.id.clone());
+        if self.genre_filter.is_empty() {
+            if !self.tracks_tab.data.is_empty() {
+                self.tracks_tab.current();
+            }
+            return;
+        }
+        let positions = self
+            .tracks_tab
+            .data
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| self.genre_match.matches(&self.genre_filter, &t.genres))
+            .map(|(i, _)| i)
+            .collect();
+        self.tracks_tab.filter_to(positions);
+        if self.tracks_tab.data.is_empty() {
+            self.tracks_tab.clear();
+            return;
+        }
+        // Stay on the selected song while it still matches, e.g. when another page loads
+        let idx = selected_id
+            .and_then(|id| self.tracks_tab.data.iter().position(|t| t.id == id))
+            .unwrap_or(0);
+        self.tracks_tab.select(idx);
+    }
+    /// The chosen genres as the Songs title shows them, e.g. "Rock or Jazz"
+    pub fn genre_filter_label(&self) -> Option<String> {
+        if self.genre_filter.is_empty() {
+            return None;
+        }
+        let joiner = match self.genre_match {
+            GenreMatch::Any => " or ",
+            GenreMatch::All => " and ",
+        };
+        Some(self.genre_filter.join(joiner))
+    }
+}
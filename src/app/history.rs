@@ -0,0 +1,52 @@
+use crate::app::{PickerKind, Track};
+
+use super::App;
+impl App {
+    /// Remember a track that started playing, saving the list when `save_history` is set
+    pub fn push_history(&mut self, track: &Track) {
+        // Cover urls carry the credentials, keep them out of history.json
+        let track = Track {
+            cover_art: None,
+            ..track.clone()
+        };
+        self.history.push(&self.config.active_server, track);
+        if self.config.playback.save_history
+            && let Err(e) = self.history.save()
+        {
+            log::warn!("Failed to save history: {}", e);
+        }
+    }
+    /// List recently played tracks, Enter queues the chosen one again
+    pub fn open_history(&mut self) {
+        let entries: Vec<_> = self
+            .history
+            .for_server(&self.config.active_server)
+            .cloned()
+            .collect();
+        if entries.is_empty() {
+            self.set_notification("Nothing played yet");
+            return;
+        }
+        let items = entries
+            .iter()
+            .map(|e| {
+                format!(
+                    "{:>8}  {} - {}",
+                    e.age_label(),
+                    e.track.title,
+                    e.track.artist
+                )
+            })
+            .collect();
+        let tracks = entries.into_iter().map(|e| e.track).collect();
+        self.open_picker(PickerKind::History { tracks }, "Recently played", items);
+    }
+    pub fn requeue_from_history(&mut self, mut track: Track) {
+        // History entries are kept without their cover url, see push_history
+        track.cover_art = self.cover_art_url(&track);
+        let title = track.title.clone();
+        if self.append_to_queue(vec![track]) > 0 {
+            self.set_notification(format!("Added '{}' to the queue", title));
+        }
+    }
+}
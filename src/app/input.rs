@@ -0,0 +1,195 @@
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::{ConfirmAction, InputMode, Picker, PickerKind, PromptKind, TabSelection};
+
+use super::App;
+impl App {
+    pub fn open_prompt(&mut self, kind: PromptKind) {
+        self.prompt = Some(kind);
+        self.prompt_input.clear();
+        self.input_mode = InputMode::Prompt;
+    }
+    pub fn close_prompt(&mut self) {
+        self.prompt = None;
+        self.prompt_input.clear();
+        self.input_mode = InputMode::Normal;
+    }
+    pub async fn handle_prompt_input(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.close_prompt();
+            }
+            KeyCode::Enter => {
+                let input = self.prompt_input.trim().to_string();
+                if let Some(kind) = self.prompt.clone() {
+                    if input.is_empty() {
+                        // keep the prompt open so the user can fix the input
+                        self.set_notification(format!("{} cannot be empty", kind.label()));
+                        return Ok(false);
+                    }
+                    self.close_prompt();
+                    self.submit_prompt(kind, input).await?;
+                }
+            }
+            KeyCode::Backspace => {
+                self.prompt_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.prompt_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+    pub fn open_picker(&mut self, kind: PickerKind, title: impl Into<String>, items: Vec<String>) {
+        let mut list = TabSelection::new();
+        list.data = items;
+        if !list.data.is_empty() {
+            list.select(0);
+        }
+        self.picker = Some(Picker {
+            kind,
+            title: title.into(),
+            list,
+        });
+        self.input_mode = InputMode::Picker;
+    }
+    pub fn close_picker(&mut self) {
+        self.picker = None;
+        self.input_mode = InputMode::Normal;
+    }
+    pub async fn handle_picker_input(&mut self, key: KeyEvent) -> Result<bool> {
+        let Some(picker) = self.picker.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return Ok(false);
+        };
+        let len = picker.list.len();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.close_picker();
+            }
+            KeyCode::Char('j') | KeyCode::Down if len > 0 => {
+                picker.list.select((picker.list.index + 1) % len);
+            }
+            KeyCode::Char('k') | KeyCode::Up if len > 0 => {
+                picker.list.select((picker.list.index + len - 1) % len);
+            }
+            // The genre picker stays open to choose several
+            KeyCode::Char(' ') if matches!(picker.kind, PickerKind::Genre { .. }) => {
+                if let PickerKind::Genre { genres } = &picker.kind
+                    && let Some(genre) = genres.get(picker.list.index).cloned()
+                {
+                    self.toggle_genre(&genre);
+                    self.refresh_genre_picker();
+                }
+            }
+            KeyCode::Tab if matches!(picker.kind, PickerKind::Genre { .. }) => {
+                self.toggle_genre_match();
+                self.refresh_genre_picker();
+            }
+            KeyCode::Backspace if matches!(picker.kind, PickerKind::Genre { .. }) => {
+                self.clear_genre_filter();
+                self.refresh_genre_picker();
+            }
+            KeyCode::Enter => {
+                if let Some(picker) = self.picker.take() {
+                    self.close_picker();
+                    if picker.list.index < picker.list.len() {
+                        self.submit_picker(picker.kind, picker.list.index).await?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+    async fn submit_picker(&mut self, kind: PickerKind, index: usize) -> Result<()> {
+        match kind {
+            PickerKind::AddToPlaylist {
+                track_ids,
+                playlist_ids,
+            } => {
+                if let Some(playlist_id) = playlist_ids.get(index) {
+                    self.add_to_playlist(playlist_id, &track_ids).await?;
+                }
+                Ok(())
+            }
+            PickerKind::SwitchServer { names } => {
+                if let Some(name) = names.get(index) {
+                    self.switch_server(name).await?;
+                }
+                Ok(())
+            }
+            PickerKind::ResumeBookmark { bookmarks } => {
+                if let Some(bookmark) = bookmarks.get(index) {
+                    self.resume_bookmark(bookmark.clone()).await?;
+                }
+                Ok(())
+            }
+            PickerKind::OutputDevice { names } => {
+                if let Some(name) = names.get(index) {
+                    self.set_output_device(name.clone()).await;
+                }
+                Ok(())
+            }
+            PickerKind::History { mut tracks } => {
+                if index < tracks.len() {
+                    self.requeue_from_history(tracks.swap_remove(index));
+                }
+                Ok(())
+            }
+            PickerKind::RadioStation { mut stations } => {
+                if index < stations.len() {
+                    self.play_radio_station(stations.swap_remove(index)).await?;
+                }
+                Ok(())
+            }
+            PickerKind::Genre { genres } => {
+                if let Some(genre) = genres.get(index) {
+                    self.toggle_genre(genre);
+                }
+                Ok(())
+            }
+        }
+    }
+    pub fn open_confirm(&mut self, action: ConfirmAction) {
+        self.confirm = Some(action);
+        self.input_mode = InputMode::Confirm;
+    }
+    pub async fn handle_confirm_input(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                // Take the action out before running it so a dismissed or repeated
+                // confirmation can never fire it a second time
+                let action = self.confirm.take();
+                self.input_mode = InputMode::Normal;
+                if let Some(action) = action {
+                    self.run_confirmed(action).await?;
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc | KeyCode::Char('q') => {
+                self.confirm = None;
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+    async fn run_confirmed(&mut self, action: ConfirmAction) -> Result<()> {
+        match action {
+            ConfirmAction::DeletePlaylist { id, name } => self.delete_playlist(&id, &name).await,
+            ConfirmAction::ClearCoverArtCache => self.clear_cover_art_cache().await,
+        }
+    }
+    async fn submit_prompt(&mut self, kind: PromptKind, input: String) -> Result<()> {
+        match kind {
+            PromptKind::PlaylistName => self.save_queue_as_playlist(&input).await,
+            PromptKind::SleepTimer => {
+                self.set_sleep_timer(&input).await;
+                Ok(())
+            }
+            PromptKind::Volume => self.set_volume_percent(&input).await,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use crate::logging;
+
+use super::App;
+impl App {
+    /// Switch debug output in the log file on or off, for chasing a problem as it happens
+    pub fn toggle_verbose_logging(&mut self) {
+        self.verbose_logging = !self.verbose_logging;
+        logging::set_verbose(self.verbose_logging);
+        if self.verbose_logging {
+            log::info!("Verbose logging on");
+            let path = logging::log_path();
+            self.set_notification(format!("Verbose logging to {}", path.display()));
+        } else {
+            self.set_notification("Verbose logging off");
+        }
+    }
+}
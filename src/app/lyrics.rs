@@ -0,0 +1,69 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use tokio::sync::mpsc;
+
+use super::App;
+impl App {
+    pub fn toggle_lyrics_view(&mut self) {
+        self.lyrics_view = !self.lyrics_view;
+        self.load_lyrics();
+    }
+    /// Keys of the lyrics view, returns false for the ones it leaves to the normal bindings so
+    /// playback can still be controlled from it
+    pub fn handle_lyrics_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('l') => self.lyrics_view = false,
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.lyrics_scroll = self.lyrics_scroll.saturating_add(1)
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.lyrics_scroll = self.lyrics_scroll.saturating_sub(1)
+            }
+            _ => return false,
+        }
+        true
+    }
+    /// Keep the lyrics in step with the playing track while the view is open
+    pub fn load_lyrics(&mut self) {
+        if let Some(rx) = &mut self.lyrics_rx {
+            match rx.try_recv() {
+                Ok(lyrics) => {
+                    self.lyrics = lyrics;
+                    self.lyrics_rx = None;
+                    self.needs_redraw = true;
+                }
+                Err(mpsc::error::TryRecvError::Disconnected) => self.lyrics_rx = None,
+                Err(mpsc::error::TryRecvError::Empty) => {}
+            }
+        }
+        if !self.lyrics_view {
+            return;
+        }
+        let Some(track) = self.current_track.clone() else {
+            return;
+        };
+        if self.lyrics_track_id.as_ref() == Some(&track.id) {
+            return;
+        }
+        self.lyrics = None;
+        self.lyrics_scroll = 0;
+        if self.offline {
+            // Fetched once the server is back
+            self.lyrics_rx = None;
+            return;
+        }
+        self.lyrics_track_id = Some(track.id.clone());
+        // Replacing the receiver drops whatever the previous track's fetch still sends
+        let (tx, rx) = mpsc::channel(1);
+        self.lyrics_rx = Some(rx);
+        let client = self.subsonic_client.clone();
+        tokio::spawn(async move {
+            let lyrics = client
+                .get_lyrics(&track)
+                .await
+                .inspect_err(|e| log::debug!("Failed to fetch lyrics: {}", e))
+                .ok()
+                .flatten();
+            let _ = tx.send(lyrics).await;
+        });
+    }
+}
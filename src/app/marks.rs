@@ -0,0 +1,82 @@
+use anyhow::Result;
+
+use crate::app::{ActiveSection, ActiveTab, TabSelection, Track};
+
+use super::App;
+impl App {
+    /// The track list that can hold marks, if one is showing
+    fn markable_tab(&mut self) -> Option<&mut TabSelection<Track>> {
+        if self.active_section != ActiveSection::Others {
+            return None;
+        }
+        match self.active_tab {
+            ActiveTab::Songs => Some(&mut self.tracks_tab),
+            ActiveTab::Search => Some(&mut self.search_tab),
+            ActiveTab::Favorites => Some(&mut self.favorite_tab),
+            _ => None,
+        }
+    }
+    pub fn can_mark(&mut self) -> bool {
+        self.markable_tab().is_some()
+    }
+    /// Start marking tracks with Space, or stop and drop the marks
+    pub fn toggle_visual_mode(&mut self) {
+        if self.visual_mode {
+            self.clear_marks();
+            self.set_notification("Selection cleared");
+        } else if self.can_mark() {
+            self.visual_mode = true;
+            self.set_notification("Space marks tracks, Enter plays them, a queues them, v clears");
+        } else {
+            self.set_notification("Tracks can be marked in the Songs, Search and Favorites tabs");
+        }
+    }
+    pub fn toggle_mark(&mut self) {
+        if let Some(tab) = self.markable_tab()
+            && tab.index < tab.len()
+            && !tab.marked.remove(&tab.index)
+        {
+            tab.marked.insert(tab.index);
+        }
+    }
+    pub fn clear_marks(&mut self) {
+        self.visual_mode = false;
+        self.tracks_tab.marked.clear();
+        self.search_tab.marked.clear();
+        self.favorite_tab.marked.clear();
+    }
+    pub fn has_marks(&mut self) -> bool {
+        self.markable_tab()
+            .is_some_and(|tab| !tab.marked.is_empty())
+    }
+    /// Marked tracks in list order
+    fn marked_tracks(&mut self) -> Vec<Track> {
+        let Some(tab) = self.markable_tab() else {
+            return Vec::new();
+        };
+        let mut indices: Vec<usize> = tab.marked.iter().copied().collect();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .filter_map(|i| tab.data.get(i).cloned())
+            .collect()
+    }
+    pub fn queue_marked(&mut self) {
+        let tracks = self.marked_tracks();
+        let added = self.append_to_queue(tracks);
+        self.clear_marks();
+        self.set_notification(format!("Added {} track(s) to the queue", added));
+    }
+    /// Replace the queue with the marked tracks and play the first
+    pub async fn play_marked(&mut self) -> Result<()> {
+        let tracks = self.marked_tracks();
+        self.clear_marks();
+        let Some(first) = tracks.first().cloned() else {
+            return Ok(());
+        };
+        self.queue_tab.data = tracks;
+        self.queue_tab.index = 0;
+        self.disable_shuffle();
+        self.start_playback(first, 0).await
+    }
+}
@@ -1,8 +1,9 @@
 use anyhow::Result;
-use mpris_server::{PlaybackStatus, Property};
-use notify_rust::{Hint, Notification};
+use mpris_server::{PlaybackStatus, Property, TrackId, TrackListProperty, TrackListSignal};
+use notify_rust::{Hint, Notification, Timeout};
 
-use crate::app::Track;
+use crate::app::{ShuffleMode, Track, cover_art::fetch_and_cache_image};
+use crate::mpris_handler::track_object_path;
 
 use super::App;
 impl App {
@@ -15,8 +16,7 @@ impl App {
             PlaybackStatus::Stopped
         };
 
-        let can_next = self.playing_index < self.queue_tab.len().saturating_sub(1);
-        let can_prev = self.playing_index > 0;
+        let (can_next, can_prev) = queue_navigation(self.playing_index, self.queue_tab.len());
         let current_pos = self.player.lock().await.get_position();
 
         if let Ok(mut state) = self.shared_state.write() {
@@ -38,29 +38,144 @@ impl App {
             .await;
     }
 
-    pub async fn notify_now_playing(&mut self, track: &Track) -> Result<()> {
+    /// Show the desktop notification on a task of its own, so a slow cover download or
+    /// notification daemon never holds up the track that is starting
+    pub fn notify_now_playing(&self, track: &Track) {
+        let settings = self.config.notifications.clone();
+        if !settings.enabled {
+            return;
+        }
         let mut notif = Notification::new()
             .appname("Sonicrust")
             .summary("Now playing")
             .body(format!("{} - {}", track.title, track.artist).as_str())
             .finalize();
-        if let Some(url) = &track.cover_art
-            && !url.is_empty()
-        {
-            let album = self.sanitize_album_name(&track.album);
-
-            match self.fetch_and_cache_image(url, &album).await {
-                Ok(path) => {
-                    notif.hint(Hint::ImagePath(path));
-                }
-                Err(e) => {
-                    log::debug!("Could not load cover art for notification: {}", e);
+        if let Some(ms) = settings.timeout_ms {
+            notif.timeout(Timeout::Milliseconds(ms));
+        }
+        let cover = track
+            .cover_art
+            .clone()
+            .filter(|url| settings.show_cover && !url.is_empty());
+        let album = self.cover_cache_key(track);
+        let network = self.config.network.clone();
+        tokio::spawn(async move {
+            if let Some(url) = cover {
+                match fetch_and_cache_image(url, album, network).await {
+                    Ok(path) => {
+                        notif.hint(Hint::ImagePath(path));
+                    }
+                    Err(e) => {
+                        log::debug!("Could not load cover art for notification: {}", e);
+                    }
                 }
             }
+            let _ = tokio::task::spawn_blocking(move || notif.show()).await;
+        });
+    }
+    /// Publish the queue as the MPRIS TrackList whenever it changed
+    pub async fn sync_track_list(&mut self) {
+        let tracks = {
+            let Ok(mut state) = self.shared_state.write() else {
+                return;
+            };
+            let unchanged = state.track_list.len() == self.queue_tab.len()
+                && state
+                    .track_list
+                    .iter()
+                    .zip(&self.queue_tab.data)
+                    .all(|(a, b)| a.id == b.id);
+            if unchanged {
+                return;
+            }
+            state.track_list = self.queue_tab.data.clone();
+            state
+                .track_list
+                .iter()
+                .map(|t| track_object_path(&t.id))
+                .collect()
+        };
+        let current_track = self
+            .current_track
+            .as_ref()
+            .map_or(TrackId::NO_TRACK, |t| track_object_path(&t.id));
+        let _ = self
+            .mpris
+            .track_list_emit(TrackListSignal::TrackListReplaced {
+                tracks,
+                current_track,
+            })
+            .await;
+        let _ = self
+            .mpris
+            .track_list_properties_changed([TrackListProperty::Tracks])
+            .await;
+    }
+    /// Play the queue entry a desktop widget picked
+    pub async fn mpris_go_to(&mut self, id: &TrackId) -> Result<()> {
+        let Some(index) = queue_position(&self.queue_tab.data, id, self.playing_index) else {
+            return Ok(());
+        };
+        let track = self.queue_tab.data[index].clone();
+        self.queue_tab.index = index;
+        self.start_playback(track, index).await
+    }
+    pub fn mpris_remove_track(&mut self, id: &TrackId) {
+        let Some(index) = queue_position(&self.queue_tab.data, id, self.playing_index) else {
+            return;
+        };
+        if index == self.playing_index && self.current_track.is_some() {
+            self.set_notification("The playing track can't be removed from the queue");
+            return;
+        }
+        self.queue_tab.data.remove(index);
+        if index < self.playing_index {
+            self.playing_index -= 1;
+        }
+        self.queue_tab.index = self
+            .queue_tab
+            .index
+            .min(self.queue_tab.len().saturating_sub(1));
+        self.queue_edited();
+    }
+    /// Queue a song after `after`, or first for the NoTrack id
+    pub async fn mpris_add_track(
+        &mut self,
+        song_id: &str,
+        after: &TrackId,
+        play: bool,
+    ) -> Result<()> {
+        let track = match self.subsonic_client.get_song(song_id).await {
+            Ok(track) => track,
+            Err(e) => {
+                self.set_notification(format!("Could not add track {}: {}", song_id, e));
+                return Ok(());
+            }
+        };
+        let index = if *after == TrackId::NO_TRACK {
+            0
+        } else {
+            match queue_position(&self.queue_tab.data, after, self.playing_index) {
+                Some(i) => i + 1,
+                None => self.queue_tab.len(),
+            }
+        };
+        self.queue_tab.data.insert(index, track.clone());
+        if index <= self.playing_index && self.current_track.is_some() {
+            self.playing_index += 1;
+        }
+        self.queue_edited();
+        if play {
+            self.start_playback(track, index).await?;
         }
-        let _ = notif.show();
         Ok(())
     }
+    /// Shuffle order holds queue positions, so it is rebuilt after the queue shifted
+    pub fn queue_edited(&mut self) {
+        if self.shuffle_mode == ShuffleMode::On {
+            self.enable_shuffle();
+        }
+    }
     pub async fn update_mpris_position(&mut self) -> Result<()> {
         if self.is_playing {
             let current_pos = self.player.lock().await.get_position();
@@ -71,3 +186,63 @@ impl App {
         Ok(())
     }
 }
+
+/// Whether next/previous are available, going by the playing track rather than the cursor
+fn queue_navigation(playing_index: usize, queue_len: usize) -> (bool, bool) {
+    let can_next = playing_index + 1 < queue_len;
+    let can_prev = playing_index > 0 && queue_len > 0;
+    (can_next, can_prev)
+}
+
+/// Where a TrackList id sits in the queue. A track queued twice has one id, so the copy
+/// nearest after `playing_index` wins, being the one a client most likely means
+fn queue_position(queue: &[Track], id: &TrackId, playing_index: usize) -> Option<usize> {
+    let matching: Vec<usize> = queue
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| &track_object_path(&t.id) == id)
+        .map(|(i, _)| i)
+        .collect();
+    matching
+        .iter()
+        .find(|&&i| i >= playing_index)
+        .or_else(|| matching.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str) -> Track {
+        Track {
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_queue_position_prefers_the_copy_after_the_playing_track() {
+        let queue = [track("a"), track("b"), track("a"), track("c")];
+        let id = track_object_path("a");
+        assert_eq!(queue_position(&queue, &id, 0), Some(0));
+        assert_eq!(queue_position(&queue, &id, 1), Some(2));
+        assert_eq!(queue_position(&queue, &id, 3), Some(0));
+        assert_eq!(queue_position(&queue, &track_object_path("d"), 0), None);
+    }
+
+    #[test]
+    fn test_queue_navigation_at_the_ends() {
+        assert_eq!(queue_navigation(0, 3), (true, false));
+        assert_eq!(queue_navigation(1, 3), (true, true));
+        assert_eq!(queue_navigation(2, 3), (false, true));
+    }
+
+    #[test]
+    fn test_queue_navigation_single_and_empty_queue() {
+        assert_eq!(queue_navigation(0, 1), (false, false));
+        assert_eq!(queue_navigation(0, 0), (false, false));
+        // The queue was cleared while the index still points past it
+        assert_eq!(queue_navigation(4, 0), (false, false));
+    }
+}
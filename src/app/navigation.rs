@@ -1,18 +1,16 @@
-use crate::app::{ActiveSection, ActiveTab, InputMode};
+use std::collections::HashMap;
+
+use crate::{
+    app::{ActiveSection, ActiveTab, AlbumSort, InputMode, SongSort, Track},
+    subsonic::AlbumListType,
+};
 
 use super::App;
 macro_rules! navigate_list {
-    ($state:expr, $forward:expr) => {
+    ($state:expr, $forward:expr, $wrap:expr) => {
         let len = $state.data.len();
         if len > 0 {
-            let i = if $forward {
-                $state.state.selected().map_or(0, |s| (s + 1) % len)
-            } else {
-                $state
-                    .state
-                    .selected()
-                    .map_or(len - 1, |s| if s == 0 { len - 1 } else { s - 1 })
-            };
+            let i = step_index($state.state.selected(), len, $forward, $wrap);
             $state.index = i;
             $state.state.select(Some(i));
         } else {
@@ -21,8 +19,26 @@ macro_rules! navigate_list {
     };
 }
 
+/// Index one row up or down from `selected` in a list of `len` rows, which must not be empty.
+/// Past either end it wraps around when `wrap` is set and stays put otherwise
+fn step_index(selected: Option<usize>, len: usize, forward: bool, wrap: bool) -> usize {
+    match (selected, forward) {
+        (None, true) => 0,
+        (None, false) => len - 1,
+        (Some(s), true) if s + 1 < len => s + 1,
+        (Some(_), true) if wrap => 0,
+        (Some(_), true) => len - 1,
+        (Some(0), false) if wrap => len - 1,
+        (Some(s), false) => s.saturating_sub(1),
+    }
+}
+
 impl App {
     pub fn select_tab(&mut self, tab: ActiveTab) {
+        if tab != self.active_tab {
+            self.clear_marks();
+            self.clear_filters();
+        }
         match self.active_tab {
             // ActiveTab::Queue => self.queue_state.select(None),
             ActiveTab::Songs => self.tracks_tab.clear(),
@@ -30,6 +46,8 @@ impl App {
             ActiveTab::Artists => self.artist_tab.clear(),
             ActiveTab::Albums => self.album_tab.clear(),
             ActiveTab::Favorites => self.favorite_tab.clear(),
+            ActiveTab::Folders => self.folder_tab.clear(),
+            ActiveTab::Podcasts => self.podcast_tab.clear(),
             ActiveTab::Search => {
                 self.search_tab.clear();
                 self.input_mode = InputMode::Normal;
@@ -58,123 +76,465 @@ impl App {
             ActiveTab::Search if !self.search_tab.data.is_empty() => {
                 self.search_tab.current();
             }
+            ActiveTab::Folders if !self.folder_tab.data.is_empty() => {
+                self.folder_tab.current();
+            }
+            ActiveTab::Podcasts if !self.podcast_tab.data.is_empty() => {
+                self.podcast_tab.current();
+            }
             _ => {}
         }
     }
     pub fn next_tab(&mut self) {
-        self.active_section = match self.active_section {
-            ActiveSection::Queue => ActiveSection::Others,
-            ActiveSection::Others => ActiveSection::Queue,
-        };
         match self.active_section {
-            ActiveSection::Queue => {
-                if !self.queue_tab.data.is_empty() {
-                    self.queue_tab.current();
-                }
+            ActiveSection::Queue => self.focus_library(),
+            ActiveSection::Others => self.focus_queue(),
+        }
+    }
+    /// Move the focus to the queue, wherever it is now
+    pub fn focus_queue(&mut self) {
+        self.active_section = ActiveSection::Queue;
+        if !self.queue_tab.data.is_empty() {
+            self.queue_tab.current();
+        }
+    }
+    /// Move the focus to the library tabs, keeping the tab that is shown
+    pub fn focus_library(&mut self) {
+        self.active_section = ActiveSection::Others;
+        match self.active_tab {
+            ActiveTab::Songs => {
+                self.artist_tab.select(self.tracks_tab.index);
             }
-            ActiveSection::Others => match self.active_tab {
-                ActiveTab::Songs => {
-                    self.artist_tab.select(self.tracks_tab.index);
-                }
-                ActiveTab::Favorites => {
-                    self.favorite_tab.current();
-                }
-                ActiveTab::Playlist => {
-                    self.playlist_tab.current();
-                }
-                ActiveTab::Artists => {
-                    self.album_tab.select(self.artist_tab.index);
-                }
-                ActiveTab::Albums => {
-                    self.tracks_tab.select(self.album_tab.index);
-                }
-                ActiveTab::Search => {
-                    self.tracks_tab.select(self.search_tab.index);
-                }
-            },
-        };
+            ActiveTab::Favorites => {
+                self.favorite_tab.current();
+            }
+            ActiveTab::Playlist => {
+                self.playlist_tab.current();
+            }
+            ActiveTab::Artists => {
+                self.album_tab.select(self.artist_tab.index);
+            }
+            ActiveTab::Albums => {
+                self.tracks_tab.select(self.album_tab.index);
+            }
+            ActiveTab::Search => {
+                self.tracks_tab.select(self.search_tab.index);
+            }
+            ActiveTab::Folders => {
+                self.folder_tab.current();
+            }
+            ActiveTab::Podcasts => {
+                self.podcast_tab.current();
+            }
+        }
     }
     pub fn previous_tab(&mut self) {
         self.next_tab();
     }
     pub fn next_item_in_tab(&mut self) {
+        let wrap = self.config.ui.wrap_navigation;
         match self.active_section {
             ActiveSection::Queue => {
-                navigate_list!(self.queue_tab, true);
+                navigate_list!(self.queue_tab, true, wrap);
             }
             ActiveSection::Others => match self.active_tab {
                 ActiveTab::Search => {
                     if !self.search_tab.data.is_empty() {
-                        navigate_list!(self.search_tab, true);
+                        navigate_list!(self.search_tab, true, wrap);
                     }
                 }
                 ActiveTab::Playlist => {
                     if !self.playlist_tab.data.is_empty() {
-                        navigate_list!(self.playlist_tab, true);
+                        navigate_list!(self.playlist_tab, true, wrap);
                     }
                 }
                 ActiveTab::Songs => {
                     if !self.tracks_tab.data.is_empty() {
-                        navigate_list!(self.tracks_tab, true);
+                        navigate_list!(self.tracks_tab, true, wrap);
                     }
                 }
                 ActiveTab::Favorites => {
                     if !self.favorite_tab.data.is_empty() {
-                        navigate_list!(self.favorite_tab, true);
+                        navigate_list!(self.favorite_tab, true, wrap);
                     }
                 }
                 ActiveTab::Artists => {
                     if !self.artist_tab.data.is_empty() {
-                        navigate_list!(self.artist_tab, true);
+                        navigate_list!(self.artist_tab, true, wrap);
                     }
                 }
                 ActiveTab::Albums => {
                     if !self.album_tab.data.is_empty() {
-                        navigate_list!(self.album_tab, true);
+                        navigate_list!(self.album_tab, true, wrap);
+                    }
+                }
+                ActiveTab::Folders => {
+                    if !self.folder_tab.data.is_empty() {
+                        navigate_list!(self.folder_tab, true, wrap);
+                    }
+                }
+                ActiveTab::Podcasts => {
+                    if !self.podcast_tab.data.is_empty() {
+                        navigate_list!(self.podcast_tab, true, wrap);
                     }
                 }
             },
         }
     }
     pub fn previous_item_in_tab(&mut self) {
+        let wrap = self.config.ui.wrap_navigation;
         match self.active_section {
             ActiveSection::Queue => {
                 if !self.queue_tab.data.is_empty() {
-                    navigate_list!(self.queue_tab, false);
+                    navigate_list!(self.queue_tab, false, wrap);
                 }
             }
             ActiveSection::Others => match self.active_tab {
                 ActiveTab::Favorites => {
                     if !self.favorite_tab.data.is_empty() {
-                        navigate_list!(self.favorite_tab, false);
+                        navigate_list!(self.favorite_tab, false, wrap);
                     }
                 }
                 ActiveTab::Playlist => {
                     if !self.playlist_tab.data.is_empty() {
-                        navigate_list!(self.playlist_tab, false);
+                        navigate_list!(self.playlist_tab, false, wrap);
                     }
                 }
                 ActiveTab::Search => {
                     if !self.search_tab.data.is_empty() {
-                        navigate_list!(self.search_tab, false);
+                        navigate_list!(self.search_tab, false, wrap);
                     }
                 }
                 ActiveTab::Songs => {
                     if !self.tracks_tab.data.is_empty() {
-                        navigate_list!(self.tracks_tab, false);
+                        navigate_list!(self.tracks_tab, false, wrap);
                     }
                 }
                 ActiveTab::Artists => {
                     if !self.artist_tab.data.is_empty() {
-                        navigate_list!(self.artist_tab, false);
+                        navigate_list!(self.artist_tab, false, wrap);
                     }
                 }
                 ActiveTab::Albums => {
                     if !self.album_tab.data.is_empty() {
-                        navigate_list!(self.album_tab, false);
+                        navigate_list!(self.album_tab, false, wrap);
+                    }
+                }
+                ActiveTab::Folders => {
+                    if !self.folder_tab.data.is_empty() {
+                        navigate_list!(self.folder_tab, false, wrap);
+                    }
+                }
+                ActiveTab::Podcasts => {
+                    if !self.podcast_tab.data.is_empty() {
+                        navigate_list!(self.podcast_tab, false, wrap);
                     }
                 }
             },
         }
     }
+    /// Select the playing track's album in the Albums tab, preferring the one by the same artist
+    pub fn jump_to_current_album(&mut self) {
+        let Some(track) = self.current_track.clone() else {
+            self.set_notification("Nothing is playing");
+            return;
+        };
+        let artist = track.album_artist.as_ref().unwrap_or(&track.artist);
+        let same_name = |name: &str| name.eq_ignore_ascii_case(&track.album);
+        let albums = &self.album_tab.data;
+        let found = track
+            .album_id
+            .as_ref()
+            .and_then(|id| albums.iter().position(|a| &a.id == id))
+            .or_else(|| {
+                albums
+                    .iter()
+                    .position(|a| same_name(&a.name) && a.artist.eq_ignore_ascii_case(artist))
+            })
+            .or_else(|| albums.iter().position(|a| same_name(&a.name)));
+        let Some(idx) = found else {
+            self.set_notification(format!("'{}' is not in the loaded albums", track.album));
+            return;
+        };
+        self.active_section = ActiveSection::Others;
+        self.select_tab(ActiveTab::Albums);
+        self.album_tab.select(idx);
+    }
+    /// Select the playing track's artist in the Artists tab
+    pub fn jump_to_current_artist(&mut self) {
+        let Some(track) = self.current_track.clone() else {
+            self.set_notification("Nothing is playing");
+            return;
+        };
+        let by_id = track
+            .artist_id
+            .as_ref()
+            .and_then(|id| self.artist_tab.data.iter().position(|a| &a.id == id));
+        // The album artist is what the Artists tab lists, featured artists aren't in it
+        let found = by_id.or_else(|| {
+            [track.album_artist.as_ref(), Some(&track.artist)]
+                .into_iter()
+                .flatten()
+                .find_map(|name| {
+                    self.artist_tab
+                        .data
+                        .iter()
+                        .position(|a| a.name.eq_ignore_ascii_case(name))
+                })
+        });
+        let Some(idx) = found else {
+            self.set_notification(format!("'{}' is not in the loaded artists", track.artist));
+            return;
+        };
+        self.active_section = ActiveSection::Others;
+        self.select_tab(ActiveTab::Artists);
+        self.artist_tab.select(idx);
+    }
+    /// Switch the Songs tab between a flat list and one grouped under album headers
+    pub fn toggle_grouped_songs(&mut self) {
+        self.songs_grouped = !self.songs_grouped;
+        self.apply_song_order();
+    }
+    /// Cycle the sort order of the Songs or Albums tab
+    pub fn cycle_sort(&mut self) {
+        if self.active_section != ActiveSection::Others {
+            return;
+        }
+        match self.active_tab {
+            ActiveTab::Songs => {
+                self.song_sort = SongSort::next(self.song_sort);
+                self.apply_song_order();
+            }
+            ActiveTab::Albums if self.album_list_type != AlbumListType::default() => {
+                self.set_notification("Switch back to all albums with 'L' to sort them");
+            }
+            ActiveTab::Albums => {
+                self.album_sort = AlbumSort::next(self.album_sort);
+                self.apply_album_sort();
+            }
+            _ => self.set_notification("Only the Songs and Albums tabs can be sorted"),
+        }
+    }
+    /// Reapply the chosen sort and grouping, e.g. after choosing another sort
+    pub fn apply_song_order(&mut self) {
+        self.reorder_songs(Vec::new());
+    }
+    /// Add a page of the library as it loads, in the chosen order
+    pub fn append_songs(&mut self, songs: Vec<Track>) {
+        self.reorder_songs(songs);
+    }
+    fn reorder_songs(&mut self, appended: Vec<Track>) {
+        // Marks are positions in the shown rows, which a filter narrows. Follow the tracks they
+        // were on through dropping the filters, the reorder and filtering again
+        let marked_ids = self.tracks_tab.marked_keys(|t| t.id.clone());
+        self.tracks_tab.clear_filter();
+        self.tracks_tab.data.extend(appended);
+        if let Some(sort) = self.song_sort {
+            let selected_id = self
+                .tracks_tab
+                .data
+                .get(self.tracks_tab.index)
+                .map(|t| t.id.clone());
+            sort_songs(&mut self.tracks_tab.data, sort);
+            self.reselect_song(selected_id);
+        }
+        if self.songs_grouped {
+            self.group_songs_by_album();
+        }
+        if !self.genre_filter.is_empty() {
+            self.apply_genre_filter();
+        }
+        self.reapply_song_filter();
+        if !marked_ids.is_empty() {
+            self.tracks_tab.mark_keys(&marked_ids, |t| t.id.clone());
+        }
+        self.refresh_inline_matches();
+    }
+    pub fn apply_album_sort(&mut self) {
+        self.album_tab.clear_filter();
+        let Some(sort) = self.album_sort else {
+            return;
+        };
+        let selected_id = self
+            .album_tab
+            .data
+            .get(self.album_tab.index)
+            .map(|a| a.id.clone());
+        let data = &mut self.album_tab.data;
+        match sort {
+            AlbumSort::Name => data.sort_by_key(|a| a.name.to_lowercase()),
+            AlbumSort::Artist => {
+                data.sort_by_key(|a| (a.artist.to_lowercase(), a.name.to_lowercase()))
+            }
+            // Newest first, albums without a year last
+            AlbumSort::Year => data.sort_by_key(|a| std::cmp::Reverse(a.year.unwrap_or(0))),
+        }
+        if let Some(id) = selected_id
+            && let Some(idx) = self.album_tab.data.iter().position(|a| a.id == id)
+        {
+            self.album_tab.index = idx;
+            if self.album_tab.state.selected().is_some() {
+                self.album_tab.state.select(Some(idx));
+            }
+        }
+    }
+    fn reselect_song(&mut self, selected_id: Option<String>) {
+        if let Some(id) = selected_id
+            && let Some(idx) = self.tracks_tab.data.iter().position(|t| t.id == id)
+        {
+            self.tracks_tab.index = idx;
+            if self.tracks_tab.state.selected().is_some() {
+                self.tracks_tab.state.select(Some(idx));
+            }
+        }
+    }
+    /// Keep albums in the order they first appear and order each album by track number
+    pub fn group_songs_by_album(&mut self) {
+        let selected_id = self
+            .tracks_tab
+            .data
+            .get(self.tracks_tab.index)
+            .map(|t| t.id.clone());
+        // Keys are worked out once per track, not on every comparison of the sort
+        let keys: Vec<(usize, i32)> = {
+            let mut album_order: HashMap<AlbumKey, usize> = HashMap::new();
+            self.tracks_tab
+                .data
+                .iter()
+                .map(|track| {
+                    let next = album_order.len();
+                    let order = *album_order.entry(AlbumKey::of(track)).or_insert(next);
+                    (order, track.track_number.unwrap_or(0))
+                })
+                .collect()
+        };
+        let mut rows: Vec<_> = keys
+            .into_iter()
+            .zip(std::mem::take(&mut self.tracks_tab.data))
+            .collect();
+        rows.sort_by_key(|(key, _)| *key);
+        self.tracks_tab.data = rows.into_iter().map(|(_, track)| track).collect();
+        self.reselect_song(selected_id);
+    }
+    /// Cycle the Albums tab between the whole library and the server's discovery lists
+    pub async fn cycle_album_list(&mut self) {
+        let list_type = self.album_list_type.next();
+        match self.subsonic_client.get_album_list(list_type, 0, 500).await {
+            Ok(albums) => {
+                self.album_list_type = list_type;
+                self.album_tab.clear_filter();
+                self.album_tab.data = albums;
+                // The discovery lists are already ordered by the server
+                if list_type == AlbumListType::default() {
+                    self.apply_album_sort();
+                } else {
+                    self.album_sort = None;
+                }
+                self.album_tab.index = 0;
+                if self.active_tab == ActiveTab::Albums && !self.album_tab.data.is_empty() {
+                    self.album_tab.select(0);
+                } else {
+                    self.album_tab.clear();
+                }
+                self.set_notification(format!("Albums: {}", list_type.label()));
+            }
+            Err(e) => self.set_notification(format!("Failed to load albums: {}", e)),
+        }
+    }
+}
+
+fn sort_songs(data: &mut [Track], sort: SongSort) {
+    match sort {
+        SongSort::Title => data.sort_by_key(|t| t.title.to_lowercase()),
+        SongSort::Artist => data.sort_by_key(|t| (t.artist.to_lowercase(), t.title.to_lowercase())),
+        SongSort::Album => {
+            data.sort_by_key(|t| (t.album.to_lowercase(), t.track_number.unwrap_or(0)))
+        }
+        SongSort::PlayCount => data.sort_by_key(|t| std::cmp::Reverse(t.play_count.unwrap_or(0))),
+    }
+}
+
+/// Which album a track groups under, the id when the server gave one, else the name and
+/// album artist, matching `Track::same_album`
+#[derive(PartialEq, Eq, Hash)]
+enum AlbumKey<'a> {
+    Id(&'a str),
+    Name(&'a str, Option<&'a str>),
+}
+impl<'a> AlbumKey<'a> {
+    fn of(track: &'a Track) -> Self {
+        match &track.album_id {
+            Some(id) => AlbumKey::Id(id),
+            None => AlbumKey::Name(&track.album, track.album_artist.as_deref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::TabSelection;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_step_index_wraps_at_ends() {
+        assert_eq!(step_index(Some(4), 5, true, true), 0);
+        assert_eq!(step_index(Some(0), 5, false, true), 4);
+        assert_eq!(step_index(Some(2), 5, true, true), 3);
+        assert_eq!(step_index(Some(2), 5, false, true), 1);
+    }
+
+    #[test]
+    fn test_step_index_clamps_at_ends() {
+        assert_eq!(step_index(Some(4), 5, true, false), 4);
+        assert_eq!(step_index(Some(0), 5, false, false), 0);
+        assert_eq!(step_index(Some(2), 5, true, false), 3);
+        assert_eq!(step_index(Some(2), 5, false, false), 1);
+        // A single row stays selected either way
+        assert_eq!(step_index(Some(0), 1, true, false), 0);
+        assert_eq!(step_index(Some(0), 1, false, false), 0);
+    }
+
+    fn track(id: &str, title: &str) -> Track {
+        Track {
+            id: id.to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_marks_follow_their_tracks_through_a_sort_with_a_filter() {
+        let mut tab = TabSelection::new();
+        tab.data = vec![
+            track("1", "c"),
+            track("2", "a"),
+            track("3", "b"),
+            track("4", "d"),
+        ];
+        // Filtered down to c, a and b, with c and b marked
+        tab.filter_to(vec![0, 1, 2]);
+        tab.marked = HashSet::from([0, 2]);
+
+        let marked_ids = tab.marked_keys(|t| t.id.clone());
+        tab.clear_filter();
+        sort_songs(&mut tab.data, SongSort::Title);
+        // a, b and c are the first three rows now
+        tab.filter_to(vec![0, 1, 2]);
+        tab.mark_keys(&marked_ids, |t| t.id.clone());
+
+        assert_eq!(tab.marked, HashSet::from([1, 2]));
+        assert_eq!(
+            tab.marked_keys(|t| t.title.clone()),
+            HashSet::from(["b".to_string(), "c".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_step_index_without_selection() {
+        for wrap in [true, false] {
+            assert_eq!(step_index(None, 5, true, wrap), 0);
+            assert_eq!(step_index(None, 5, false, wrap), 4);
+        }
+    }
 }
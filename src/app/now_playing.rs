@@ -0,0 +1,55 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+
+use super::App;
+
+/// How often to ask the server what other clients are playing
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+impl App {
+    /// Refresh what other sessions on the account are playing, at most every few seconds
+    pub fn refresh_now_playing(&mut self) {
+        if self.offline {
+            return;
+        }
+        if let Some(rx) = &mut self.now_playing_rx {
+            match rx.try_recv() {
+                Ok(entries) => {
+                    // The server records our requests under the configured client name
+                    let username = &self.config.username;
+                    let own_player = &self.config.subsonic.client_name;
+                    self.now_playing = entries
+                        .into_iter()
+                        .filter(|e| {
+                            &e.username != username || e.player_name.as_ref() != Some(own_player)
+                        })
+                        .collect();
+                    self.now_playing_rx = None;
+                    self.needs_redraw = true;
+                }
+                Err(mpsc::error::TryRecvError::Disconnected) => self.now_playing_rx = None,
+                Err(mpsc::error::TryRecvError::Empty) => {}
+            }
+            return;
+        }
+        if self
+            .last_now_playing_check
+            .is_some_and(|t| t.elapsed() < REFRESH_INTERVAL)
+        {
+            return;
+        }
+        self.last_now_playing_check = Some(Instant::now());
+        let (tx, rx) = mpsc::channel(1);
+        self.now_playing_rx = Some(rx);
+        let client = self.subsonic_client.clone();
+        tokio::spawn(async move {
+            match client.get_now_playing().await {
+                Ok(entries) => {
+                    let _ = tx.send(entries).await;
+                }
+                Err(e) => log::debug!("Failed to fetch now playing: {}", e),
+            }
+        });
+    }
+}
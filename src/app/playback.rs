@@ -1,7 +1,8 @@
 use anyhow::Result;
-use futures::future;
-use mpris_server::{Metadata, Property};
+use mpris_server::{Metadata, Property, Time};
 use rand::seq::SliceRandom;
+use std::time::Duration;
+use url::Url;
 
 use crate::{
     app::{ActiveSection, ActiveTab, AppError, RepeatMode, ShuffleMode, Track, VolumeDirection},
@@ -9,12 +10,52 @@ use crate::{
 };
 
 use super::App;
+
+/// Step and ceiling of the manual boost in decibels
+const BOOST_STEP_DB: f32 = 1.5;
+const MAX_BOOST_DB: f32 = 12.0;
+
+/// Show the format the stream asked the server to transcode to, when it asked for one,
+/// rather than the one the file is stored in
+fn apply_transcoding(track: &mut Track, stream_url: &str) {
+    let Ok(url) = Url::parse(stream_url) else {
+        return;
+    };
+    let mut format = None;
+    let mut max_bit_rate = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "format" => format = Some(value.into_owned()),
+            "maxBitRate" => max_bit_rate = value.parse::<i32>().ok().filter(|&max| max > 0),
+            _ => {}
+        }
+    }
+    if let Some(format) =
+        format.filter(|f| f != "raw" && track.suffix.as_deref() != Some(f.as_str()))
+    {
+        track.suffix = Some(format);
+        track.content_type = None;
+        // The stored bitrate says nothing about the transcoded one
+        track.bit_rate = None;
+    }
+    if let Some(max) = max_bit_rate {
+        track.bit_rate = Some(track.bit_rate.map_or(max, |rate| rate.min(max)));
+    }
+}
+
+/// How long after a track starts an end is only believed once the sink is confirmed empty
+const TRACK_START_GRACE: Duration = Duration::from_millis(500);
+
 impl App {
     pub async fn toggle_playback(&mut self) -> Result<(), AppError> {
         if self.is_playing {
             let player = self.player.lock().await;
             player.pause()?;
             self.is_playing = false;
+        } else if self.current_track.is_none() && !self.queue_tab.data.is_empty() {
+            // Stopped, start the queue again from where it was
+            self.play_from_queue(self.playing_index).await?;
+            return Ok(());
         } else if self.current_track.is_none() {
             return Err(AppError::NoTrackLoaded);
         } else if self.current_track.is_some() {
@@ -24,7 +65,7 @@ impl App {
                 self.is_playing = true;
                 let track = self.current_track.clone().unwrap();
                 drop(player);
-                self.notify_now_playing(&track).await?;
+                self.notify_now_playing(&track);
             }
         } else if !self.queue_tab.data.is_empty() {
             self.play_selected(self.playing_index).await?;
@@ -36,27 +77,138 @@ impl App {
         Ok(())
     }
 
-    async fn start_playback(&mut self, track: Track, queue_index: usize) -> Result<()> {
-        let stream_url = match &self.config.search.mode {
+    async fn resolve_stream_url(&self, id: &str) -> Result<String> {
+        if let Some(url) = self.stream_urls.get(id) {
+            return Ok(url.clone());
+        }
+        match self.search_mode {
             crate::config::SearchMode::Remote => {
-                self.subsonic_client
-                    .get_stream_url_with_retry(&track.id, 5)
-                    .await?
+                self.subsonic_client.get_stream_url_with_retry(id, 5).await
             }
-            crate::config::SearchMode::Local => self.subsonic_client.get_stream_url(&track.id)?,
-        };
+            crate::config::SearchMode::Local => self.subsonic_client.get_stream_url(id),
+        }
+    }
+    /// Play a track. Streamed tracks are only marked as loading here and fetched on the next
+    /// `update`, so the header can show that they are buffering while the download blocks
+    pub async fn start_playback(&mut self, mut track: Track, queue_index: usize) -> Result<()> {
+        if track.cover_art.is_none() {
+            track.cover_art = self.cover_art_url(&track);
+        }
+        // A boost is meant for one quiet record, not whatever plays after it
+        if self.boost_db != 0.0
+            && self
+                .current_track
+                .as_ref()
+                .is_none_or(|t| !t.same_album(&track))
+        {
+            self.set_boost(0.0).await;
+        }
+        self.push_history(&track);
+        self.scrobbled_current = false;
+        self.radio = None;
+        if self.offline_cache.contains(&track.id) {
+            return self.load_and_play(track, queue_index).await;
+        }
+        self.loading_track = true;
+        self.playing_index = queue_index;
+        self.current_track = Some(track.clone());
+        self.metadata = track_to_metadata(&track);
+        self.pending_playback = Some((track, queue_index));
+        Ok(())
+    }
+    /// Play whatever `start_playback` left pending
+    pub async fn load_pending_playback(&mut self) -> Result<()> {
+        if let Some((track, queue_index)) = self.pending_playback.take() {
+            self.needs_redraw = true;
+            let title = track.title.clone();
+            // A stream the server refused shouldn't take the app down with it
+            if let Err(e) = self.load_and_play(track, queue_index).await {
+                self.loading_track = false;
+                self.is_playing = false;
+                // The saved track may be gone from the server, carry on with the one after it
+                if self.resuming
+                    && let Some(next) = self.queue_tab.data.get(queue_index + 1).cloned()
+                {
+                    log::warn!("Skipping '{}' while resuming: {}", title, e);
+                    self.pending_seek = None;
+                    self.queue_tab.select(queue_index + 1);
+                    return self.start_playback(next, queue_index + 1).await;
+                }
+                self.resuming = false;
+                self.resume_paused = false;
+                self.set_notification(format!("Could not play '{}': {}", title, e));
+                self.sync_mpris().await;
+            }
+        }
+        Ok(())
+    }
+    async fn load_and_play(&mut self, mut track: Track, queue_index: usize) -> Result<()> {
+        let load_started = std::time::Instant::now();
+        let mut resolved_url = None;
+        let offline_path = self.offline_cache.path_for(&track.id);
         {
             let mut player = self.player.lock().await;
-            player.load_url(&stream_url).await?;
-            player.play()?;
+            let gain = track
+                .replay_gain
+                .as_ref()
+                .map_or(1.0, |rg| rg.factor(&self.config.playback.replay_gain));
+            player.set_gain(gain);
+            // Downloaded tracks are played from disk and never hit the stream endpoint
+            let loaded = match offline_path {
+                Some(path) => player.load_file(&path).await,
+                None => match self.resolve_stream_url(&track.id).await {
+                    Ok(stream_url) => {
+                        let loaded = player.load_url(&stream_url).await;
+                        resolved_url = Some(stream_url);
+                        loaded
+                    }
+                    Err(e) => Err(e),
+                },
+            };
+            self.loading_track = false;
+            loaded?;
+            if self.resume_paused {
+                player.pause_immediately();
+            } else {
+                player.play()?;
+            }
+            // Some servers leave the duration out, the decoder may still know it
+            if track.duration <= 0
+                && let Some(duration) = player.decoded_duration()
+            {
+                track.duration = duration.as_micros() as i64;
+                if let Some(queued) = self.queue_tab.data.get_mut(queue_index)
+                    && queued.id == track.id
+                {
+                    queued.duration = track.duration;
+                }
+            }
+            self.track_started = Some(std::time::Instant::now());
+            self.deferred_finish = None;
+            if let Some(secs) = self.pending_seek.take() {
+                player.seek_absolute(secs)?;
+            }
+            self.resuming = false;
         }
-        self.is_playing = true;
+        let paused = std::mem::take(&mut self.resume_paused);
+        log::debug!(
+            "Started '{}' {:?} after it was requested",
+            track.title,
+            load_started.elapsed()
+        );
+        if let Some(url) = resolved_url {
+            apply_transcoding(&mut track, &url);
+            self.stream_urls.insert(track.id.clone(), url);
+        }
+        self.is_playing = !paused;
         self.playing_index = queue_index;
         self.current_track = Some(track.clone());
         self.metadata = track_to_metadata(&track);
+        self.prefetch_stream_urls();
         self.load_cover_art_for_track(&track).await;
-        self.notify_now_playing(&track).await?;
+        self.notify_now_playing(&track);
         self.sync_mpris().await;
+        self.lastfm_now_playing(&track);
         self.subsonic_client.scrobble(&track, false).await?;
         Ok(())
     }
@@ -86,12 +238,22 @@ impl App {
         }
     }
     pub fn toggle_repeat(&mut self) {
+        self.repeat_before_one = None;
         self.on_repeat = match self.on_repeat {
             RepeatMode::None => RepeatMode::One,
             RepeatMode::One => RepeatMode::All,
             RepeatMode::All => RepeatMode::None,
         }
     }
+    /// Loop the playing track, or go back to the repeat mode from before
+    pub fn toggle_repeat_one(&mut self) {
+        if self.on_repeat == RepeatMode::One {
+            self.on_repeat = self.repeat_before_one.take().unwrap_or(RepeatMode::None);
+        } else {
+            let previous = std::mem::replace(&mut self.on_repeat, RepeatMode::One);
+            self.repeat_before_one = Some(previous);
+        }
+    }
     pub async fn play_next(&mut self) -> Result<(), AppError> {
         if self.current_track.is_none() {
             return Err(AppError::NoTrackLoaded);
@@ -104,23 +266,22 @@ impl App {
         }
         if self.queue_tab.data.is_empty() {
             return Err(AppError::EmptyQueue);
-        } else if self.playing_index < self.queue_tab.data.len() - 1 {
-            match self.on_repeat {
-                RepeatMode::One => {
-                    self.play_selected(self.playing_index).await?;
-                }
-                RepeatMode::None => {
-                    self.playing_index += 1;
-                    self.play_from_queue(self.playing_index).await?;
-                }
-                RepeatMode::All => {}
+        }
+        let at_end = self.playing_index + 1 >= self.queue_tab.data.len();
+        match self.on_repeat {
+            RepeatMode::One => {
+                self.play_selected(self.playing_index).await?;
+            }
+            RepeatMode::All => {
+                let next = (self.playing_index + 1) % self.queue_tab.len();
+                self.play_from_queue(next).await?;
+            }
+            // The last track keeps playing rather than stopping out of the blue
+            RepeatMode::None if at_end => self.set_notification("End of queue"),
+            RepeatMode::None => {
+                self.playing_index += 1;
+                self.play_from_queue(self.playing_index).await?;
             }
-        } else {
-            self.player.lock().await.stop()?;
-            self.is_playing = false;
-            self.current_track = None;
-            self.metadata = Metadata::default();
-            self.sync_mpris().await;
         }
         Ok(())
     }
@@ -130,24 +291,41 @@ impl App {
             return Err(AppError::EmptyQueue);
         } else if self.playing_index > 0 {
             self.play_from_queue(self.playing_index - 1).await?;
+        } else if self.on_repeat == RepeatMode::All {
+            self.play_from_queue(self.queue_tab.len() - 1).await?;
         } else {
-            self.player.lock().await.stop()?;
-            self.is_playing = false;
-            self.metadata = Metadata::default();
-            self.sync_mpris().await;
+            self.set_notification("Start of queue");
         }
         Ok(())
     }
+    /// Show an empty queue or nothing playing as a notification, a key that can't do anything
+    /// then says why. Other errors are passed on
+    pub fn notify_boundary(&mut self, result: Result<(), AppError>) -> Result<(), AppError> {
+        match result {
+            Err(e @ (AppError::NoTrackLoaded | AppError::EmptyQueue)) => {
+                self.set_notification(e.to_string());
+                Ok(())
+            }
+            result => result,
+        }
+    }
 
     pub async fn stop_playback(&mut self) -> Result<(), AppError> {
+        self.pending_playback = None;
+        self.loading_track = false;
+        self.radio = None;
         {
-            let player = self.player.lock().await;
+            let mut player = self.player.lock().await;
             player.stop()?;
             self.is_playing = false;
             self.current_track = None;
             self.metadata = Metadata::default();
         }
         self.sync_mpris().await;
+        // The stopped sink still reports where it was, start the progress from zero
+        if let Ok(mut state) = self.shared_state.write() {
+            state.position = Time::ZERO;
+        }
         Ok(())
     }
 
@@ -160,6 +338,7 @@ impl App {
         if let Ok(mut state) = self.shared_state.write() {
             state.volume = clamped;
         }
+        self.current_volume = clamped;
 
         let _ = self
             .mpris
@@ -169,41 +348,164 @@ impl App {
         Ok(())
     }
     pub async fn adjust_volume(&mut self, direction: VolumeDirection) -> Result<()> {
+        let playback = &self.config.playback;
         let delta = match direction {
-            VolumeDirection::Up => 0.1,
-            VolumeDirection::Down => -0.1,
+            VolumeDirection::Up => playback.volume_step,
+            VolumeDirection::Down => -playback.volume_step,
+            VolumeDirection::FineUp => playback.fine_volume_step,
+            VolumeDirection::FineDown => -playback.fine_volume_step,
         };
         let current = { self.shared_state.read().map(|s| s.volume).unwrap_or(1.0) };
         self.set_volume(current + delta).await
     }
-    pub async fn seek_forward(&mut self) -> Result<()> {
-        let player = self.player.lock().await;
-        player.seek_relative(5)?;
+    pub async fn adjust_boost(&mut self, up: bool) {
+        let step = if up { BOOST_STEP_DB } else { -BOOST_STEP_DB };
+        let boost = (self.boost_db + step).clamp(0.0, MAX_BOOST_DB);
+        self.set_boost(boost).await;
+        if boost == 0.0 {
+            self.set_notification("Boost off");
+        } else {
+            self.set_notification(format!("Boost +{:.1} dB", boost));
+        }
+    }
+    async fn set_boost(&mut self, boost_db: f32) {
+        self.boost_db = boost_db;
+        self.player
+            .lock()
+            .await
+            .set_boost(10f32.powf(boost_db / 20.0));
+    }
+    /// Set the volume from a typed percentage, clamping anything outside 0-100
+    pub async fn set_volume_percent(&mut self, input: &str) -> Result<()> {
+        let input = input.trim().trim_end_matches('%').trim();
+        let Some(percent) = input.parse::<f64>().ok().filter(|p| p.is_finite()) else {
+            self.set_notification(format!("'{}' is not a volume between 0 and 100", input));
+            return Ok(());
+        };
+        let clamped = percent.clamp(0.0, 100.0);
+        self.set_volume(clamped / 100.0).await?;
+        if clamped != percent {
+            self.set_notification(format!("Volume clamped to {:.0}%", clamped));
+        } else {
+            self.set_notification(format!("Volume {:.0}%", clamped));
+        }
+        Ok(())
+    }
+    pub async fn seek_forward(&mut self, secs: u64) -> Result<()> {
+        let mut player = self.player.lock().await;
+        player.seek_relative(secs as i64)?;
         Ok(())
     }
 
-    pub async fn seek_backward(&mut self) -> Result<()> {
-        let player = self.player.lock().await;
-        player.seek_relative(-5)?;
+    pub async fn seek_backward(&mut self, secs: u64) -> Result<()> {
+        let mut player = self.player.lock().await;
+        player.seek_relative(-(secs as i64))?;
         Ok(())
     }
-    pub async fn check_track_finished(&mut self) -> Result<()> {
-        if !self.is_playing || self.current_track.is_none() {
+    /// The player reports the end of each track through `PlayerCommand::TrackFinished`, one
+    /// that arrives after a skip or stop belongs to a sink that is already gone
+    pub async fn handle_track_finished(&mut self, generation: u64) -> Result<()> {
+        if !self.is_playing || self.current_track.is_none() || self.loading_track {
             return Ok(());
         }
-        let is_finished = {
-            let player = self.player.lock().await;
-            player.is_finished() && player.has_track_loaded()
+        if !self.player.lock().await.is_current_generation(generation) {
+            return Ok(());
+        }
+        // A station only ends when its stream broke off, there is nothing to advance to
+        if let Some(station) = self.radio.clone() {
+            self.stop_playback().await?;
+            self.set_notification(format!("Lost the stream of {}", station.name));
+            return Ok(());
+        }
+        // The sink can look empty for a moment before the first samples are queued
+        if self
+            .track_started
+            .is_some_and(|t| t.elapsed() < TRACK_START_GRACE)
+        {
+            self.deferred_finish = Some(generation);
+            return Ok(());
+        }
+        self.on_track_finished().await
+    }
+    /// Act on an end that came in too early once the grace period is over and the sink is
+    /// still empty, otherwise the track is really playing and the end is dropped
+    pub async fn check_deferred_finish(&mut self) -> Result<()> {
+        let Some(generation) = self.deferred_finish else {
+            return Ok(());
+        };
+        if self
+            .track_started
+            .is_some_and(|t| t.elapsed() < TRACK_START_GRACE)
+        {
+            return Ok(());
+        }
+        self.deferred_finish = None;
+        if !self.player.lock().await.is_drained() {
+            log::debug!("Ignoring the end of a track that had only just started");
+            return Ok(());
+        }
+        self.handle_track_finished(generation).await
+    }
+    /// Drive the crossfade, which has to start before the track actually ends
+    pub async fn check_crossfade(&mut self) -> Result<()> {
+        if !self.is_playing || self.current_track.is_none() || self.loading_track {
+            return Ok(());
+        }
+        let crossfade = Duration::from_secs(self.config.playback.crossfade_secs);
+        let has_next = self.has_next_track();
+        let fade_now = {
+            let mut player = self.player.lock().await;
+            player.tick_crossfade();
+            let duration = self.current_track.as_ref().map_or(0, |t| t.duration);
+            let remaining = duration - player.get_position().as_micros();
+            let fade_now = !crossfade.is_zero()
+                && has_next
+                && !player.is_crossfading()
+                && duration > 2 * crossfade.as_micros() as i64
+                && remaining <= crossfade.as_micros() as i64;
+            if fade_now {
+                player.begin_crossfade(crossfade);
+            }
+            fade_now
         };
-        if is_finished {
+        if fade_now {
             self.on_track_finished().await?;
         }
         Ok(())
     }
+    /// Whether finishing the current track moves on to another one
+    fn has_next_track(&self) -> bool {
+        // Let the last track play out rather than fading into one that won't start
+        if self.album_finished() || self.sleep_expired() {
+            return false;
+        }
+        match self.on_repeat {
+            RepeatMode::One | RepeatMode::All => !self.queue_tab.data.is_empty(),
+            RepeatMode::None => self.playing_index + 1 < self.queue_tab.data.len(),
+        }
+    }
     async fn on_track_finished(&mut self) -> Result<()> {
-        self.subsonic_client
-            .scrobble(self.current_track.as_ref().unwrap(), true)
-            .await?;
+        let finished = self.current_track.clone().unwrap();
+        // Playing to the end counts even if the threshold check never ran in between
+        if !self.scrobbled_current {
+            let played = Duration::from_micros(finished.duration.max(0) as u64);
+            self.scrobble_current(&finished, played).await?;
+        }
+        self.advance_after_finished().await?;
+        // Pick up the new play count, once the next track is already under way
+        self.refresh_track(&finished.id);
+        Ok(())
+    }
+    async fn advance_after_finished(&mut self) -> Result<()> {
+        if self.sleep_expired() {
+            return self.stop_for_sleep().await;
+        }
+        if self.album_finished() {
+            self.stop_after_current_group = false;
+            self.stop_playback().await?;
+            self.set_notification("Album finished, stopped playback");
+            return Ok(());
+        }
         match self.on_repeat {
             RepeatMode::One => {
                 self.play_selected(self.playing_index).await?;
@@ -248,7 +550,7 @@ impl App {
             self.start_playback(track, self.queue_tab.index).await?;
         } else {
             {
-                let player = self.player.lock().await;
+                let mut player = self.player.lock().await;
                 player.stop()?;
             }
             self.is_playing = false;
@@ -257,6 +559,34 @@ impl App {
         }
         Ok(())
     }
+    /// Queue the whole album of the selected song and start at that song
+    pub async fn play_album_from_selected(&mut self) -> Result<()> {
+        if self.active_section != ActiveSection::Others || self.active_tab != ActiveTab::Songs {
+            return self.play_selected(self.find_selected()).await;
+        }
+        let Some(track) = self.tracks_tab.get().cloned() else {
+            return Ok(());
+        };
+        let Some(album_id) = track.album_id.as_deref() else {
+            self.set_notification(format!(
+                "The server did not say which album '{}' is on",
+                track.title
+            ));
+            return Ok(());
+        };
+        let songs = self.subsonic_client.get_album_songs(album_id).await?;
+        let Some(start) = songs.iter().position(|t| t.id == track.id) else {
+            self.set_notification(format!(
+                "'{}' is not in its album's track list",
+                track.title
+            ));
+            return Ok(());
+        };
+        self.queue_tab.data = songs;
+        self.queue_tab.index = start;
+        self.disable_shuffle();
+        self.start_playback(track, start).await
+    }
     pub async fn play_selected(&mut self, songindex: usize) -> Result<()> {
         match self.active_section {
             ActiveSection::Queue => {
@@ -265,6 +595,10 @@ impl App {
             ActiveSection::Others => {
                 let mut track_to_play: Option<Track> = None;
                 match self.active_tab {
+                    // Directories are entered rather than played
+                    ActiveTab::Folders => return self.activate_folder_entry().await,
+                    // Channels are entered rather than played
+                    ActiveTab::Podcasts => return self.activate_podcast_entry().await,
                     ActiveTab::Search => {
                         if let Some(track) =
                             self.search_tab.data.get(self.search_tab.index).cloned()
@@ -294,22 +628,15 @@ impl App {
                         }
                     }
                     ActiveTab::Artists => {
-                        if let Some(artist) = self.artist_tab.data.get(self.artist_tab.index) {
-                            let artist_albums =
-                                self.subsonic_client.get_artist_albums(artist).await?;
-                            if !artist_albums.is_empty() {
-                                let songs_futures = artist_albums
-                                    .iter()
-                                    .map(|album| self.subsonic_client.get_songs_in_album(album));
-                                let nested_songs = future::try_join_all(songs_futures).await?;
-                                let songs: Vec<Track> =
-                                    nested_songs.into_iter().flatten().collect();
-                                if !songs.is_empty() {
-                                    self.queue_tab.data = songs;
-                                    self.queue_tab.index = 0;
-                                    track_to_play = self.queue_tab.data.first().cloned();
-                                    self.playing_index = self.queue_tab.index;
-                                }
+                        if let Some(artist) =
+                            self.artist_tab.data.get(self.artist_tab.index).cloned()
+                        {
+                            let songs = self.artist_songs(&artist).await?;
+                            if !songs.is_empty() {
+                                self.queue_tab.data = songs;
+                                self.queue_tab.index = 0;
+                                track_to_play = self.queue_tab.data.first().cloned();
+                                self.playing_index = self.queue_tab.index;
                             }
                         }
                     }
@@ -344,7 +671,7 @@ impl App {
                     self.start_playback(track.clone(), self.queue_tab.index)
                         .await?;
                 } else {
-                    let player = self.player.lock().await;
+                    let mut player = self.player.lock().await;
                     player.stop()?;
                     self.is_playing = false;
                     self.current_track = None;
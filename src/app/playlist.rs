@@ -0,0 +1,123 @@
+use anyhow::Result;
+
+use crate::app::{ActiveSection, ActiveTab, ConfirmAction, PickerKind, PromptKind};
+
+use super::App;
+impl App {
+    pub fn start_save_queue_as_playlist(&mut self) {
+        if self.queue_tab.data.is_empty() {
+            self.set_notification("Queue is empty, nothing to save");
+            return;
+        }
+        self.open_prompt(PromptKind::PlaylistName);
+    }
+    pub async fn save_queue_as_playlist(&mut self, name: &str) -> Result<()> {
+        let song_ids: Vec<String> = self.queue_tab.data.iter().map(|t| t.id.clone()).collect();
+        match self.subsonic_client.create_playlist(name, &song_ids).await {
+            Ok(()) => {
+                self.reload_playlists().await;
+                self.set_notification(format!(
+                    "Created playlist '{}' with {} tracks",
+                    name,
+                    song_ids.len()
+                ));
+            }
+            Err(e) => {
+                self.set_notification(format!("Failed to create playlist: {}", e));
+            }
+        }
+        Ok(())
+    }
+    pub fn start_add_to_playlist(&mut self) {
+        let Some(track) = self.selected_track() else {
+            self.set_notification("Select a track to add it to a playlist");
+            return;
+        };
+        if self.playlist_tab.data.is_empty() {
+            self.set_notification("No playlists yet, create one first with 'c'");
+            return;
+        }
+        let items = self
+            .playlist_tab
+            .data
+            .iter()
+            .map(|p| format!("{} ({} songs)", p.name, p.song_count))
+            .collect();
+        let playlist_ids = self
+            .playlist_tab
+            .data
+            .iter()
+            .map(|p| p.id.clone())
+            .collect();
+        self.open_picker(
+            PickerKind::AddToPlaylist {
+                track_ids: vec![track.id],
+                playlist_ids,
+            },
+            format!("Add '{}' to playlist", track.title),
+            items,
+        );
+    }
+    pub async fn add_to_playlist(&mut self, playlist_id: &str, track_ids: &[String]) -> Result<()> {
+        match self
+            .subsonic_client
+            .update_playlist(playlist_id, track_ids, &[])
+            .await
+        {
+            Ok(()) => {
+                self.reload_playlists().await;
+                self.set_notification(format!("Added {} track(s) to playlist", track_ids.len()));
+            }
+            Err(e) => {
+                self.set_notification(format!("Failed to update playlist: {}", e));
+            }
+        }
+        Ok(())
+    }
+    pub fn start_delete_playlist(&mut self) {
+        if self.active_section != ActiveSection::Others || self.active_tab != ActiveTab::Playlist {
+            return;
+        }
+        let Some(playlist) = self.playlist_tab.data.get(self.playlist_tab.index) else {
+            self.set_notification("No playlist selected");
+            return;
+        };
+        // Capture the id now so the confirmation always refers to the playlist that was
+        // highlighted when it was opened
+        let action = ConfirmAction::DeletePlaylist {
+            id: playlist.id.clone(),
+            name: playlist.name.clone(),
+        };
+        self.open_confirm(action);
+    }
+    pub async fn delete_playlist(&mut self, id: &str, name: &str) -> Result<()> {
+        if let Err(e) = self.subsonic_client.delete_playlist(id).await {
+            self.set_notification(format!("Failed to delete playlist: {}", e));
+            return Ok(());
+        }
+        self.playlist_tab.clear_filter();
+        self.playlist_tab.data.retain(|p| p.id != id);
+        let len = self.playlist_tab.len();
+        if len == 0 {
+            self.playlist_tab.index = 0;
+            self.playlist_tab.clear();
+        } else if self.playlist_tab.index >= len {
+            self.playlist_tab.select(len - 1);
+        }
+        self.set_notification(format!("Deleted playlist '{}'", name));
+        Ok(())
+    }
+    /// Refetch the playlists from the server, keeping the selection in bounds
+    pub async fn reload_playlists(&mut self) {
+        match self.subsonic_client.get_playlists().await {
+            Ok(playlists) => {
+                self.playlist_tab.clear_filter();
+                self.playlist_tab.data = playlists;
+                if self.playlist_tab.index >= self.playlist_tab.len() {
+                    self.playlist_tab.index = self.playlist_tab.len().saturating_sub(1);
+                }
+            }
+            Err(e) => log::debug!("Failed to reload playlists: {}", e),
+        }
+    }
+}
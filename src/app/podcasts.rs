@@ -0,0 +1,144 @@
+use anyhow::Result;
+
+use crate::app::{ActiveSection, ActiveTab, FolderLevel, PodcastEntry, Track};
+
+use super::App;
+
+/// How many episodes the Newest list asks the server for
+const NEWEST_EPISODES: usize = 30;
+
+impl App {
+    /// Load the channels the first time the Podcasts tab is opened
+    pub async fn open_podcasts(&mut self) {
+        if self.podcast_channel.is_none() && self.podcast_tab.data.is_empty() {
+            self.load_podcasts(None, 0).await;
+        }
+    }
+    /// List a channel's episodes, the newest episodes for an empty id, or the channels when
+    /// `id` is None. Returns whether it loaded
+    async fn load_podcasts(&mut self, id: Option<&str>, select: usize) -> bool {
+        let entries = match id {
+            Some("") => {
+                self.subsonic_client
+                    .get_newest_podcasts(NEWEST_EPISODES)
+                    .await
+            }
+            Some(id) => self.subsonic_client.get_podcast_episodes(id).await,
+            None => self.subsonic_client.get_podcasts().await.map(|channels| {
+                let mut entries = vec![PodcastEntry::Newest];
+                entries.extend(channels);
+                entries
+            }),
+        };
+        match entries {
+            Ok(entries) => {
+                self.podcast_tab.clear_filter();
+                self.podcast_tab.data = entries;
+                self.podcast_tab.index = select.min(self.podcast_tab.len().saturating_sub(1));
+                if self.active_tab == ActiveTab::Podcasts && !self.podcast_tab.data.is_empty() {
+                    self.podcast_tab.current();
+                } else {
+                    self.podcast_tab.clear();
+                }
+                true
+            }
+            Err(e) => {
+                self.set_notification(format!("Failed to load podcasts: {}", e));
+                false
+            }
+        }
+    }
+    /// Open the selected channel, or play the listed episodes from the selected one
+    pub async fn activate_podcast_entry(&mut self) -> Result<()> {
+        let index = self.podcast_tab.index;
+        let (id, name) = match self.podcast_tab.get().cloned() {
+            Some(PodcastEntry::Newest) => (String::new(), "Newest episodes".to_string()),
+            Some(PodcastEntry::Channel { id, title }) => (id, title),
+            Some(PodcastEntry::Episode(episode)) => {
+                if !episode.is_downloaded() {
+                    self.set_notification(format!(
+                        "'{}' is {} on the server",
+                        episode.track.title,
+                        episode.status_label()
+                    ));
+                    return Ok(());
+                }
+                let episodes = self.podcast_episodes();
+                let start = episodes
+                    .iter()
+                    .position(|t| t.id == episode.track.id)
+                    .unwrap_or(0);
+                self.queue_tab.data = episodes;
+                self.queue_tab.index = start;
+                self.disable_shuffle();
+                return self.start_playback(episode.track, start).await;
+            }
+            None => return Ok(()),
+        };
+        if self.load_podcasts(Some(&id), 0).await {
+            self.podcast_channel = Some(FolderLevel {
+                id,
+                name,
+                parent_index: index,
+            });
+        }
+        Ok(())
+    }
+    /// Go back to the channels, keeping the one we came from selected
+    pub async fn leave_podcast_channel(&mut self) {
+        if self.active_section != ActiveSection::Others || self.active_tab != ActiveTab::Podcasts {
+            return;
+        }
+        let Some(level) = self.podcast_channel.take() else {
+            return;
+        };
+        if !self.load_podcasts(None, level.parent_index).await {
+            self.podcast_channel = Some(level);
+        }
+    }
+    /// The listed episodes the server can stream
+    pub fn podcast_episodes(&self) -> Vec<Track> {
+        self.podcast_tab
+            .data
+            .iter()
+            .filter_map(|e| match e {
+                PodcastEntry::Episode(episode) if episode.is_downloaded() => {
+                    Some(episode.track.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+    /// The selected episode, or the streamable episodes of the selected channel
+    pub async fn selected_podcast_tracks(&mut self) -> Result<Vec<Track>> {
+        let entries = match self.podcast_tab.get().cloned() {
+            Some(PodcastEntry::Episode(episode)) if episode.is_downloaded() => {
+                return Ok(vec![episode.track]);
+            }
+            Some(PodcastEntry::Episode(episode)) => {
+                self.set_notification(format!(
+                    "'{}' is {} on the server",
+                    episode.track.title,
+                    episode.status_label()
+                ));
+                return Ok(Vec::new());
+            }
+            Some(PodcastEntry::Channel { id, .. }) => {
+                self.subsonic_client.get_podcast_episodes(&id).await?
+            }
+            Some(PodcastEntry::Newest) => {
+                self.subsonic_client
+                    .get_newest_podcasts(NEWEST_EPISODES)
+                    .await?
+            }
+            None => Vec::new(),
+        };
+        Ok(entries
+            .into_iter()
+            .filter_map(|e| match e {
+                PodcastEntry::Episode(episode) if episode.is_downloaded() => Some(episode.track),
+                _ => None,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use futures::future;
+use tokio::sync::mpsc;
+
+use crate::app::{ActiveSection, ActiveTab, Artist, Track};
+use crate::config::SearchMode;
+use crate::subsonic::SubsonicClient;
+
+use super::App;
+
+/// Tracks after the playing one whose stream urls are resolved ahead of time
+const PREFETCH_AHEAD: usize = 10;
+/// How long an artist has to stay selected before its songs are fetched
+const ARTIST_SETTLE: Duration = Duration::from_millis(300);
+/// Artists whose songs are kept, the oldest is dropped past this
+const ARTIST_CACHE_SIZE: usize = 16;
+
+/// Every song of `artist`, album by album
+async fn fetch_artist_songs(client: &SubsonicClient, artist: &Artist) -> Result<Vec<Track>> {
+    let albums = client.get_artist_albums(artist).await?;
+    let songs_futures = albums.iter().map(|album| client.get_songs_in_album(album));
+    Ok(future::try_join_all(songs_futures)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+impl App {
+    /// Check the stream urls of the upcoming queue in the background, so moving on to the next
+    /// track skips the round trip. Local search mode builds urls without asking the server
+    pub fn prefetch_stream_urls(&mut self) {
+        if !matches!(self.search_mode, SearchMode::Remote)
+            || self.offline
+            || self.stream_url_rx.is_some()
+        {
+            return;
+        }
+        let ids: Vec<String> = self
+            .queue_tab
+            .data
+            .iter()
+            .skip(self.playing_index + 1)
+            .take(PREFETCH_AHEAD)
+            .filter(|t| !self.stream_urls.contains_key(&t.id))
+            .filter(|t| !self.offline_cache.contains(&t.id))
+            .map(|t| t.id.clone())
+            .collect();
+        if ids.is_empty() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel(PREFETCH_AHEAD);
+        self.stream_url_rx = Some(rx);
+        let client = self.subsonic_client.clone();
+        tokio::spawn(async move {
+            for id in ids {
+                match client.get_stream_url_with_retry(&id, 5).await {
+                    Ok(url) => {
+                        if tx.send((id, url)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => log::debug!("Failed to resolve the stream of {}: {}", id, e),
+                }
+            }
+        });
+    }
+    /// Fetch the songs of the selected artist once it has been selected for a moment. Moving
+    /// on drops the fetch in flight, so scrolling through the list doesn't hit the server
+    pub fn prefetch_artist_songs(&mut self) {
+        let selected = match (&self.active_section, &self.active_tab) {
+            (ActiveSection::Others, ActiveTab::Artists) if !self.offline => {
+                self.artist_tab.get().cloned()
+            }
+            _ => None,
+        };
+        let Some(artist) = selected else {
+            self.artist_selection = None;
+            return;
+        };
+        let settled = match &self.artist_selection {
+            Some((id, at)) if *id == artist.id => at.elapsed() >= ARTIST_SETTLE,
+            _ => {
+                self.cancel_artist_prefetch();
+                self.artist_selection = Some((artist.id.clone(), Instant::now()));
+                return;
+            }
+        };
+        if !settled
+            || self.artist_songs_task.is_some()
+            || self.artist_songs.iter().any(|(id, _)| *id == artist.id)
+        {
+            return;
+        }
+        let (tx, rx) = mpsc::channel(1);
+        self.artist_songs_rx = Some(rx);
+        let client = self.subsonic_client.clone();
+        self.artist_songs_task = Some(tokio::spawn(async move {
+            match fetch_artist_songs(&client, &artist).await {
+                Ok(songs) => {
+                    let _ = tx.send((artist.id, songs)).await;
+                }
+                Err(e) => log::debug!("Failed to prefetch the songs of {}: {}", artist.name, e),
+            }
+        }));
+    }
+    fn cancel_artist_prefetch(&mut self) {
+        if let Some(task) = self.artist_songs_task.take() {
+            task.abort();
+        }
+        self.artist_songs_rx = None;
+    }
+    pub fn receive_artist_songs(&mut self) {
+        let Some(rx) = &mut self.artist_songs_rx else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok((id, songs)) => self.cache_artist_songs(id, songs),
+            Err(mpsc::error::TryRecvError::Empty) => return,
+            Err(mpsc::error::TryRecvError::Disconnected) => {}
+        }
+        // The finished task stays until the selection moves, so a failed fetch isn't retried
+        self.artist_songs_rx = None;
+    }
+    fn cache_artist_songs(&mut self, id: String, songs: Vec<Track>) {
+        self.artist_songs.retain(|(cached, _)| *cached != id);
+        if self.artist_songs.len() >= ARTIST_CACHE_SIZE {
+            self.artist_songs.remove(0);
+        }
+        self.artist_songs.push((id, songs));
+    }
+    /// Forget the cached artists, e.g. after the library was loaded again
+    pub fn clear_artist_songs(&mut self) {
+        self.cancel_artist_prefetch();
+        self.artist_songs.clear();
+        self.artist_selection = None;
+    }
+    /// The songs of `artist`, from the cache when the selection already fetched them
+    pub async fn artist_songs(&mut self, artist: &Artist) -> Result<Vec<Track>> {
+        if let Some((_, songs)) = self.artist_songs.iter().find(|(id, _)| *id == artist.id) {
+            return Ok(songs.clone());
+        }
+        let songs = fetch_artist_songs(&self.subsonic_client, artist).await?;
+        self.cache_artist_songs(artist.id.clone(), songs.clone());
+        Ok(songs)
+    }
+    pub fn receive_stream_urls(&mut self) {
+        let Some(rx) = &mut self.stream_url_rx else {
+            return;
+        };
+        loop {
+            match rx.try_recv() {
+                Ok((id, url)) => {
+                    self.stream_urls.insert(id, url);
+                }
+                Err(mpsc::error::TryRecvError::Empty) => return,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    self.stream_url_rx = None;
+                    return;
+                }
+            }
+        }
+    }
+}
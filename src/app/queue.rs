@@ -1,19 +1,67 @@
 use super::App;
-use crate::app::{ActiveSection, ActiveTab, Track};
+use crate::app::{ActiveSection, ActiveTab, FolderEntry, Track};
 use anyhow::Result;
-use futures::future;
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
 
 impl App {
     pub fn add_search_result_to_queue(&mut self) {
         if let Some(track) = self.search_tab.data.get(self.search_tab.index).cloned() {
-            self.queue_tab.data.push(track);
+            self.append_to_queue(vec![track]);
         }
     }
-    // Add this function to UI
-    pub fn _add_all_search_result_to_queue(&mut self) {
-        self.queue_tab.data.extend(self.search_tab.data.clone());
-    }
 
+    /// Add tracks to the end of the queue, skipping ones already queued when `dedupe_queue` is set.
+    /// Only appends, so the playing position never moves. Returns how many were added
+    pub fn append_to_queue(&mut self, tracks: impl IntoIterator<Item = Track>) -> usize {
+        let before = self.queue_tab.data.len();
+        if !self.config.playback.dedupe_queue {
+            self.queue_tab.data.extend(tracks);
+            return self.queue_tab.data.len() - before;
+        }
+        let mut queued: HashSet<String> =
+            self.queue_tab.data.iter().map(|t| t.id.clone()).collect();
+        let mut skipped = 0;
+        for track in tracks {
+            if queued.insert(track.id.clone()) {
+                self.queue_tab.data.push(track);
+            } else {
+                skipped += 1;
+            }
+        }
+        if skipped > 0 {
+            self.set_notification(format!("Skipped {} track(s) already in the queue", skipped));
+        }
+        self.queue_tab.data.len() - before
+    }
+    /// Shuffle the queue once, without turning shuffle mode on. The playing track moves to the
+    /// front so the rest of the queue plays after it in the new order
+    pub fn shuffle_queue_once(&mut self) {
+        if self.queue_tab.data.len() < 2 {
+            self.set_notification("Nothing to shuffle");
+            return;
+        }
+        self.queue_tab.clear_filter();
+        let playing_from_queue = self.radio.is_none()
+            && self.current_track.as_ref().is_some_and(|track| {
+                self.queue_tab
+                    .data
+                    .get(self.playing_index)
+                    .is_some_and(|queued| queued.id == track.id)
+            });
+        let mut rng = rand::thread_rng();
+        if playing_from_queue {
+            self.queue_tab.data.swap(0, self.playing_index);
+            self.queue_tab.data[1..].shuffle(&mut rng);
+        } else {
+            self.queue_tab.data.shuffle(&mut rng);
+        }
+        self.playing_index = 0;
+        self.queue_tab.marked.clear();
+        self.queue_tab.select(0);
+        self.queue_edited();
+        self.set_notification("Queue shuffled");
+    }
     pub fn find_selected(&self) -> usize {
         match (&self.active_section, &self.active_tab) {
             (ActiveSection::Queue, _) => self.queue_tab.index,
@@ -23,92 +71,173 @@ impl App {
             (ActiveSection::Others, ActiveTab::Playlist) => self.playlist_tab.index,
             (ActiveSection::Others, ActiveTab::Favorites) => self.favorite_tab.index,
             (ActiveSection::Others, ActiveTab::Search) => self.search_tab.index,
+            (ActiveSection::Others, ActiveTab::Folders) => self.folder_tab.index,
+            (ActiveSection::Others, ActiveTab::Podcasts) => self.podcast_tab.index,
         }
     }
-    pub async fn _add_to_queue(&mut self) -> Result<()> {
+    /// The track under the cursor, if the active list holds tracks
+    pub fn selected_track(&self) -> Option<Track> {
         match (&self.active_section, &self.active_tab) {
-            (ActiveSection::Queue, _) => (),
+            (ActiveSection::Queue, _) => self.queue_tab.data.get(self.queue_tab.index).cloned(),
             (ActiveSection::Others, ActiveTab::Songs) => {
-                self.queue_tab.data.extend(self.tracks_tab.get().cloned());
+                self.tracks_tab.data.get(self.tracks_tab.index).cloned()
             }
             (ActiveSection::Others, ActiveTab::Search) => {
-                self.queue_tab.data.extend(self.search_tab.get().cloned());
+                self.search_tab.data.get(self.search_tab.index).cloned()
             }
             (ActiveSection::Others, ActiveTab::Favorites) => {
-                self.queue_tab.data.extend(self.favorite_tab.get().cloned());
-            }
-            (ActiveSection::Others, ActiveTab::Albums) => {
-                let album = self.album_tab.get().cloned().unwrap();
-                let songs = self.subsonic_client.get_songs_in_album(&album).await?;
-                self.queue_tab.data.extend(songs);
+                self.favorite_tab.data.get(self.favorite_tab.index).cloned()
             }
-            (ActiveSection::Others, ActiveTab::Artists) => {
-                let artist = self.artist_tab.get().unwrap();
-                let artist_albums = self.subsonic_client.get_artist_albums(artist).await?;
-                if !artist_albums.is_empty() {
-                    let songs_futures = artist_albums
-                        .iter()
-                        .map(|album| self.subsonic_client.get_songs_in_album(album));
-                    let nested_songs = future::try_join_all(songs_futures).await?;
-                    let songs: Vec<Track> = nested_songs.into_iter().flatten().collect();
-
-                    if !songs.is_empty() {
-                        self.queue_tab.data.extend(songs);
-                    }
+            (ActiveSection::Others, ActiveTab::Folders) => {
+                match self.folder_tab.data.get(self.folder_tab.index) {
+                    Some(FolderEntry::Song(track)) => Some(track.as_ref().clone()),
+                    _ => None,
                 }
             }
-            (ActiveSection::Others, ActiveTab::Playlist) => {
-                let playlist = self.playlist_tab.get().unwrap();
-                let songs = self
-                    .subsonic_client
-                    .get_songs_from_playlist(playlist)
-                    .await?;
-                self.queue_tab.data.extend(songs);
-            }
+            _ => None,
         }
+    }
+    pub async fn _add_to_queue(&mut self) -> Result<()> {
+        let tracks = self.tracks_to_queue(false).await?;
+        self.append_to_queue(tracks);
         Ok(())
     }
-    pub async fn make_favorite(&mut self, remove: bool) -> Result<()> {
-        match (&self.active_section, &self.active_tab) {
-            (ActiveSection::Queue, _) => {
-                if !self.queue_tab.data.is_empty() {
-                    let song = self.queue_tab.get().unwrap();
-                    self.subsonic_client.favorite_a_song(song, remove).await?;
-                    let msg = if remove {
-                        format!("Removed '{}' from favorites", song.title)
-                    } else {
-                        format!("Added '{}' to favorites", song.title)
-                    };
-                    self.set_notification(msg);
-                }
+    /// Queue everything the current view lists, not just the row under the cursor
+    pub async fn add_view_to_queue(&mut self) -> Result<()> {
+        if self.active_section == ActiveSection::Queue {
+            self.set_notification("Already in the queue");
+            return Ok(());
+        }
+        let tracks = self.tracks_to_queue(true).await?;
+        let added = self.append_to_queue(tracks);
+        self.set_notification(format!("Added {} track(s) to the queue", added));
+        Ok(())
+    }
+    /// Tracks behind the selection, or behind every row of a track list when `whole_view` is set.
+    /// Albums, artists and playlists always resolve the selected entry
+    async fn tracks_to_queue(&mut self, whole_view: bool) -> Result<Vec<Track>> {
+        let tracks = match (&self.active_section, &self.active_tab) {
+            (ActiveSection::Queue, _) => Vec::new(),
+            (ActiveSection::Others, ActiveTab::Songs) if whole_view => self.tracks_tab.data.clone(),
+            (ActiveSection::Others, ActiveTab::Search) if whole_view => {
+                self.search_tab.data.clone()
+            }
+            (ActiveSection::Others, ActiveTab::Favorites) if whole_view => {
+                self.favorite_tab.data.clone()
             }
+            (ActiveSection::Others, ActiveTab::Folders) if whole_view => self.folder_songs(),
+            (ActiveSection::Others, ActiveTab::Folders) => self.selected_folder_tracks().await?,
+            (ActiveSection::Others, ActiveTab::Podcasts) if whole_view => self.podcast_episodes(),
+            (ActiveSection::Others, ActiveTab::Podcasts) => self.selected_podcast_tracks().await?,
             (ActiveSection::Others, ActiveTab::Songs) => {
-                if !self.tracks_tab.data.is_empty() {
-                    let song = self.tracks_tab.get().unwrap();
-                    self.subsonic_client.favorite_a_song(song, remove).await?;
-                    let msg = if remove {
-                        format!("Removed '{}' from favorites", song.title)
-                    } else {
-                        format!("Added '{}' to favorites", song.title)
-                    };
-                    self.set_notification(msg);
-                }
+                self.tracks_tab.get().cloned().into_iter().collect()
             }
             (ActiveSection::Others, ActiveTab::Search) => {
-                if !self.search_tab.data.is_empty() {
-                    let song = self.search_tab.get().unwrap();
-                    self.subsonic_client.favorite_a_song(song, remove).await?;
-                    let msg = if remove {
-                        format!("Removed '{}' from favorites", song.title)
-                    } else {
-                        format!("Added '{}' to favorites", song.title)
-                    };
-                    self.set_notification(msg);
-                }
+                self.search_tab.get().cloned().into_iter().collect()
+            }
+            (ActiveSection::Others, ActiveTab::Favorites) => {
+                self.favorite_tab.get().cloned().into_iter().collect()
+            }
+            (ActiveSection::Others, ActiveTab::Albums) => match self.album_tab.get().cloned() {
+                Some(album) => self.subsonic_client.get_songs_in_album(&album).await?,
+                None => Vec::new(),
+            },
+            (ActiveSection::Others, ActiveTab::Artists) => {
+                let Some(artist) = self.artist_tab.get().cloned() else {
+                    return Ok(Vec::new());
+                };
+                self.artist_songs(&artist).await?
             }
-            _ => (),
+            (ActiveSection::Others, ActiveTab::Playlist) => match self.playlist_tab.get() {
+                Some(playlist) => {
+                    self.subsonic_client
+                        .get_songs_from_playlist(playlist)
+                        .await?
+                }
+                None => Vec::new(),
+            },
+        };
+        Ok(tracks)
+    }
+    pub async fn make_favorite(&mut self, remove: bool) -> Result<()> {
+        let song = match (&self.active_section, &self.active_tab) {
+            (ActiveSection::Queue, _)
+            | (ActiveSection::Others, ActiveTab::Songs)
+            | (ActiveSection::Others, ActiveTab::Search) => self.selected_track(),
+            _ => None,
         };
+        if let Some(song) = song {
+            self.subsonic_client.favorite_a_song(&song, remove).await?;
+            self.update_track(&song.id, |t| t.starred = !remove);
+            let msg = if remove {
+                format!("Removed '{}' from favorites", song.title)
+            } else {
+                format!("Added '{}' to favorites", song.title)
+            };
+            self.set_notification(msg);
+        }
+        self.favorite_tab.clear_filter();
         self.favorite_tab.data = self.subsonic_client.get_all_favorites().await?;
+        self.favorite_tab.marked.clear();
+        Ok(())
+    }
+    /// Star the playing track, or unstar it if it already is, whichever tab is shown
+    pub async fn toggle_favorite_playing(&mut self) -> Result<()> {
+        let Some(track) = self.current_track.clone() else {
+            self.set_notification("Nothing is playing");
+            return Ok(());
+        };
+        if self.radio.is_some() {
+            self.set_notification("A radio station can't be a favorite");
+            return Ok(());
+        }
+        let remove = track.starred;
+        self.subsonic_client.favorite_a_song(&track, remove).await?;
+        self.update_track(&track.id, |t| t.starred = !remove);
+        self.set_notification(if remove {
+            format!("Removed '{}' from favorites", track.title)
+        } else {
+            format!("Added '{}' to favorites", track.title)
+        });
+        self.favorite_tab.clear_filter();
+        self.favorite_tab.data = self.subsonic_client.get_all_favorites().await?;
+        self.favorite_tab.marked.clear();
+        Ok(())
+    }
+    /// Queue and play the selected artist's most played songs, also listing them in the Search tab
+    pub async fn play_artist_top_tracks(&mut self) -> Result<()> {
+        if self.active_section != ActiveSection::Others || self.active_tab != ActiveTab::Artists {
+            self.set_notification("Select an artist in the Artists tab for top tracks");
+            return Ok(());
+        }
+        let Some(artist) = self.artist_tab.get().cloned() else {
+            return Ok(());
+        };
+        let songs = match self.subsonic_client.get_top_songs(&artist.name, 50).await {
+            Ok(songs) if !songs.is_empty() => songs,
+            // Not every server has the endpoint or the data behind it
+            _ => {
+                let mut songs = self.artist_songs(&artist).await?;
+                songs.sort_by_key(|t| std::cmp::Reverse(t.play_count.unwrap_or(0)));
+                songs.truncate(50);
+                songs
+            }
+        };
+        let Some(first) = songs.first().cloned() else {
+            self.set_notification(format!("No songs found for {}", artist.name));
+            return Ok(());
+        };
+        self.search_tab.data = songs.clone();
+        self.search_tab.marked.clear();
+        self.search_tab.index = 0;
+        self.search_label = Some(format!("Top tracks: {}", artist.name));
+        self.clear_search_matches();
+        self.select_tab(ActiveTab::Search);
+        self.search_tab.select(0);
+        self.queue_tab.data = songs;
+        self.queue_tab.index = 0;
+        self.disable_shuffle();
+        self.start_playback(first, 0).await?;
         Ok(())
     }
 }
@@ -0,0 +1,65 @@
+use anyhow::Result;
+
+use crate::{
+    app::{PickerKind, RadioStation, Track},
+    mpris_handler::track_to_metadata,
+};
+
+use super::App;
+impl App {
+    /// List the server's radio stations, Enter tunes in to the chosen one
+    pub async fn open_radio_stations(&mut self) {
+        let stations = match self.subsonic_client.get_radio_stations().await {
+            Ok(stations) => stations,
+            Err(e) => {
+                self.set_notification(format!("Failed to load radio stations: {}", e));
+                return;
+            }
+        };
+        if stations.is_empty() {
+            self.set_notification("No radio stations on the server");
+            return;
+        }
+        let items = stations.iter().map(|s| s.name.clone()).collect();
+        self.open_picker(PickerKind::RadioStation { stations }, "Radio", items);
+    }
+    /// Stream a station straight from its url. The queue is left alone, playing a track from
+    /// it ends the radio again
+    pub async fn play_radio_station(&mut self, station: RadioStation) -> Result<()> {
+        self.pending_playback = None;
+        self.loading_track = false;
+        let loaded = {
+            let mut player = self.player.lock().await;
+            player.set_gain(1.0);
+            match player.load_live_url(&station.stream_url).await {
+                Ok(()) => player.play(),
+                Err(e) => Err(e),
+            }
+        };
+        if let Err(e) = loaded {
+            self.set_notification(format!("Could not play {}: {}", station.name, e));
+            return Ok(());
+        }
+        let track = station_track(&station);
+        self.radio = Some(station);
+        self.is_playing = true;
+        self.track_started = Some(std::time::Instant::now());
+        self.deferred_finish = None;
+        self.current_track = Some(track.clone());
+        self.metadata = track_to_metadata(&track);
+        self.load_cover_art_for_track(&track).await;
+        self.notify_now_playing(&track);
+        self.sync_mpris().await;
+        Ok(())
+    }
+}
+
+/// What the header and MPRIS show for a station. It has no length and is never scrobbled
+fn station_track(station: &RadioStation) -> Track {
+    Track {
+        id: format!("radio-{}", station.id),
+        title: station.name.clone(),
+        artist: "Internet radio".to_string(),
+        ..Default::default()
+    }
+}
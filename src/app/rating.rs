@@ -0,0 +1,77 @@
+use crate::app::Track;
+
+use super::App;
+impl App {
+    /// Rate the selected track, or the playing one when the list has no tracks. 0 clears it
+    pub async fn rate_track(&mut self, rating: u8) {
+        let Some(track) = self.selected_track().or_else(|| self.current_track.clone()) else {
+            self.set_notification("Select a track to rate");
+            return;
+        };
+        match self.subsonic_client.set_rating(&track.id, rating).await {
+            Ok(()) => {
+                let rating = (rating > 0).then_some(rating as i32);
+                self.update_track(&track.id, |t| t.rating = rating);
+                let msg = match rating {
+                    Some(stars) => format!("Rated '{}' {}", track.title, stars_label(stars)),
+                    None => format!("Cleared the rating of '{}'", track.title),
+                };
+                self.set_notification(msg);
+            }
+            Err(e) => self.set_notification(format!("Failed to rate '{}': {}", track.title, e)),
+        }
+    }
+    /// Refetch one track in the background so its play count, rating and star match the
+    /// server, without reloading the library
+    pub fn refresh_track(&self, id: &str) {
+        let client = self.subsonic_client.clone();
+        let tx = self.refresh_tx.clone();
+        let id = id.to_string();
+        tokio::spawn(async move {
+            match client.get_song(&id).await {
+                Ok(fresh) => {
+                    let _ = tx.send(fresh).await;
+                }
+                Err(e) => log::warn!("Failed to refresh track {}: {}", id, e),
+            }
+        });
+    }
+    /// Take in what `refresh_track` fetched. Only what the server counts is copied, the rest of
+    /// the loaded track (e.g. a transcoded format) stays
+    pub fn receive_refreshed_tracks(&mut self) {
+        while let Ok(fresh) = self.refresh_rx.try_recv() {
+            self.update_track(&fresh.id, |t| {
+                t.play_count = fresh.play_count;
+                t.rating = fresh.rating;
+                t.starred = fresh.starred;
+            });
+            self.needs_redraw = true;
+        }
+    }
+    /// Apply a change to every loaded copy of a track, so lists and the header agree without
+    /// reloading the library
+    pub fn update_track(&mut self, id: &str, update: impl Fn(&mut Track)) {
+        let lists = [
+            &mut self.queue_tab,
+            &mut self.tracks_tab,
+            &mut self.search_tab,
+            &mut self.favorite_tab,
+        ];
+        for track in lists
+            .into_iter()
+            .flat_map(|tab| tab.rows_mut())
+            .filter(|t| t.id == id)
+        {
+            update(track);
+        }
+        if let Some(track) = self.current_track.as_mut().filter(|t| t.id == id) {
+            update(track);
+        }
+    }
+}
+
+/// Filled and empty stars out of five
+pub fn stars_label(rating: i32) -> String {
+    let filled = rating.clamp(0, 5) as usize;
+    format!("{}{}", "★".repeat(filled), "☆".repeat(5 - filled))
+}
@@ -0,0 +1,104 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use crate::app::Track;
+
+use super::App;
+
+/// A fractional threshold never waits longer than this, as Last.fm has it
+const SCROBBLE_MAX_SECS: i64 = 240;
+
+impl App {
+    /// Scrobble the playing track once it played past `scrobble_threshold`, so skipping near
+    /// the end still counts and skipping early doesn't
+    pub async fn check_scrobble(&mut self) {
+        if self.scrobbled_current || !self.is_playing || self.loading_track || self.radio.is_some()
+        {
+            return;
+        }
+        let Some(track) = self.current_track.clone() else {
+            return;
+        };
+        let duration_secs = track.duration / 1_000_000;
+        if duration_secs <= 0 {
+            return;
+        }
+        let position = self.player.lock().await.get_position();
+        let threshold = scrobble_point(self.config.playback.scrobble_threshold, duration_secs);
+        if position.as_secs() >= threshold {
+            let played = Duration::from_micros(position.as_micros().max(0) as u64);
+            // Runs on every update, a server that can't be reached is no reason to stop
+            if let Err(e) = self.scrobble_current(&track, played).await {
+                log::warn!("Failed to scrobble '{}': {}", track.title, e);
+            }
+        }
+    }
+    /// Submit the playing track to the server and Last.fm, once per playback
+    pub async fn scrobble_current(&mut self, track: &Track, played: Duration) -> Result<()> {
+        self.scrobbled_current = true;
+        self.lastfm_scrobble(track, played);
+        self.subsonic_client.scrobble(track, true).await
+    }
+    /// Tell Last.fm what started playing, if direct scrobbling is set up
+    pub fn lastfm_now_playing(&self, track: &Track) {
+        let Some(lastfm) = self.lastfm.clone() else {
+            return;
+        };
+        let track = track.clone();
+        let tx = self.lastfm_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = lastfm.update_now_playing(&track).await {
+                let _ = tx.send(e.to_string()).await;
+            }
+        });
+    }
+    /// Scrobble a track that has been playing for `played`. Last.fm ignores tracks of 30
+    /// seconds or less
+    pub fn lastfm_scrobble(&self, track: &Track, played: Duration) {
+        let Some(lastfm) = self.lastfm.clone() else {
+            return;
+        };
+        let duration_secs = track.duration / 1_000_000;
+        if duration_secs <= 30 {
+            return;
+        }
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+            .saturating_sub(played.as_secs());
+        let track = track.clone();
+        let tx = self.lastfm_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = lastfm.scrobble(&track, started_at).await {
+                let _ = tx.send(e.to_string()).await;
+            }
+        });
+    }
+}
+
+/// Seconds into a track of `duration_secs` at which it counts as played
+fn scrobble_point(threshold: f64, duration_secs: i64) -> i64 {
+    if threshold <= 1.0 {
+        ((duration_secs as f64 * threshold.max(0.0)) as i64).min(SCROBBLE_MAX_SECS)
+    } else {
+        (threshold as i64).min(duration_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrobble_point_fraction_is_capped() {
+        assert_eq!(scrobble_point(0.5, 200), 100);
+        assert_eq!(scrobble_point(0.5, 1200), SCROBBLE_MAX_SECS);
+        assert_eq!(scrobble_point(1.0, 200), 200);
+    }
+    #[test]
+    fn test_scrobble_point_seconds_stop_at_the_end() {
+        assert_eq!(scrobble_point(120.0, 300), 120);
+        assert_eq!(scrobble_point(120.0, 90), 90);
+    }
+}
@@ -2,96 +2,149 @@ use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 use crate::{
-    app::{ActiveSection, ActiveTab, InputMode},
+    app::{ActiveSection, ActiveTab, FolderEntry, InputMode, PodcastEntry, Track},
     config::SearchMode,
+    search::normalize_for_match,
 };
 
 use super::App;
+
+/// How far Alt+Up/Down move the fuzzy threshold
+const FUZZY_THRESHOLD_STEP: i64 = 5;
 impl App {
     pub fn start_inline_search(&mut self) {
         self.input_mode = InputMode::InlineSearch;
         self.search_query.clear();
+        self.inline_matches.clear();
     }
     pub fn exit_inline_search(&mut self) {
         self.input_mode = InputMode::Normal;
         self.search_query.clear();
+        self.inline_matches.clear();
     }
     pub fn inline_search_input(&mut self, c: char) {
         if self.input_mode == InputMode::InlineSearch {
             self.search_query.push(c);
+            self.refresh_inline_matches();
             self.jump_to_inline_match();
         }
     }
     pub fn inline_search_backspace(&mut self) {
         if self.input_mode == InputMode::InlineSearch {
             self.search_query.pop();
+            self.refresh_inline_matches();
             self.jump_to_inline_match();
         }
     }
 
-    pub fn jump_to_inline_match(&mut self) {
-        if self.search_query.is_empty() {
-            return;
+    /// Match the find query against the active list again, once per change to either rather
+    /// than on every redraw
+    pub fn refresh_inline_matches(&mut self) {
+        if self.input_mode == InputMode::InlineSearch {
+            self.inline_matches = self.list_matches(&self.search_query);
+        }
+    }
+    /// Positions in the active list whose title, artist, album or name contains `query`
+    pub fn list_matches(&self, query: &str) -> Vec<usize> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let query = normalize_for_match(query);
+        let matches = |text: &str| normalize_for_match(text).contains(&query);
+        let track_matches =
+            |t: &Track| matches(&t.title) || matches(&t.artist) || matches(&t.album);
+        fn positions<T>(data: &[T], matches: impl Fn(&T) -> bool) -> Vec<usize> {
+            data.iter()
+                .enumerate()
+                .filter(|(_, item)| matches(item))
+                .map(|(i, _)| i)
+                .collect()
         }
-        let query = self.search_query.to_lowercase();
         match self.active_section {
-            ActiveSection::Queue => {
-                if let Some(idx) = self.queue_tab.data.iter().position(|t| {
-                    t.title.to_lowercase().contains(&query)
-                        || t.artist.to_lowercase().contains(&query)
-                }) {
-                    self.queue_tab.select(idx);
-                }
-            }
+            ActiveSection::Queue => positions(&self.queue_tab.data, track_matches),
             ActiveSection::Others => match self.active_tab {
-                ActiveTab::Songs => {
-                    if let Some(idx) = self.tracks_tab.data.iter().position(|t| {
-                        t.title.to_lowercase().contains(&query)
-                            || t.artist.to_lowercase().contains(&query)
-                    }) {
-                        self.tracks_tab.select(idx);
-                    }
-                }
-                ActiveTab::Favorites => {
-                    if let Some(idx) = self.favorite_tab.data.iter().position(|a| {
-                        a.title.to_lowercase().contains(&query)
-                            || a.artist.to_lowercase().contains(&query)
-                    }) {
-                        self.favorite_tab.select(idx);
-                    }
-                }
-                ActiveTab::Artists => {
-                    if let Some(idx) = self
-                        .artist_tab
-                        .data
-                        .iter()
-                        .position(|a| a.name.to_lowercase().contains(&query))
-                    {
-                        self.artist_tab.select(idx);
-                    }
-                }
-                ActiveTab::Albums => {
-                    if let Some(idx) = self.album_tab.data.iter().position(|a| {
-                        a.name.to_lowercase().contains(&query)
-                            || a.artist.to_lowercase().contains(&query)
-                    }) {
-                        self.album_tab.select(idx);
-                    }
-                }
-                ActiveTab::Playlist => {
-                    if let Some(idx) = self
-                        .playlist_tab
-                        .data
-                        .iter()
-                        .position(|p| p.name.to_lowercase().contains(&query))
-                    {
-                        self.playlist_tab.select(idx);
-                    }
-                }
+                ActiveTab::Songs => positions(&self.tracks_tab.data, track_matches),
+                ActiveTab::Favorites => positions(&self.favorite_tab.data, track_matches),
+                ActiveTab::Artists => positions(&self.artist_tab.data, |a| matches(&a.name)),
+                ActiveTab::Albums => positions(&self.album_tab.data, |a| {
+                    matches(&a.name) || matches(&a.artist)
+                }),
+                ActiveTab::Playlist => positions(&self.playlist_tab.data, |p| matches(&p.name)),
+                ActiveTab::Folders => positions(&self.folder_tab.data, |e| match e {
+                    FolderEntry::Directory { name, .. } => matches(name),
+                    FolderEntry::Song(track) => track_matches(track),
+                }),
+                ActiveTab::Podcasts => positions(&self.podcast_tab.data, |e| match e {
+                    PodcastEntry::Newest => false,
+                    PodcastEntry::Channel { title, .. } => matches(title),
+                    PodcastEntry::Episode(episode) => track_matches(&episode.track),
+                }),
+                ActiveTab::Search => Vec::new(),
+            },
+        }
+    }
+    fn inline_search_index(&self) -> usize {
+        match self.active_section {
+            ActiveSection::Queue => self.queue_tab.index,
+            ActiveSection::Others => match self.active_tab {
+                ActiveTab::Songs => self.tracks_tab.index,
+                ActiveTab::Favorites => self.favorite_tab.index,
+                ActiveTab::Artists => self.artist_tab.index,
+                ActiveTab::Albums => self.album_tab.index,
+                ActiveTab::Playlist => self.playlist_tab.index,
+                ActiveTab::Folders => self.folder_tab.index,
+                ActiveTab::Podcasts => self.podcast_tab.index,
+                ActiveTab::Search => self.search_tab.index,
+            },
+        }
+    }
+    fn select_inline_match(&mut self, idx: usize) {
+        match self.active_section {
+            ActiveSection::Queue => self.queue_tab.select(idx),
+            ActiveSection::Others => match self.active_tab {
+                ActiveTab::Songs => self.tracks_tab.select(idx),
+                ActiveTab::Favorites => self.favorite_tab.select(idx),
+                ActiveTab::Artists => self.artist_tab.select(idx),
+                ActiveTab::Albums => self.album_tab.select(idx),
+                ActiveTab::Playlist => self.playlist_tab.select(idx),
+                ActiveTab::Folders => self.folder_tab.select(idx),
+                ActiveTab::Podcasts => self.podcast_tab.select(idx),
                 ActiveTab::Search => {}
             },
         }
     }
+    pub fn jump_to_inline_match(&mut self) {
+        if let Some(&idx) = self.inline_matches.first() {
+            self.select_inline_match(idx);
+        }
+    }
+    /// Move to the next (or previous) match after the selection, wrapping around the list
+    pub fn cycle_inline_match(&mut self, forward: bool) {
+        let matches = &self.inline_matches;
+        let current = self.inline_search_index();
+        let target = if forward {
+            matches
+                .iter()
+                .find(|&&i| i > current)
+                .or_else(|| matches.first())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|&&i| i < current)
+                .or_else(|| matches.last())
+        };
+        if let Some(&idx) = target {
+            self.select_inline_match(idx);
+        }
+    }
+    /// Which match is selected and how many there are, for the find bar
+    pub fn inline_match_position(&self) -> Option<(usize, usize)> {
+        let matches = &self.inline_matches;
+        let current = self.inline_search_index();
+        let position = matches.iter().position(|&i| i == current)?;
+        Some((position + 1, matches.len()))
+    }
     pub async fn handle_inline_search_input(&mut self, key: KeyEvent) -> Result<bool> {
         match key.code {
             KeyCode::Esc | KeyCode::Enter => {
@@ -100,6 +153,14 @@ impl App {
             KeyCode::Backspace => {
                 self.inline_search_backspace();
             }
+            KeyCode::Down => self.cycle_inline_match(true),
+            KeyCode::Up => self.cycle_inline_match(false),
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.cycle_inline_match(true);
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.cycle_inline_match(false);
+            }
             KeyCode::Char(c) => {
                 self.inline_search_input(c);
             }
@@ -108,6 +169,8 @@ impl App {
         Ok(false)
     }
     pub fn enter_search_mode(&mut self) {
+        self.search_label = None;
+        self.clear_search_matches();
         self.input_mode = InputMode::Search;
         self.active_tab = ActiveTab::Search;
         self.search_query.clear();
@@ -119,57 +182,155 @@ impl App {
     }
     pub fn search_input(&mut self, c: char) {
         if self.input_mode == InputMode::Search {
+            self.cancel_remote_search();
             self.search_query.push(c);
             self.is_searching = true
         }
     }
     pub fn search_backspace(&mut self) {
         if self.input_mode == InputMode::Search {
+            self.cancel_remote_search();
             self.search_query.pop();
             self.is_searching = true;
         }
     }
     pub fn search_clear(&mut self) {
+        self.cancel_remote_search();
+        self.is_searching = false;
         self.search_query.clear();
+        self.clear_search_matches();
         self.search_tab.index = 0;
         self.search_tab.clear();
     }
+    /// Drop the search and its results and go back to the Songs tab
+    pub fn leave_search(&mut self) {
+        self.search_clear();
+        self.search_tab.data.clear();
+        self.search_tab.marked.clear();
+        self.search_label = None;
+        self.input_mode = InputMode::Normal;
+        self.active_section = ActiveSection::Others;
+        self.select_tab(ActiveTab::Songs);
+    }
     pub async fn perform_search(&mut self) -> Result<()> {
+        self.cancel_remote_search();
+        self.clear_search_matches();
         if self.search_query.is_empty() {
             self.search_tab.clear();
             self.is_searching = false;
             return Ok(());
         }
-        match self.config.search.mode {
+        self.search_label = None;
+        match self.search_mode {
             SearchMode::Local => {
                 self.perform_local_search();
             }
+            SearchMode::Remote if self.offline => {
+                self.set_notification("Offline, searching the loaded library instead");
+                self.perform_local_search();
+            }
             SearchMode::Remote => {
-                self.perform_remote_search().await?;
+                // Results come in through `receive_search_results`
+                self.start_remote_search();
+                return Ok(());
             }
         }
         self.is_searching = false;
+        self.show_search_results();
+        Ok(())
+    }
+    /// Nudge the local search threshold by `delta` for this run and search again with it
+    pub async fn adjust_fuzzy_threshold(&mut self, delta: i64) -> Result<()> {
+        let threshold = (self.search_engine.threshold() + delta).max(0);
+        self.search_engine.set_threshold(threshold);
+        match self.search_mode {
+            SearchMode::Local => self.perform_search().await?,
+            SearchMode::Remote => {
+                self.set_notification("The threshold only applies to local search");
+            }
+        }
+        Ok(())
+    }
+    fn show_search_results(&mut self) {
         self.search_tab.index = 0;
         if !self.search_tab.data.is_empty() {
             self.search_tab.select(0);
         } else {
             self.search_tab.clear();
         }
-        Ok(())
+        self.prefetch_search_covers();
+    }
+    /// Switch between searching the loaded library and asking the server, and search again
+    pub async fn toggle_search_mode(&mut self) -> Result<()> {
+        self.search_mode = match self.search_mode {
+            SearchMode::Local => SearchMode::Remote,
+            SearchMode::Remote => SearchMode::Local,
+        };
+        self.perform_search().await
     }
     /// Perform local fuzzy search on loaded tracks
     fn perform_local_search(&mut self) {
         let results = self
             .search_engine
             .search(&self.search_query, &self.tracks_tab.data);
-        self.search_tab.data = results.into_iter().map(|r| r.track).collect();
+        self.search_tab.data = results.into_iter().map(|r| r.track.clone()).collect();
+        self.search_tab.marked.clear();
+        self.search_albums = self
+            .search_engine
+            .search_albums(&self.search_query, self.album_tab.all_rows())
+            .into_iter()
+            .map(|r| r.album)
+            .collect();
+        self.search_artists = self
+            .search_engine
+            .search_artists(&self.search_query, self.artist_tab.all_rows())
+            .into_iter()
+            .map(|r| r.artist)
+            .collect();
+    }
+    pub fn clear_search_matches(&mut self) {
+        self.search_albums.clear();
+        self.search_artists.clear();
     }
 
     /// Perform remote search using subsonic api. This is useful for when you have a proxy in
-    /// between to search for missing songs
-    async fn perform_remote_search(&mut self) -> Result<()> {
-        self.search_tab.data = self.subsonic_client.search(&self.search_query).await?;
-        Ok(())
+    /// between to search for missing songs. It runs in the background so a slow server doesn't
+    /// hold up the ui, `is_searching` stays set until the results arrive
+    fn start_remote_search(&mut self) {
+        let generation = self.search_generation;
+        let query = self.search_query.clone();
+        let client = self.subsonic_client.clone();
+        let tx = self.search_tx.clone();
+        self.search_task = Some(tokio::spawn(async move {
+            let result = client.search(&query).await;
+            let _ = tx.send((generation, result)).await;
+        }));
+    }
+    /// Stop waiting on the server search in flight, whatever it still sends is dropped
+    pub fn cancel_remote_search(&mut self) {
+        if let Some(task) = self.search_task.take() {
+            task.abort();
+        }
+        self.search_generation = self.search_generation.wrapping_add(1);
+    }
+    pub fn receive_search_results(&mut self) {
+        while let Ok((generation, result)) = self.search_rx.try_recv() {
+            // A newer keystroke or search already replaced this one
+            if generation != self.search_generation {
+                continue;
+            }
+            self.search_task = None;
+            self.is_searching = false;
+            self.needs_redraw = true;
+            match result {
+                Ok(tracks) => {
+                    self.search_tab.data = tracks;
+                    self.search_tab.marked.clear();
+                    self.show_search_results();
+                }
+                Err(e) => self.set_notification(format!("Search failed: {}", e)),
+            }
+        }
     }
     pub async fn handle_search_input(&mut self, key: KeyEvent) -> Result<bool> {
         match key.code {
@@ -190,6 +351,15 @@ impl App {
             KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 self.add_search_result_to_queue();
             }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.toggle_search_mode().await?;
+            }
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.adjust_fuzzy_threshold(FUZZY_THRESHOLD_STEP).await?;
+            }
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.adjust_fuzzy_threshold(-FUZZY_THRESHOLD_STEP).await?;
+            }
             KeyCode::Char(c) => {
                 self.search_input(c);
                 //We need a delay here or else every key will perform a search, it can get
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use mpris_server::Metadata;
+
+use crate::{
+    app::{PickerKind, TabSelection},
+    offline::OfflineCache,
+    subsonic::SubsonicClient,
+};
+
+use super::App;
+impl App {
+    pub fn start_switch_server(&mut self) {
+        let profiles = self.config.profiles();
+        if profiles.len() < 2 {
+            self.set_notification("Only one server is configured, add more under [[servers]]");
+            return;
+        }
+        let items = profiles
+            .iter()
+            .map(|p| {
+                let marker = if p.name == self.config.active_server {
+                    "● "
+                } else {
+                    "  "
+                };
+                format!("{}{} ({})", marker, p.name, p.server_url)
+            })
+            .collect();
+        let names = profiles.into_iter().map(|p| p.name).collect();
+        self.open_picker(PickerKind::SwitchServer { names }, "Switch server", items);
+    }
+    pub async fn switch_server(&mut self, name: &str) -> Result<()> {
+        if name == self.config.active_server {
+            self.set_notification(format!("Already connected to '{}'", name));
+            return Ok(());
+        }
+        if let Err(e) = self.config.select_server(name) {
+            self.set_notification(format!("Cannot switch server: {}", e));
+            return Ok(());
+        }
+        let client = match SubsonicClient::new(&self.config) {
+            Ok(client) => client,
+            Err(e) => {
+                self.set_notification(format!("Cannot switch server: {}", e));
+                return Ok(());
+            }
+        };
+        self.subsonic_client = Arc::new(client);
+        self.offline_cache = OfflineCache::load(&self.config.active_server);
+
+        // Track ids belong to the previous server, so nothing from it can be played anymore
+        self.player.lock().await.stop()?;
+        self.pending_playback = None;
+        self.loading_track = false;
+        self.radio = None;
+        self.is_playing = false;
+        self.current_track = None;
+        self.metadata = Metadata::default();
+        self.queue_tab = TabSelection::new();
+        self.search_tab = TabSelection::new();
+        self.cancel_remote_search();
+        self.is_searching = false;
+        self.clear_search_matches();
+        self.tracks_tab = TabSelection::new();
+        self.artist_tab = TabSelection::new();
+        self.album_tab = TabSelection::new();
+        self.playlist_tab = TabSelection::new();
+        self.favorite_tab = TabSelection::new();
+        self.folder_tab = TabSelection::new();
+        self.folder_path.clear();
+        self.podcast_tab = TabSelection::new();
+        self.podcast_channel = None;
+        self.disable_shuffle();
+        self.playing_index = 0;
+        self.sync_mpris().await;
+
+        // Drop whatever the old server was still sending
+        self.library_rx = None;
+        self.refreshing = false;
+        self.exit_offline_mode();
+        self.now_playing.clear();
+        self.now_playing_rx = None;
+        self.last_now_playing_check = None;
+        self.thumbnail_cache.clear();
+        self.thumbnail_requested.clear();
+        self.stream_urls.clear();
+        self.stream_url_rx = None;
+        self.clear_artist_songs();
+        self.refresh_library();
+        self.set_notification(format!("Switched to '{}', loading library...", name));
+        Ok(())
+    }
+}
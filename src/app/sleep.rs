@@ -0,0 +1,115 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::app::PromptKind;
+
+use super::App;
+
+/// How long playback fades out once the sleep timer runs out
+const FADE_OUT: Duration = Duration::from_secs(5);
+
+impl App {
+    pub fn start_sleep_timer(&mut self) {
+        self.open_prompt(PromptKind::SleepTimer);
+    }
+    /// Set the timer from the prompt, 0 or an empty input cancels it
+    pub async fn set_sleep_timer(&mut self, input: &str) {
+        let input = input.trim();
+        let minutes = if input.is_empty() {
+            0
+        } else {
+            match input.parse::<u64>() {
+                Ok(minutes) => minutes,
+                Err(_) => {
+                    self.set_notification(format!("'{}' is not a number of minutes", input));
+                    return;
+                }
+            }
+        };
+        if minutes == 0 {
+            self.cancel_sleep_timer().await;
+            return;
+        }
+        self.sleep_deadline = Some(Instant::now() + Duration::from_secs(minutes * 60));
+        let mode = if self.config.playback.sleep_finish_track {
+            ", finishing the track playing then"
+        } else {
+            ""
+        };
+        self.set_notification(format!("Stopping in {} min{}", minutes, mode));
+    }
+    pub async fn cancel_sleep_timer(&mut self) {
+        if self.sleep_deadline.take().is_none() {
+            self.set_notification("No sleep timer set");
+            return;
+        }
+        // Undo a fade that was already under way
+        let _ = self
+            .player
+            .lock()
+            .await
+            .set_volume(self.current_volume as f32);
+        self.set_notification("Sleep timer cancelled");
+    }
+    /// Time left before the timer stops playback, zero while it fades out or waits on the track
+    pub fn sleep_remaining(&self) -> Option<Duration> {
+        self.sleep_deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+    /// Whether playback should stop instead of moving on to the next track
+    pub fn sleep_expired(&self) -> bool {
+        self.sleep_remaining().is_some_and(|left| left.is_zero())
+    }
+    /// Stop once the timer has passed, fading out first. With `sleep_finish_track` the stop
+    /// happens in `on_track_finished` instead
+    pub async fn check_sleep_timer(&mut self) -> Result<()> {
+        let Some(deadline) = self.sleep_deadline else {
+            return Ok(());
+        };
+        if self.config.playback.sleep_finish_track || Instant::now() < deadline {
+            return Ok(());
+        }
+        let fading = deadline.elapsed();
+        if fading >= FADE_OUT || !self.is_playing {
+            self.stop_for_sleep().await?;
+        } else {
+            let left = 1.0 - fading.as_secs_f32() / FADE_OUT.as_secs_f32();
+            let volume = self.current_volume as f32 * left;
+            self.player.lock().await.set_volume(volume)?;
+        }
+        Ok(())
+    }
+    pub fn toggle_stop_after_album(&mut self) {
+        self.stop_after_current_group = !self.stop_after_current_group;
+        if self.stop_after_current_group {
+            self.set_notification("Stopping when this album ends");
+        } else {
+            self.set_notification("Playing on after this album");
+        }
+    }
+    /// With `stop_after_current_group` set, whether the track coming up in the queue belongs
+    /// to a different album than the one that just finished
+    pub fn album_finished(&self) -> bool {
+        if !self.stop_after_current_group {
+            return false;
+        }
+        let Some(current) = &self.current_track else {
+            return false;
+        };
+        self.queue_tab
+            .data
+            .get(self.playing_index + 1)
+            .is_none_or(|next| !next.same_album(current))
+    }
+    pub async fn stop_for_sleep(&mut self) -> Result<()> {
+        self.sleep_deadline = None;
+        self.stop_playback().await?;
+        self.player
+            .lock()
+            .await
+            .set_volume(self.current_volume as f32)?;
+        self.set_notification("Sleep timer stopped playback");
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use crate::app::Track;
+
+use super::App;
+
+/// Tracks listed under "Most played" in the stats view
+const MOST_PLAYED: usize = 5;
+
+/// Library totals for the stats view, counted from what the tabs loaded
+pub struct LibraryStats {
+    pub artists: usize,
+    pub albums: usize,
+    pub songs: usize,
+    pub total_secs: i64,
+    pub most_played: Vec<Track>,
+}
+
+impl App {
+    pub fn toggle_stats_view(&mut self) {
+        self.stats_view = !self.stats_view;
+    }
+    pub fn library_stats(&self) -> LibraryStats {
+        let songs = self.tracks_tab.all_rows();
+        LibraryStats {
+            artists: self.artist_tab.all_rows().len(),
+            albums: self.album_tab.all_rows().len(),
+            songs: songs.len(),
+            // Durations are in microseconds
+            total_secs: songs.iter().map(|t| t.duration / 1_000_000).sum(),
+            most_played: most_played(songs, MOST_PLAYED),
+        }
+    }
+}
+
+/// The `count` tracks with the highest play count, leaving out ones never played
+fn most_played(tracks: &[Track], count: usize) -> Vec<Track> {
+    let mut played: Vec<&Track> = tracks
+        .iter()
+        .filter(|t| t.play_count.is_some_and(|c| c > 0))
+        .collect();
+    played.sort_by_key(|t| std::cmp::Reverse(t.play_count));
+    played.into_iter().take(count).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str, play_count: Option<i32>) -> Track {
+        Track {
+            id: title.to_string(),
+            title: title.to_string(),
+            play_count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_most_played_orders_by_play_count_and_skips_unplayed() {
+        let tracks = [
+            track("a", Some(3)),
+            track("b", None),
+            track("c", Some(10)),
+            track("d", Some(0)),
+            track("e", Some(5)),
+        ];
+        let titles: Vec<String> = most_played(&tracks, 2)
+            .into_iter()
+            .map(|t| t.title)
+            .collect();
+        assert_eq!(titles, ["c", "e"]);
+        assert_eq!(most_played(&tracks, 10).len(), 3);
+    }
+}
@@ -8,7 +8,7 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum ConfigError {
-    #[error("Configuration file not found at {path}. A default config has been creaeted")]
+    #[error("Configuration file not found at {path}")]
     NotFound { path: PathBuf },
     #[error("Failed to parse Configuration file at {path}: {reason}")]
     ParseError { path: PathBuf, reason: String },
@@ -22,14 +22,84 @@ pub enum ConfigError {
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
+    // The active server. Filled from the selected profile when `servers` is used
+    #[serde(default)]
     pub server_url: String,
+    #[serde(default)]
     pub username: String,
+    #[serde(default)]
     pub password: String,
+    #[serde(default)]
     pub secret: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<ServerProfile>,
+    #[serde(skip)]
+    pub active_server: String,
     #[serde(default)]
     pub theme: Theme,
     #[serde(default)]
     pub search: SearchConfig,
+    #[serde(default)]
+    pub playback: PlaybackConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub mpris: MprisConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub lastfm: LastfmConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    #[serde(default)]
+    pub subsonic: SubsonicConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerProfile {
+    pub name: String,
+    pub server_url: String,
+    pub username: String,
+    pub password: String,
+    pub secret: String,
+}
+
+const DEFAULT_PROFILE_NAME: &str = "default";
+/// `password` value that reads the real password from the system keyring
+pub const KEYRING_PLACEHOLDER: &str = "$KEYRING";
+/// `password` prefix that reads the real password from the named environment variable
+pub const ENV_PLACEHOLDER_PREFIX: &str = "$ENV:";
+pub const KEYRING_SERVICE: &str = "sonicrust";
+
+static PLAINTEXT_WARNING: std::sync::Once = std::sync::Once::new();
+
+/// Turn the `password` config value into the real password,
+/// looking it up in the keyring or environment when it is a placeholder
+pub fn resolve_password(raw: &str, username: &str) -> Result<String> {
+    if raw == KEYRING_PLACEHOLDER {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, username)
+            .context("Failed to open the system keyring")?;
+        return entry.get_password().with_context(|| {
+            format!(
+                "No password for '{}' in the system keyring, store one with `sonicrust --store-password`",
+                username
+            )
+        });
+    }
+    if let Some(var) = raw.strip_prefix(ENV_PLACEHOLDER_PREFIX) {
+        return std::env::var(var)
+            .with_context(|| format!("Environment variable {} is not set", var));
+    }
+    PLAINTEXT_WARNING.call_once(|| {
+        log::warn!(
+            "The password is stored in plaintext in the config, consider \"{}\" or \"{}VAR\" instead",
+            KEYRING_PLACEHOLDER,
+            ENV_PLACEHOLDER_PREFIX
+        );
+    });
+    Ok(raw.to_string())
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -38,9 +108,15 @@ pub struct SearchConfig {
     pub mode: SearchMode,
     #[serde(default = "default_search_threshold")]
     pub fuzzy_threshold: i64,
+    // Local search results listed at most, raise it for big libraries
+    #[serde(default = "default_max_results")]
+    pub max_results: usize,
+    // Download the covers of search results ahead of time, so playing one shows its art at once
+    #[serde(default)]
+    pub prefetch_covers: bool,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum SearchMode {
     Local,
@@ -53,11 +129,305 @@ fn default_search_mode() -> SearchMode {
 fn default_search_threshold() -> i64 {
     30
 }
+fn default_max_results() -> usize {
+    30
+}
 impl Default for SearchConfig {
     fn default() -> Self {
         Self {
             mode: default_search_mode(),
             fuzzy_threshold: default_search_threshold(),
+            max_results: default_max_results(),
+            prefetch_covers: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlaybackConfig {
+    #[serde(default)]
+    pub replay_gain: ReplayGainMode,
+    // Seconds the previous track fades out under the next one, 0 disables crossfading
+    #[serde(default)]
+    pub crossfade_secs: u64,
+    // Milliseconds the volume ramps over when starting, pausing or stopping, 0 cuts instantly
+    #[serde(default = "default_fade_ms")]
+    pub fade_ms: u64,
+    #[serde(default = "default_volume_step")]
+    pub volume_step: f64,
+    #[serde(default = "default_fine_volume_step")]
+    pub fine_volume_step: f64,
+    // Seconds to seek with Left/Right, and with Shift held
+    #[serde(default = "default_seek_step")]
+    pub seek_step_secs: u64,
+    #[serde(default = "default_long_seek_step")]
+    pub long_seek_step_secs: u64,
+    // Skip tracks that are already in the queue when adding more
+    #[serde(default)]
+    pub dedupe_queue: bool,
+    // Let the sleep timer finish the track playing instead of fading out mid-track
+    #[serde(default)]
+    pub sleep_finish_track: bool,
+    // Name of the audio output to play through, the system default when unset or missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_device: Option<String>,
+    // Keep the recently played list in history.json across restarts
+    #[serde(default)]
+    pub save_history: bool,
+    // When a track counts as played: a fraction of its length up to 1.0, capped at four
+    // minutes, or a number of seconds above that
+    #[serde(default = "default_scrobble_threshold")]
+    pub scrobble_threshold: f64,
+    // Start playing the restored session on launch instead of waiting with the queue paused
+    #[serde(default)]
+    pub resume_on_start: bool,
+    // Which decoder plays tracks, symphonia needs the feature of the same name
+    #[serde(default)]
+    pub decoder: DecoderKind,
+}
+
+fn default_fade_ms() -> u64 {
+    150
+}
+fn default_scrobble_threshold() -> f64 {
+    0.5
+}
+fn default_volume_step() -> f64 {
+    0.1
+}
+fn default_fine_volume_step() -> f64 {
+    0.01
+}
+fn default_seek_step() -> u64 {
+    5
+}
+fn default_long_seek_step() -> u64 {
+    30
+}
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            replay_gain: ReplayGainMode::default(),
+            crossfade_secs: 0,
+            fade_ms: default_fade_ms(),
+            volume_step: default_volume_step(),
+            fine_volume_step: default_fine_volume_step(),
+            seek_step_secs: default_seek_step(),
+            long_seek_step_secs: default_long_seek_step(),
+            dedupe_queue: false,
+            sleep_finish_track: false,
+            output_device: None,
+            save_history: false,
+            scrobble_threshold: default_scrobble_threshold(),
+            resume_on_start: false,
+            decoder: DecoderKind::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NetworkConfig {
+    // Extra attempts for requests that failed to connect or timed out
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    // Seconds to wait for a connection to the server
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_secs: u64,
+    // Seconds a whole request, including downloading the response, may take
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_retries() -> u32 {
+    3
+}
+fn default_connect_timeout() -> u64 {
+    10
+}
+fn default_timeout() -> u64 {
+    60
+}
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            retries: default_retries(),
+            connect_timeout_secs: default_connect_timeout(),
+            timeout_secs: default_timeout(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MprisConfig {
+    // Let desktop controls raise the terminal, reported through CanRaise
+    #[serde(default = "default_true")]
+    pub raise: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+impl Default for MprisConfig {
+    fn default() -> Self {
+        Self {
+            raise: default_true(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NotificationsConfig {
+    // Desktop notification when a track starts
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // Attach the album cover, which has to be downloaded first
+    #[serde(default = "default_true")]
+    pub show_cover: bool,
+    // How long the notification stays up, the notification server decides when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+}
+
+/// Direct Last.fm scrobbling, off until all three are set. `sonicrust --lastfm-auth` gets the
+/// session key once the key and secret are in place
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LastfmConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_secret: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_key: Option<String>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            show_cover: true,
+            timeout_ms: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UiConfig {
+    // Draw cover art with terminal graphics, set false to never try
+    #[serde(default = "default_true")]
+    pub images: bool,
+    // Milliseconds between redraws while a track plays, keeps the progress bar smooth
+    #[serde(default = "default_tick_ms")]
+    pub tick_ms: u64,
+    // Milliseconds between redraws while paused or stopped, higher saves battery
+    #[serde(default = "default_idle_tick_ms")]
+    pub idle_tick_ms: u64,
+    // Library tab focused at startup, one of TAB_NAMES
+    #[serde(default = "default_tab")]
+    pub default_tab: String,
+    // Jump from the last row to the first and back, otherwise stop at the ends
+    #[serde(default = "default_true")]
+    pub wrap_navigation: bool,
+    // What the progress bar label shows at startup, cycled with `t`
+    #[serde(default)]
+    pub progress_label: ProgressLabel,
+}
+
+/// Names `default_tab` accepts
+pub const TAB_NAMES: [&str; 7] = [
+    "songs",
+    "artists",
+    "albums",
+    "playlists",
+    "favorites",
+    "folders",
+    "podcasts",
+];
+fn default_tab() -> String {
+    "songs".to_string()
+}
+
+fn default_tick_ms() -> u64 {
+    100
+}
+fn default_idle_tick_ms() -> u64 {
+    250
+}
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            images: default_true(),
+            tick_ms: default_tick_ms(),
+            idle_tick_ms: default_idle_tick_ms(),
+            default_tab: default_tab(),
+            wrap_navigation: true,
+            progress_label: ProgressLabel::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SubsonicConfig {
+    // Protocol version sent with every request
+    #[serde(default = "default_api_version")]
+    pub api_version: String,
+    // Shown by the server in now playing and scrobble views
+    #[serde(default = "default_client_name")]
+    pub client_name: String,
+}
+
+fn default_api_version() -> String {
+    "1.16.1".to_string()
+}
+fn default_client_name() -> String {
+    "sonicrust".to_string()
+}
+impl Default for SubsonicConfig {
+    fn default() -> Self {
+        Self {
+            api_version: default_api_version(),
+            client_name: default_client_name(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    Off,
+    #[default]
+    Track,
+    Album,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DecoderKind {
+    // rodio's decoder, which plays the container's default track
+    #[default]
+    Rodio,
+    // symphonia driven directly, playing the first track that holds audio. Same formats as rodio,
+    // which decodes through symphonia as well
+    Symphonia,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressLabel {
+    // 1:23/4:56
+    #[default]
+    Elapsed,
+    // -3:33
+    Remaining,
+    // 28%
+    Percent,
+}
+
+impl ProgressLabel {
+    pub fn next(self) -> Self {
+        match self {
+            ProgressLabel::Elapsed => ProgressLabel::Remaining,
+            ProgressLabel::Remaining => ProgressLabel::Percent,
+            ProgressLabel::Percent => ProgressLabel::Elapsed,
         }
     }
 }
@@ -67,25 +437,85 @@ impl Config {
         let config_path = Self::config_path()?;
 
         if !config_path.exists() {
-            // TODO: make a gui to allow people to input their config
-            let config = Config::default();
-            config.save().map_err(|e| {
-                ConfigError::Io(
-                    e.downcast::<std::io::Error>()
-                        .unwrap_or_else( std::io::Error::other),
-                )
-            })?;
+            // The first run setup in setup.rs writes the config once it can connect
             return Err(ConfigError::NotFound { path: config_path });
         }
         let contents = fs::read_to_string(&config_path).map_err( ConfigError::Io)?;
-        let config: Config = toml::from_str(&contents).map_err(|e| ConfigError::ParseError {
-            path: config_path.clone(),
-            reason: e.to_string(),
-        })?;
+        let mut config: Config =
+            toml::from_str(&contents).map_err(|e| ConfigError::ParseError {
+                path: config_path.clone(),
+                reason: e.to_string(),
+            })?;
+        config.apply_default_profile()?;
         config.validate()?;
         Ok(config)
     }
-    fn validate(&self) -> Result<(), ConfigError> {
+    /// All configured servers. The flat single-server layout is exposed as one default profile
+    pub fn profiles(&self) -> Vec<ServerProfile> {
+        if self.servers.is_empty() {
+            vec![ServerProfile {
+                name: DEFAULT_PROFILE_NAME.to_string(),
+                server_url: self.server_url.clone(),
+                username: self.username.clone(),
+                password: self.password.clone(),
+                secret: self.secret.clone(),
+            }]
+        } else {
+            self.servers.clone()
+        }
+    }
+    /// Make the named profile the active server, leaving the current one in place if it is invalid
+    pub fn select_server(&mut self, name: &str) -> Result<(), ConfigError> {
+        let profile = self
+            .profiles()
+            .into_iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| {
+                ConfigError::ValidationError(format!("no server profile named '{}'", name))
+            })?;
+        let previous = self
+            .profiles()
+            .into_iter()
+            .find(|p| p.name == self.active_server);
+        self.apply_profile(&profile);
+        if let Err(e) = self.validate() {
+            if let Some(previous) = previous {
+                self.apply_profile(&previous);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+    fn apply_profile(&mut self, profile: &ServerProfile) {
+        self.server_url = profile.server_url.clone();
+        self.username = profile.username.clone();
+        self.password = profile.password.clone();
+        self.secret = profile.secret.clone();
+        self.active_server = profile.name.clone();
+    }
+    fn apply_default_profile(&mut self) -> Result<(), ConfigError> {
+        if self.servers.is_empty() {
+            self.active_server = DEFAULT_PROFILE_NAME.to_string();
+            return Ok(());
+        }
+        let profile = match &self.default {
+            Some(name) => self
+                .servers
+                .iter()
+                .find(|p| &p.name == name)
+                .ok_or_else(|| {
+                    ConfigError::ValidationError(format!(
+                        "default server '{}' is not one of the configured servers",
+                        name
+                    ))
+                })?,
+            None => &self.servers[0],
+        };
+        let profile = profile.clone();
+        self.apply_profile(&profile);
+        Ok(())
+    }
+    pub fn validate(&self) -> Result<(), ConfigError> {
         if self.server_url.is_empty() {
             return Err(ConfigError::ValidationError(
                 "server_url cannot be empty".into(),
@@ -112,6 +542,13 @@ impl Config {
                 "secret cannot be empty".into(),
             ));
         }
+        if !TAB_NAMES.contains(&self.ui.default_tab.to_lowercase().as_str()) {
+            return Err(ConfigError::ValidationError(format!(
+                "default_tab must be one of {}, got: {}",
+                TAB_NAMES.join(", "),
+                self.ui.default_tab
+            )));
+        }
         Ok(())
     }
     pub fn save(&self) -> Result<()> {
@@ -125,11 +562,40 @@ impl Config {
             .with_context(|| format!("Failed to write config to {:?}", config_path))?;
         Ok(())
     }
+    /// Remember the chosen output in the config file, leaving the rest of it as the user wrote it
+    pub fn save_output_device(device: Option<&str>) -> Result<()> {
+        let config_path = Self::config_path().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let content = fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read config from {:?}", config_path))?;
+        let content = with_output_device(&content, device)?;
+        fs::write(&config_path, content)
+            .with_context(|| format!("Failed to write config to {:?}", config_path))?;
+        Ok(())
+    }
     fn config_path() -> Result<PathBuf, ConfigError> {
         let config_dir = dirs::config_dir().ok_or(ConfigError::NoConfigDir)?;
         Ok(config_dir.join("sonicrust").join("config.toml"))
     }
 }
+/// Set or clear `output_device` under `[playback]` in a config file's contents. Edited in place
+/// so comments, ordering and formatting stay as they were
+fn with_output_device(content: &str, device: Option<&str>) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut = content.parse().context("Failed to parse config")?;
+    let playback = doc
+        .entry("playback")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .context("[playback] is not a table")?;
+    match device {
+        Some(name) => {
+            playback.insert("output_device", toml_edit::value(name));
+        }
+        None => {
+            playback.remove("output_device");
+        }
+    }
+    Ok(doc.to_string())
+}
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -137,8 +603,18 @@ impl Default for Config {
             username: "admin".to_string(),
             password: "admin".to_string(),
             secret: randomword(),
+            default: None,
+            servers: Vec::new(),
+            active_server: DEFAULT_PROFILE_NAME.to_string(),
             theme: Theme::default(),
             search: SearchConfig::default(),
+            playback: PlaybackConfig::default(),
+            network: NetworkConfig::default(),
+            mpris: MprisConfig::default(),
+            notifications: NotificationsConfig::default(),
+            lastfm: LastfmConfig::default(),
+            ui: UiConfig::default(),
+            subsonic: SubsonicConfig::default(),
         }
     }
 }
@@ -164,6 +640,10 @@ mod tests {
         secret = "randomsecret123"
         "#
     }
+    /// A valid config with `section` (e.g. a `[playback]` table) added after the server settings
+    fn config_with(section: &str) -> Config {
+        toml::from_str(&format!("{}\n{}", valid_config_toml(), section)).unwrap()
+    }
     fn write_config(dir: &TempDir, content: &str) -> PathBuf {
         let path = dir.path().join("config.toml");
         fs::write(&path, content).unwrap();
@@ -177,20 +657,34 @@ mod tests {
             username: "admin".to_string(),
             password: "secret".to_string(),
             secret: "randomsecret123".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
         assert!(config.validate().is_ok());
     }
     #[test]
+    fn test_validate_default_tab() {
+        let mut config = Config {
+            server_url: "http://localhost:4533".to_string(),
+            username: "admin".to_string(),
+            password: "secret".to_string(),
+            secret: "randomsecret123".to_string(),
+            ..Config::default()
+        };
+        config.ui.default_tab = "Albums".to_string();
+        assert!(config.validate().is_ok());
+
+        config.ui.default_tab = "queue".to_string();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("default_tab must be one of"));
+    }
+    #[test]
     fn test_validate_empty_server_url() {
         let config = Config {
             server_url: "".to_string(),
             username: "admin".to_string(),
             password: "secret".to_string(),
             secret: "randomsecret123".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
 
         let err = config.validate().unwrap_err();
@@ -204,8 +698,7 @@ mod tests {
             username: "admin".to_string(),
             password: "secret".to_string(),
             secret: "randomsecret123".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
 
         let err = config.validate().unwrap_err();
@@ -220,8 +713,7 @@ mod tests {
             username: "admin".to_string(),
             password: "secret".to_string(),
             secret: "randomsecret123".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
         assert!(config.validate().is_ok());
     }
@@ -232,8 +724,7 @@ mod tests {
             username: "".to_string(),
             password: "password".to_string(),
             secret: "secret".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
         let err = config.validate().unwrap_err();
         assert!(matches!(err, ConfigError::ValidationError(_)));
@@ -247,8 +738,7 @@ mod tests {
             username: "admin".to_string(),
             password: "".to_string(),
             secret: "secret".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
         let err = config.validate().unwrap_err();
         assert!(matches!(err, ConfigError::ValidationError(_)));
@@ -262,8 +752,7 @@ mod tests {
             username: "admin".to_string(),
             password: "password".to_string(),
             secret: "".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
         let err = config.validate().unwrap_err();
         assert!(matches!(err, ConfigError::ValidationError(_)));
@@ -288,24 +777,286 @@ mod tests {
         let config: Config = toml::from_str(toml).unwrap();
         // SearchConfig defaults
         assert_eq!(config.search.fuzzy_threshold, 30);
+        assert_eq!(config.search.max_results, 30);
         assert!(matches!(config.search.mode, SearchMode::Local));
+        // The other sections' defaults, which tests of a single field leave to this one
+        assert_eq!(config.playback.replay_gain, ReplayGainMode::Track);
+        assert_eq!(config.playback.crossfade_secs, 0);
+        assert_eq!(config.playback.fade_ms, 150);
+        assert_eq!(config.playback.scrobble_threshold, 0.5);
+        assert!(!config.playback.dedupe_queue);
+        assert!(!config.playback.sleep_finish_track);
+        assert_eq!(config.playback.volume_step, 0.1);
+        assert_eq!(config.playback.fine_volume_step, 0.01);
+        assert_eq!(config.playback.seek_step_secs, 5);
+        assert_eq!(config.playback.long_seek_step_secs, 30);
+        assert_eq!(config.network.retries, 3);
+        assert_eq!(config.network.timeout_secs, 60);
+        assert!(config.mpris.raise);
+        assert!(config.notifications.enabled);
+        assert!(config.notifications.show_cover);
+        assert_eq!(config.notifications.timeout_ms, None);
+        assert!(config.ui.images);
+        assert_eq!(config.ui.idle_tick_ms, 250);
+        assert_eq!(config.subsonic.client_name, "sonicrust");
     }
 
     #[test]
     fn test_deserialize_with_search_config() {
+        let config = config_with(
+            r#"
+            [search]
+            mode = "remote"
+            fuzzy_threshold = 50
+            "#,
+        );
+        assert!(matches!(config.search.mode, SearchMode::Remote));
+        assert_eq!(config.search.fuzzy_threshold, 50);
+    }
+
+    #[test]
+    fn test_deserialize_with_search_max_results() {
+        let config = config_with(
+            r#"
+            [search]
+            max_results = 200
+            "#,
+        );
+        assert_eq!(config.search.max_results, 200);
+        assert_eq!(config.search.fuzzy_threshold, 30);
+    }
+
+    #[test]
+    fn test_flat_layout_is_a_single_default_profile() {
+        let mut config: Config = toml::from_str(valid_config_toml()).unwrap();
+        config.apply_default_profile().unwrap();
+        let profiles = config.profiles();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].name, "default");
+        assert_eq!(profiles[0].server_url, "http://localhost:4533");
+        assert_eq!(config.active_server, "default");
+    }
+
+    #[test]
+    fn test_servers_array_applies_default_profile() {
         let toml = r#"
-        server_url = "http://localhost:4533"
-        username = "admin"
-        password = "secret"
+        default = "friend"
+
+        [[servers]]
+        name = "home"
+        server_url = "http://home:4533"
+        username = "me"
+        password = "pw"
         secret = "abc"
 
-        [search]
-        mode = "remote"
-        fuzzy_threshold = 50
+        [[servers]]
+        name = "friend"
+        server_url = "https://friend.example"
+        username = "guest"
+        password = "pw2"
+        secret = "def"
         "#;
-        let config: Config = toml::from_str(toml).unwrap();
-        assert!(matches!(config.search.mode, SearchMode::Remote));
-        assert_eq!(config.search.fuzzy_threshold, 50);
+        let mut config: Config = toml::from_str(toml).unwrap();
+        config.apply_default_profile().unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.active_server, "friend");
+        assert_eq!(config.server_url, "https://friend.example");
+        assert_eq!(config.username, "guest");
+
+        config.select_server("home").unwrap();
+        assert_eq!(config.active_server, "home");
+        assert_eq!(config.server_url, "http://home:4533");
+    }
+
+    #[test]
+    fn test_unknown_default_server_is_rejected() {
+        let toml = r#"
+        default = "missing"
+
+        [[servers]]
+        name = "home"
+        server_url = "http://home:4533"
+        username = "me"
+        password = "pw"
+        secret = "abc"
+        "#;
+        let mut config: Config = toml::from_str(toml).unwrap();
+        let err = config.apply_default_profile().unwrap_err();
+        assert!(err.to_string().contains("missing"));
+    }
+
+    #[test]
+    fn test_select_invalid_server_keeps_current() {
+        let toml = r#"
+        [[servers]]
+        name = "home"
+        server_url = "http://home:4533"
+        username = "me"
+        password = "pw"
+        secret = "abc"
+
+        [[servers]]
+        name = "broken"
+        server_url = "home"
+        username = "me"
+        password = "pw"
+        secret = "abc"
+        "#;
+        let mut config: Config = toml::from_str(toml).unwrap();
+        config.apply_default_profile().unwrap();
+        assert!(config.select_server("broken").is_err());
+        assert_eq!(config.active_server, "home");
+        assert_eq!(config.server_url, "http://home:4533");
+    }
+
+    #[test]
+    fn test_resolve_plaintext_password() {
+        assert_eq!(resolve_password("hunter2", "admin").unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn test_resolve_password_from_env() {
+        // SAFETY: the variable name is unique to this test
+        unsafe { std::env::set_var("SONICRUST_TEST_PASSWORD", "from-env") };
+        let password = resolve_password("$ENV:SONICRUST_TEST_PASSWORD", "admin").unwrap();
+        assert_eq!(password, "from-env");
+    }
+
+    #[test]
+    fn test_resolve_password_from_missing_env_fails() {
+        let err = resolve_password("$ENV:SONICRUST_TEST_UNSET_PASSWORD", "admin").unwrap_err();
+        assert!(err.to_string().contains("SONICRUST_TEST_UNSET_PASSWORD"));
+    }
+
+    #[test]
+    fn test_deserialize_with_playback_config() {
+        let config = config_with(
+            r#"
+            [playback]
+            replay_gain = "album"
+            crossfade_secs = 4
+            fade_ms = 0
+            dedupe_queue = true
+            sleep_finish_track = true
+            scrobble_threshold = 120
+            "#,
+        );
+        assert_eq!(config.playback.replay_gain, ReplayGainMode::Album);
+        assert_eq!(config.playback.crossfade_secs, 4);
+        assert_eq!(config.playback.fade_ms, 0);
+        assert_eq!(config.playback.scrobble_threshold, 120.0);
+        assert!(config.playback.dedupe_queue);
+        assert!(config.playback.sleep_finish_track);
+    }
+
+    #[test]
+    fn test_with_output_device() {
+        let content = with_output_device(valid_config_toml(), Some("USB DAC")).unwrap();
+        let config: Config = toml::from_str(&content).unwrap();
+        assert_eq!(config.playback.output_device.as_deref(), Some("USB DAC"));
+        assert_eq!(config.username, "admin");
+
+        let content = with_output_device(&content, None).unwrap();
+        let config: Config = toml::from_str(&content).unwrap();
+        assert_eq!(config.playback.output_device, None);
+    }
+
+    #[test]
+    fn test_with_output_device_keeps_comments_and_order() {
+        let toml = r#"# my server
+server_url = "http://localhost:4533"
+username = "admin"
+password = "secret"
+secret = "salt"
+
+[playback]
+fade_ms = 0  # no fades
+volume_step = 0.05
+"#;
+        let content = with_output_device(toml, Some("USB DAC")).unwrap();
+        assert!(content.starts_with("# my server\nserver_url"));
+        assert!(content.contains("fade_ms = 0  # no fades\nvolume_step = 0.05\n"));
+        assert!(content.contains("output_device = \"USB DAC\""));
+
+        assert_eq!(with_output_device(&content, None).unwrap(), toml);
+    }
+
+    #[test]
+    fn test_deserialize_seek_steps() {
+        let config = config_with(
+            r#"
+            [playback]
+            seek_step_secs = 10
+            long_seek_step_secs = 60
+            "#,
+        );
+        assert_eq!(config.playback.seek_step_secs, 10);
+        assert_eq!(config.playback.long_seek_step_secs, 60);
+    }
+
+    #[test]
+    fn test_deserialize_with_network_config() {
+        let config = config_with(
+            r#"
+            [network]
+            retries = 0
+            timeout_secs = 5
+            "#,
+        );
+        assert_eq!(config.network.retries, 0);
+        assert_eq!(config.network.timeout_secs, 5);
+        assert_eq!(config.network.connect_timeout_secs, 10);
+    }
+
+    #[test]
+    fn test_deserialize_with_mpris_config() {
+        let config = config_with(
+            r#"
+            [mpris]
+            raise = false
+            "#,
+        );
+        assert!(!config.mpris.raise);
+    }
+
+    #[test]
+    fn test_deserialize_with_notifications_config() {
+        let config = config_with(
+            r#"
+            [notifications]
+            show_cover = false
+            timeout_ms = 3000
+            "#,
+        );
+        assert!(config.notifications.enabled);
+        assert!(!config.notifications.show_cover);
+        assert_eq!(config.notifications.timeout_ms, Some(3000));
+    }
+
+    #[test]
+    fn test_deserialize_with_ui_config() {
+        let config = config_with(
+            r#"
+            [ui]
+            images = false
+            idle_tick_ms = 1000
+            "#,
+        );
+        assert!(!config.ui.images);
+        assert_eq!(config.ui.tick_ms, 100);
+        assert_eq!(config.ui.idle_tick_ms, 1000);
+    }
+
+    #[test]
+    fn test_deserialize_with_subsonic_config() {
+        let config = config_with(
+            r#"
+            [subsonic]
+            client_name = "sonicrust-laptop"
+            "#,
+        );
+        assert_eq!(config.subsonic.client_name, "sonicrust-laptop");
+        assert_eq!(config.subsonic.api_version, "1.16.1");
     }
 
     #[test]
@@ -322,8 +1073,7 @@ mod tests {
             username: "user".to_string(),
             password: "pass".to_string(),
             secret: "mysecret".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
         let serialized = toml::to_string_pretty(&config).unwrap();
         let deserialized: Config = toml::from_str(&serialized).unwrap();
@@ -365,6 +1115,7 @@ mod tests {
     fn test_default_search_config() {
         let search = SearchConfig::default();
         assert_eq!(search.fuzzy_threshold, 30);
+        assert_eq!(search.max_results, 30);
         assert!(matches!(search.mode, SearchMode::Local));
     }
 
@@ -382,8 +1133,7 @@ mod tests {
             username: "user".to_string(),
             password: "pass".to_string(),
             secret: "secret".to_string(),
-            theme: Theme::default(),
-            search: SearchConfig::default(),
+            ..Config::default()
         };
 
         // Write manually to simulate save
@@ -0,0 +1,160 @@
+//! Decoding straight through symphonia, used instead of rodio's decoder when the config asks
+//! for `decoder = "symphonia"`. It probes every container and codec symphonia was built with
+use anyhow::{Result, anyhow};
+use rodio::source::SeekError;
+use rodio::{ChannelCount, SampleRate, Source};
+use std::time::Duration;
+use symphonia::core::audio::{SampleBuffer, SignalSpec};
+use symphonia::core::codecs::{CODEC_TYPE_NULL, Decoder, DecoderOptions};
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+pub struct SymphoniaSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    spec: SignalSpec,
+    buffer: SampleBuffer<f32>, // interleaved samples of the last decoded packet
+    offset: usize,             // samples of `buffer` already handed out
+    total_duration: Option<Duration>,
+}
+
+impl SymphoniaSource {
+    pub fn new(source: Box<dyn MediaSource>) -> Result<Self> {
+        let stream = MediaSourceStream::new(source, Default::default());
+        let options = FormatOptions {
+            enable_gapless: true,
+            ..Default::default()
+        };
+        let mut format = symphonia::default::get_probe()
+            .format(&Hint::new(), stream, &options, &MetadataOptions::default())?
+            .format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| anyhow!("no audio track symphonia can decode"))?;
+        let track_id = track.id;
+        let total_duration = track
+            .codec_params
+            .time_base
+            .zip(track.codec_params.n_frames)
+            .map(|(base, frames)| base.calc_time(frames).into());
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+        let (spec, buffer) = decode_packet(format.as_mut(), decoder.as_mut(), track_id)
+            .ok_or_else(|| anyhow!("no audio in the stream"))?;
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            spec,
+            buffer,
+            offset: 0,
+            total_duration,
+        })
+    }
+}
+
+/// The next packet of `track_id` with audio in it, skipping the ones that fail to decode.
+/// None once the stream ends or breaks
+fn decode_packet(
+    format: &mut dyn FormatReader,
+    decoder: &mut dyn Decoder,
+    track_id: u32,
+) -> Option<(SignalSpec, SampleBuffer<f32>)> {
+    loop {
+        let packet = format.next_packet().ok()?;
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(decoded) if decoded.frames() > 0 => {
+                let spec = *decoded.spec();
+                let mut buffer = SampleBuffer::new(decoded.capacity() as u64, spec);
+                buffer.copy_interleaved_ref(decoded);
+                return Some((spec, buffer));
+            }
+            // Metadata only, which happens after seeking in some formats
+            Ok(_) => continue,
+            Err(Error::DecodeError(e)) => log::debug!("Skipping a damaged packet: {}", e),
+            Err(e) => {
+                log::warn!("Decoding stopped: {}", e);
+                return None;
+            }
+        }
+    }
+}
+
+impl Iterator for SymphoniaSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.offset >= self.buffer.len() {
+            let (spec, buffer) =
+                decode_packet(self.format.as_mut(), self.decoder.as_mut(), self.track_id)?;
+            self.spec = spec;
+            self.buffer = buffer;
+            self.offset = 0;
+        }
+        let sample = *self.buffer.samples().get(self.offset)?;
+        self.offset += 1;
+        Some(sample)
+    }
+}
+
+impl Source for SymphoniaSource {
+    fn current_span_len(&self) -> Option<usize> {
+        // Channels and rate only change between packets
+        Some(self.buffer.len() - self.offset).filter(|&len| len > 0)
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.spec.channels.count() as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.spec.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let target = self.total_duration.map_or(pos, |total| pos.min(total));
+        let channels = self.channels() as usize;
+        let channel = self.offset % channels;
+        let seeked = self
+            .format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time {
+                    time: target.into(),
+                    track_id: Some(self.track_id),
+                },
+            )
+            .map_err(|e| SeekError::Other(Box::new(e)))?;
+        // The demuxer moved without the decoder knowing, and the buffer is from before the seek
+        self.decoder.reset();
+        self.offset = self.buffer.len();
+        // It lands on the packet holding the target, decode the rest of the way
+        let frames = match self.decoder.codec_params().time_base {
+            Some(base) => {
+                let skip: Duration = base
+                    .calc_time(seeked.required_ts.saturating_sub(seeked.actual_ts))
+                    .into();
+                (skip.as_secs_f64() * self.sample_rate() as f64) as usize
+            }
+            None => 0,
+        };
+        // Keep handing out samples for the channel that was next before the seek
+        for _ in 0..frames * channels + channel {
+            self.next();
+        }
+        Ok(())
+    }
+}
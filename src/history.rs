@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app::Track;
+
+/// Entries kept, the oldest are dropped first
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    // Track ids only make sense on the server they came from
+    pub server: String,
+    pub track: Track,
+    // Seconds since the unix epoch
+    pub played_at: u64,
+}
+
+impl HistoryEntry {
+    /// How long ago the track started, e.g. "20m ago"
+    pub fn age_label(&self) -> String {
+        let secs = now().saturating_sub(self.played_at);
+        match secs {
+            0..60 => "just now".to_string(),
+            60..3600 => format!("{}m ago", secs / 60),
+            3600..86400 => format!("{}h ago", secs / 3600),
+            _ => format!("{}d ago", secs / 86400),
+        }
+    }
+}
+
+/// Tracks started on this machine, newest first. Saved to `history.json` when
+/// `save_history` is set
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    pub entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sonicrust").join("history.json"))
+    }
+    pub fn load() -> Option<Self> {
+        let contents = fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&contents)
+            .inspect_err(|e| log::warn!("Ignoring unreadable history file: {}", e))
+            .ok()
+    }
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("Could not determine the config directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let contents = serde_json::to_string(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(())
+    }
+    pub fn push(&mut self, server: &str, track: Track) {
+        self.entries.push_front(HistoryEntry {
+            server: server.to_string(),
+            track,
+            played_at: now(),
+        });
+        self.entries.truncate(MAX_ENTRIES);
+    }
+    /// Entries played on `server`, newest first
+    pub fn for_server<'a>(&'a self, server: &'a str) -> impl Iterator<Item = &'a HistoryEntry> {
+        self.entries.iter().filter(move |e| e.server == server)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,178 @@
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+use crate::app::Track;
+use crate::config::{Config, LastfmConfig, NetworkConfig};
+use crate::network::build_client;
+
+const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+const AUTH_URL: &str = "https://www.last.fm/api/auth/";
+
+/// Scrobbles straight to Last.fm, next to the server's own scrobbling.
+///
+/// Needs an API account from https://www.last.fm/api/account/create for the key and secret,
+/// and a session key, which `sonicrust --lastfm-auth` gets through the desktop auth flow:
+/// `auth.getToken`, approving the token in the browser, then `auth.getSession`
+#[derive(Clone)]
+pub struct LastfmClient {
+    client: reqwest::Client,
+    api_key: String,
+    api_secret: String,
+    session_key: String,
+}
+
+impl LastfmClient {
+    /// None unless the key, the secret and a session key are all configured
+    pub fn from_config(config: &LastfmConfig, network: &NetworkConfig) -> Result<Option<Self>> {
+        let (Some(api_key), Some(api_secret), Some(session_key)) = (
+            config.api_key.clone(),
+            config.api_secret.clone(),
+            config.session_key.clone(),
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            client: build_client(network)?,
+            api_key,
+            api_secret,
+            session_key,
+        }))
+    }
+    pub async fn update_now_playing(&self, track: &Track) -> Result<()> {
+        self.call("track.updateNowPlaying", track_params(track))
+            .await
+            .map(|_| ())
+    }
+    /// `started_at` is when playback of the track began, in seconds since the unix epoch
+    pub async fn scrobble(&self, track: &Track, started_at: u64) -> Result<()> {
+        let mut params = track_params(track);
+        params.push(("timestamp", started_at.to_string()));
+        self.call("track.scrobble", params).await.map(|_| ())
+    }
+    async fn call(&self, method: &str, mut params: Vec<(&str, String)>) -> Result<Value> {
+        params.push(("sk", self.session_key.clone()));
+        signed_call(
+            &self.client,
+            &self.api_key,
+            &self.api_secret,
+            method,
+            params,
+        )
+        .await
+    }
+}
+
+fn track_params(track: &Track) -> Vec<(&'static str, String)> {
+    let mut params = vec![
+        ("artist", track.artist.clone()),
+        ("track", track.title.clone()),
+        ("album", track.album.clone()),
+    ];
+    if let Some(album_artist) = &track.album_artist {
+        params.push(("albumArtist", album_artist.clone()));
+    }
+    if let Some(number) = track.track_number {
+        params.push(("trackNumber", number.to_string()));
+    }
+    if track.duration > 0 {
+        params.push(("duration", (track.duration / 1_000_000).to_string()));
+    }
+    params
+}
+
+/// Post a method call with its `api_sig`, turning Last.fm's error replies into errors
+async fn signed_call(
+    client: &reqwest::Client,
+    api_key: &str,
+    api_secret: &str,
+    method: &str,
+    mut params: Vec<(&str, String)>,
+) -> Result<Value> {
+    params.push(("api_key", api_key.to_string()));
+    params.push(("method", method.to_string()));
+    let api_sig = sign(&params, api_secret);
+    params.push(("api_sig", api_sig));
+    // Not part of the signature
+    params.push(("format", "json".to_string()));
+    let reply: Value = client
+        .post(API_URL)
+        .form(&params)
+        .send()
+        .await?
+        .json()
+        .await
+        .with_context(|| format!("Unreadable Last.fm reply to {}", method))?;
+    if let Some(code) = reply.get("error") {
+        let message = reply["message"].as_str().unwrap_or("no message");
+        bail!("Last.fm {} failed ({}): {}", method, code, message);
+    }
+    Ok(reply)
+}
+
+/// md5 of every name and value sorted by name, followed by the secret
+fn sign(params: &[(&str, String)], api_secret: &str) -> String {
+    let mut sorted: Vec<_> = params.iter().collect();
+    sorted.sort_by_key(|(name, _)| *name);
+    let mut text: String = sorted
+        .iter()
+        .map(|(name, value)| format!("{}{}", name, value))
+        .collect();
+    text.push_str(api_secret);
+    format!("{:x}", md5::compute(text))
+}
+
+/// Walk through the desktop auth flow and print the session key to put in the config
+pub async fn authorize() -> Result<()> {
+    let config = Config::load()?;
+    let (Some(api_key), Some(api_secret)) = (&config.lastfm.api_key, &config.lastfm.api_secret)
+    else {
+        bail!("Set api_key and api_secret under [lastfm] in the config first");
+    };
+    let client = build_client(&config.network)?;
+    let reply = signed_call(&client, api_key, api_secret, "auth.getToken", Vec::new()).await?;
+    let token = reply["token"]
+        .as_str()
+        .context("Last.fm sent no token")?
+        .to_string();
+    println!(
+        "Allow sonicrust to scrobble at {}?api_key={}&token={}",
+        AUTH_URL, api_key, token
+    );
+    print!("Press Enter once it is allowed... ");
+    io::stdout().flush()?;
+    io::stdin().lock().read_line(&mut String::new())?;
+    let reply = signed_call(
+        &client,
+        api_key,
+        api_secret,
+        "auth.getSession",
+        vec![("token", token)],
+    )
+    .await?;
+    let session_key = reply["session"]["key"]
+        .as_str()
+        .context("Last.fm sent no session key")?;
+    println!("Add this under [lastfm] in the config:");
+    println!("session_key = \"{}\"", session_key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_sorts_params_and_appends_secret() {
+        let params = vec![
+            ("token", "tok".to_string()),
+            ("api_key", "key".to_string()),
+            ("method", "auth.getSession".to_string()),
+        ];
+        let expected = format!(
+            "{:x}",
+            md5::compute("api_keykeymethodauth.getSessiontokentoksecret")
+        );
+        assert_eq!(sign(&params, "secret"), expected);
+    }
+}
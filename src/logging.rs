@@ -0,0 +1,56 @@
+use log::LevelFilter;
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Level of the log while verbose logging is on
+const VERBOSE: LevelFilter = LevelFilter::Debug;
+
+/// Level the log was started with, verbose logging goes back to it
+static QUIET: OnceLock<LevelFilter> = OnceLock::new();
+
+/// The terminal belongs to the ui, so the log goes to a file in the cache dir
+pub fn log_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("sonicrust")
+        .join("sonicrust.log")
+}
+
+/// Set up the log. `RUST_LOG` works as usual, without it only warnings are written until
+/// verbose logging is switched on, which lets our own debug output through
+pub fn init() {
+    let from_env = std::env::var_os("RUST_LOG").is_some();
+    let mut builder = env_logger::Builder::from_default_env();
+    if !from_env {
+        builder
+            .filter_level(LevelFilter::Warn)
+            .filter_module("sonicrust", VERBOSE);
+    }
+    let path = log_path();
+    let file = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path));
+    match file {
+        Ok(file) => {
+            builder.target(env_logger::Target::Pipe(Box::new(file)));
+        }
+        Err(e) => eprintln!("Failed to open the log at {:?}: {}", path, e),
+    }
+    builder.init();
+    // The filter lets debug output through, the max level decides if it gets there
+    let quiet = if from_env {
+        log::max_level()
+    } else {
+        LevelFilter::Warn
+    };
+    let _ = QUIET.set(quiet);
+    log::set_max_level(quiet);
+}
+
+/// Raise the log to debug output, or back to the level it was started with
+pub fn set_verbose(verbose: bool) {
+    let quiet = QUIET.get().copied().unwrap_or(LevelFilter::Warn);
+    log::set_max_level(if verbose { quiet.max(VERBOSE) } else { quiet });
+}
@@ -1,8 +1,17 @@
 mod app;
 mod config;
+#[cfg(feature = "symphonia")]
+mod decoder;
+mod history;
+mod lastfm;
+mod logging;
 mod mpris_handler;
+mod network;
+mod offline;
 mod player;
 mod search;
+mod session;
+mod setup;
 mod subsonic;
 mod theme;
 mod ui;
@@ -23,11 +32,18 @@ use std::{
     time::Duration,
 };
 
-use crate::app::InputMode;
+use crate::app::{InputMode, PromptKind};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
+    logging::init();
+
+    if std::env::args().any(|arg| arg == "--store-password") {
+        return setup::store_password_in_keyring();
+    }
+    if std::env::args().any(|arg| arg == "--lastfm-auth") {
+        return lastfm::authorize().await;
+    }
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -36,8 +52,14 @@ async fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
     let _guard = app::TerminalGuard::new();
 
-    let app = App::new().await?;
-    let res = run_app(&mut terminal, app).await;
+    let res = match setup::load_or_setup(&mut terminal).await? {
+        Some(config) => {
+            let app = App::new(config).await?;
+            run_app(&mut terminal, app).await
+        }
+        // Setup was cancelled, leave without touching anything
+        None => Ok(()),
+    };
 
     disable_raw_mode()?;
     execute!(
@@ -54,61 +76,199 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<()> {
+async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<()>
+where
+    B::Error: Send + Sync + 'static,
+{
     loop {
-        terminal.draw(|f| ui::draw(f, &mut app)).unwrap();
+        if app.wants_redraw() {
+            terminal.draw(|f| ui::draw(f, &mut app))?;
+            app.needs_redraw = false;
+        }
 
-        if crossterm::event::poll(Duration::from_millis(100))?
-            && let Event::Key(key) = event::read()?
-        {
-            if app.input_mode == InputMode::Search {
+        // Waiting for input is the only pause in the loop, so this sets the redraw rate
+        let tick = if app.is_playing {
+            app.config.ui.tick_ms
+        } else {
+            app.config.ui.idle_tick_ms
+        };
+        let event = if crossterm::event::poll(Duration::from_millis(tick))? {
+            Some(event::read()?)
+        } else {
+            None
+        };
+        if event.is_some() {
+            // Keys change state and a resize needs a full redraw
+            app.needs_redraw = true;
+        }
+        if let Some(Event::Key(key)) = event {
+            if app.lyrics_view && app.input_mode == InputMode::Normal && app.handle_lyrics_key(key)
+            {
+                // Handled by the lyrics view
+            } else if app.artist_info.is_some()
+                && app.input_mode == InputMode::Normal
+                && app.handle_artist_info_key(key).await
+            {
+                // Handled by the artist overlay
+            } else if app.input_mode == InputMode::Search {
                 app.handle_search_input(key).await?;
             } else if app.input_mode == InputMode::InlineSearch {
                 app.handle_inline_search_input(key).await?;
+            } else if app.input_mode == InputMode::Filter {
+                app.handle_filter_input(key).await?;
+            } else if app.input_mode == InputMode::Prompt {
+                app.handle_prompt_input(key).await?;
+            } else if app.input_mode == InputMode::Picker {
+                app.handle_picker_input(key).await?;
+            } else if app.input_mode == InputMode::Confirm {
+                app.handle_confirm_input(key).await?;
             } else {
                 match key.code {
                     KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Char(' ') => app.toggle_playback().await?,
+                    KeyCode::Char('Q') => match app.save_session().await {
+                        Ok(()) => return Ok(()),
+                        Err(e) => app.set_notification(format!("Failed to save session: {}", e)),
+                    },
+                    KeyCode::Char(' ') if app.visual_mode && app.can_mark() => app.toggle_mark(),
+                    KeyCode::Char(' ') => {
+                        let result = app.toggle_playback().await;
+                        app.notify_boundary(result)?
+                    }
+                    KeyCode::Char('x') => app.stop_playback().await?,
+                    KeyCode::Char('g') => app.toggle_grouped_songs(),
+                    KeyCode::Char('y') => app.open_genre_picker(),
+                    KeyCode::Char('o') => app.cycle_sort(),
+                    KeyCode::Char('L') => app.cycle_album_list().await,
+                    KeyCode::Char('T') => app.play_artist_top_tracks().await?,
+                    KeyCode::Char('w') => app.open_artist_info().await,
+                    KeyCode::Char('v') => app.toggle_visual_mode(),
+                    KeyCode::Char('u') => app.toggle_album_thumbnails(),
+                    KeyCode::Char('z') => app.start_sleep_timer(),
+                    KeyCode::Char('Z') => app.cancel_sleep_timer().await,
+                    KeyCode::Char('e') => app.toggle_stop_after_album(),
+                    KeyCode::Char('m') => app.compact = !app.compact,
+                    KeyCode::Char('t') => app.progress_label = app.progress_label.next(),
+                    KeyCode::Char('l') => app.toggle_lyrics_view(),
+                    KeyCode::Char('V') => app.open_prompt(PromptKind::Volume),
+                    KeyCode::Char(']') => app.adjust_boost(true).await,
+                    KeyCode::Char('[') => app.adjust_boost(false).await,
+                    KeyCode::Char('O') => app.reconnect_audio().await,
+                    KeyCode::Char('W') => app.start_pick_output_device().await,
+                    KeyCode::Char('J') => app.jump_to_current_album(),
+                    KeyCode::Char('K') => app.jump_to_current_artist(),
                     KeyCode::Char('k') | KeyCode::Up => app.previous_item_in_tab(),
                     KeyCode::Char('j') | KeyCode::Down => app.next_item_in_tab(),
+                    KeyCode::Enter if app.has_marks() => app.play_marked().await?,
+                    KeyCode::Enter if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                        app.play_album_from_selected().await?
+                    }
+                    KeyCode::Char('E') => app.play_album_from_selected().await?,
                     KeyCode::Enter => app.play_selected(app.find_selected()).await?,
-                    KeyCode::Left => app.seek_backward().await?,
+                    KeyCode::Left if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                        app.seek_backward(app.config.playback.long_seek_step_secs)
+                            .await?
+                    }
+                    KeyCode::Left => {
+                        app.seek_backward(app.config.playback.seek_step_secs)
+                            .await?
+                    }
                     KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                        app.refresh_library().await?
+                        app.refresh_library()
                     }
                     KeyCode::Char('r') => app.toggle_repeat(),
+                    KeyCode::Char('U') => app.toggle_repeat_one(),
                     KeyCode::Char('S') => app.toggle_shuffle(),
-                    KeyCode::Right => app.seek_forward().await?,
+                    KeyCode::Char('M') => app.shuffle_queue_once(),
+                    KeyCode::Right if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                        app.seek_forward(app.config.playback.long_seek_step_secs)
+                            .await?
+                    }
+                    KeyCode::Right => app.seek_forward(app.config.playback.seek_step_secs).await?,
+                    KeyCode::Char('a') if app.has_marks() => app.queue_marked(),
                     KeyCode::Char('a') => app._add_to_queue().await?,
+                    KeyCode::Char('A') => app.add_view_to_queue().await?,
                     KeyCode::Char('+') => app.adjust_volume(app::VolumeDirection::Up).await?,
                     KeyCode::Char('-') => app.adjust_volume(app::VolumeDirection::Down).await?,
+                    KeyCode::Char('>') => app.adjust_volume(app::VolumeDirection::FineUp).await?,
+                    KeyCode::Char('<') => app.adjust_volume(app::VolumeDirection::FineDown).await?,
                     KeyCode::Tab => app.next_tab(),
                     KeyCode::BackTab => app.previous_tab(),
+                    // Alt+digit rates, plain digits pick tabs
+                    KeyCode::Char(c @ '0'..='5') if key.modifiers.contains(KeyModifiers::ALT) => {
+                        app.rate_track(c as u8 - b'0').await
+                    }
+                    KeyCode::Char('0') => app.focus_queue(),
+                    KeyCode::Char('9') => app.focus_library(),
                     KeyCode::Char('1') => app.select_tab(app::ActiveTab::Songs),
                     KeyCode::Char('2') => app.select_tab(app::ActiveTab::Artists),
                     KeyCode::Char('3') => app.select_tab(app::ActiveTab::Albums),
                     KeyCode::Char('4') => app.select_tab(app::ActiveTab::Playlist),
                     KeyCode::Char('5') => app.select_tab(app::ActiveTab::Favorites),
+                    KeyCode::Char('6') => {
+                        app.select_tab(app::ActiveTab::Folders);
+                        app.open_folders().await;
+                    }
+                    KeyCode::Char('7') => {
+                        app.select_tab(app::ActiveTab::Podcasts);
+                        app.open_podcasts().await;
+                    }
+                    KeyCode::Backspace if app.active_tab == app::ActiveTab::Podcasts => {
+                        app.leave_podcast_channel().await
+                    }
+                    KeyCode::Backspace
+                        if app.active_tab == app::ActiveTab::Search
+                            && app.active_section == app::ActiveSection::Others =>
+                    {
+                        app.leave_search()
+                    }
+                    KeyCode::Backspace => app.leave_folder().await,
                     KeyCode::Char('s') => {
                         app.select_tab(app::ActiveTab::Search);
                         app.enter_search_mode();
                     }
-                    KeyCode::Char('/') => {
-                        if app.active_tab != app::ActiveTab::Search {
-                            app.start_inline_search();
-                        }
+                    KeyCode::Char('/') if app.active_tab != app::ActiveTab::Search => {
+                        app.start_inline_search();
                     }
+                    KeyCode::Char('\\') => app.start_filter(),
+                    KeyCode::Esc if app.stats_view => app.stats_view = false,
+                    KeyCode::Esc if app.is_filtered() => app.clear_active_filter(),
+                    KeyCode::Char('c') => app.start_save_queue_as_playlist(),
+                    KeyCode::Char('i') => app.start_add_to_playlist(),
+                    KeyCode::Char('D') => app.start_delete_playlist(),
+                    KeyCode::Char('d') => app.download_selected(),
+                    KeyCode::Char('P') => app.start_switch_server(),
+                    KeyCode::Char('b') => app.bookmark_current_position().await,
+                    KeyCode::Char('B') => app.start_resume_bookmark().await,
+                    KeyCode::Char('H') => app.open_history(),
+                    KeyCode::Char('I') => app.open_radio_stations().await,
+                    KeyCode::Char('N') => app.toggle_stats_view(),
+                    KeyCode::Char('C') => app.reload_cover_art().await,
+                    KeyCode::Char('Y') => app.retry_cover_art().await,
+                    KeyCode::Char('X') => app.start_clear_cover_art_cache(),
+                    KeyCode::Char('G') => app.toggle_verbose_logging(),
                     KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::SHIFT) => {
                         app.make_favorite(true).await?
                     }
                     KeyCode::Char('f') => app.make_favorite(false).await?,
-                    KeyCode::Char('n') => app.play_next().await?,
-                    KeyCode::Char('p') => app.play_previous().await?,
+                    KeyCode::Char('F') => app.toggle_favorite_playing().await?,
+                    KeyCode::Char('n') => {
+                        let result = app.play_next().await;
+                        app.notify_boundary(result)?
+                    }
+                    KeyCode::Char('p') => {
+                        let result = app.play_previous().await;
+                        app.notify_boundary(result)?
+                    }
                     _ => {}
                 }
             }
         }
+        if app.loading_track {
+            // Show the buffering state before update blocks on downloading the track
+            terminal.draw(|f| ui::draw(f, &mut app))?;
+        }
         app.update().await?;
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        // Polling blocks this worker, let tasks spawned from here run on it too
+        tokio::task::yield_now().await;
     }
 }
@@ -1,5 +1,7 @@
 use mpris_server::zbus;
 use mpris_server::zbus::zvariant::ObjectPath;
+use std::io::Write;
+use std::process::{Command, Stdio};
 use tokio::sync::mpsc;
 
 use crate::app::Track;
@@ -8,17 +10,29 @@ use crate::player::{PlayerCommand, SharedPlayerState};
 
 use mpris_server::{
     LoopStatus, Metadata, PlaybackRate, PlaybackStatus, PlayerInterface, RootInterface, Time,
-    TrackId, Volume,
+    TrackId, TrackListInterface, Uri, Volume,
     zbus::{Result, fdo},
 };
 
+/// Scheme of the uris AddTrack accepts, `sonicrust:track/<song id>`
+const TRACK_URI_PREFIX: &str = "sonicrust:track/";
+
 pub struct MprisPlayer {
     command_tx: mpsc::Sender<PlayerCommand>,
     pub state: SharedPlayerState,
+    can_raise: bool,
 }
 impl MprisPlayer {
-    pub fn new(command_tx: mpsc::Sender<PlayerCommand>, state: SharedPlayerState) -> Self {
-        Self { command_tx, state }
+    pub fn new(
+        command_tx: mpsc::Sender<PlayerCommand>,
+        state: SharedPlayerState,
+        can_raise: bool,
+    ) -> Self {
+        Self {
+            command_tx,
+            state,
+            can_raise,
+        }
     }
     fn send_command(&self, cmd: PlayerCommand) {
         if let Err(e) = self.command_tx.try_send(cmd) {
@@ -26,8 +40,32 @@ impl MprisPlayer {
         }
     }
 }
+/// Best effort, since a TUI doesn't own its window. Focuses the terminal through xdotool when
+/// it exports $WINDOWID, otherwise rings the bell, which most terminals show as urgent
+fn raise_terminal() {
+    if let Ok(window_id) = std::env::var("WINDOWID") {
+        let focused = Command::new("xdotool")
+            .args(["windowactivate", &window_id])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if focused {
+            return;
+        }
+    }
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(b"\x07");
+    let _ = stdout.flush();
+}
 impl RootInterface for MprisPlayer {
     async fn raise(&self) -> fdo::Result<()> {
+        if !self.can_raise {
+            return Err(fdo::Error::NotSupported("Raise is disabled".to_string()));
+        }
+        // xdotool blocks until it is done, keep it off the D-Bus executor. That executor is
+        // zbus' own rather than tokio's, so a plain thread rather than spawn_blocking
+        std::thread::spawn(raise_terminal);
         Ok(())
     }
 
@@ -54,11 +92,11 @@ impl RootInterface for MprisPlayer {
     }
 
     async fn can_raise(&self) -> fdo::Result<bool> {
-        Ok(true)
+        Ok(self.can_raise)
     }
 
     async fn has_track_list(&self) -> fdo::Result<bool> {
-        Ok(false)
+        Ok(true)
     }
 
     async fn identity(&self) -> fdo::Result<String> {
@@ -70,7 +108,7 @@ impl RootInterface for MprisPlayer {
     }
 
     async fn supported_uri_schemes(&self) -> fdo::Result<Vec<String>> {
-        Ok(vec![])
+        Ok(vec!["sonicrust".to_string()])
     }
     async fn supported_mime_types(&self) -> fdo::Result<Vec<String>> {
         Ok(vec![])
@@ -221,14 +259,87 @@ impl PlayerInterface for MprisPlayer {
         Ok(true)
     }
 }
+impl TrackListInterface for MprisPlayer {
+    async fn get_tracks_metadata(&self, track_ids: Vec<TrackId>) -> fdo::Result<Vec<Metadata>> {
+        let state = self
+            .state
+            .read()
+            .map_err(|_| fdo::Error::Failed("Lock error".into()))?;
+        Ok(track_ids
+            .iter()
+            .filter_map(|id| {
+                state
+                    .track_list
+                    .iter()
+                    .find(|t| &track_object_path(&t.id) == id)
+            })
+            .map(track_to_metadata)
+            .collect())
+    }
+
+    async fn add_track(
+        &self,
+        uri: Uri,
+        after_track: TrackId,
+        set_as_current: bool,
+    ) -> fdo::Result<()> {
+        let Some(song_id) = uri.strip_prefix(TRACK_URI_PREFIX) else {
+            return Err(fdo::Error::NotSupported(format!(
+                "Only {}<song id> uris can be added",
+                TRACK_URI_PREFIX
+            )));
+        };
+        self.send_command(PlayerCommand::AddTrack {
+            song_id: song_id.to_string(),
+            after: after_track,
+            play: set_as_current,
+        });
+        Ok(())
+    }
+
+    async fn remove_track(&self, track_id: TrackId) -> fdo::Result<()> {
+        self.send_command(PlayerCommand::RemoveTrack(track_id));
+        Ok(())
+    }
+
+    async fn go_to(&self, track_id: TrackId) -> fdo::Result<()> {
+        self.send_command(PlayerCommand::GoTo(track_id));
+        Ok(())
+    }
+
+    async fn tracks(&self) -> fdo::Result<Vec<TrackId>> {
+        let state = self
+            .state
+            .read()
+            .map_err(|_| fdo::Error::Failed("Lock error".into()))?;
+        Ok(state
+            .track_list
+            .iter()
+            .map(|t| track_object_path(&t.id))
+            .collect())
+    }
+
+    async fn can_edit_tracks(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+}
+/// The D-Bus object path standing for a track, in the Metadata and the TrackList alike.
+/// Only letters, digits and underscores are allowed in a path element
+pub fn track_object_path(id: &str) -> TrackId {
+    let element: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    ObjectPath::try_from(format!(
+        "/org/mpris/MediaPlayer2/Sonicrust/Track/_{}",
+        element
+    ))
+    .expect("a path of letters, digits and underscores is valid")
+    .into()
+}
 /// Helper to convert our internal Track crate to MPRIS Metadata
 pub fn track_to_metadata(track: &Track) -> Metadata {
-    // The Track ID must be a valid D-Bus ObjectPath
-    let track_id = ObjectPath::try_from(format!(
-        "/org/mpris/MediaPlayer2/Sonicrust/Track/{}",
-        track.id.replace('-', "_")
-    ))
-    .unwrap();
+    let track_id = track_object_path(&track.id);
     let art_url = match track.cover_art.clone() {
         Some(url) => url,
         None => "".to_string(),
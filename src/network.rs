@@ -0,0 +1,69 @@
+use std::{future::Future, time::Duration};
+
+use crate::config::NetworkConfig;
+
+/// First wait before retrying, doubled after every failed attempt
+const BASE_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Connection problems and timeouts are worth retrying. Anything the server actually answered,
+/// like a 401 or 404, will fail the same way again
+pub fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect()
+}
+
+/// Run a request up to `retries` more times while it fails with a transient error,
+/// backing off exponentially between attempts
+pub async fn with_retry<T, F, Fut>(retries: u32, mut request: F) -> reqwest::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = reqwest::Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match request().await {
+            Err(e) if attempt < retries && is_transient(&e) => {
+                log::debug!("Transient network error, retrying ({}): {}", attempt + 1, e);
+                tokio::time::sleep(BASE_BACKOFF * 2u32.pow(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// HTTP client with the configured timeouts, so a hung server can't stall the app
+pub fn build_client(config: &NetworkConfig) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()
+}
+
+/// Client for audio: whole tracks, downloads and endless streams such as internet radio. A total
+/// timeout would cut off a large file on a slow link, so only connecting and each read are bounded
+pub fn build_stream_client(config: &NetworkConfig) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.timeout_secs))
+        .build()
+}
+
+/// Blocking counterpart of [`build_client`]. Must be called off the async runtime
+pub fn build_blocking_client(config: &NetworkConfig) -> reqwest::Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()
+}
+
+/// Whether a request failed because the server did not answer in time
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_timeout())
+}
+
+/// Whether a request failed because the server could not be reached at all
+pub fn is_unreachable(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .is_some_and(is_transient)
+}
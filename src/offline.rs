@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Tracks downloaded for offline playback. Files live in the user cache dir (not the temp
+/// dir used for covers) next to a small json index keyed by track id. Track ids only make
+/// sense on the server they came from, so every server gets a directory of its own.
+pub struct OfflineCache {
+    dir: PathBuf,
+    index: HashMap<String, PathBuf>,
+}
+
+impl OfflineCache {
+    pub fn load(server: &str) -> Self {
+        let dir = dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("sonicrust")
+            .join("tracks")
+            .join(file_name(server));
+        let index = fs::read_to_string(dir.join("index.json"))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self { dir, index }
+    }
+    /// Local file for a track, only if it is still on disk. A file removed behind our back is
+    /// dropped from the index, so the track streams from then on
+    pub fn path_for(&mut self, id: &str) -> Option<PathBuf> {
+        let path = self.index.get(id)?;
+        if path.exists() {
+            return Some(path.clone());
+        }
+        log::warn!("Offline copy of {} is gone: {:?}", id, path);
+        self.index.remove(id);
+        if let Err(e) = self.save_index() {
+            log::warn!("{:#}", e);
+        }
+        None
+    }
+    /// Whether the index lists the track. Cheap enough to ask for every row on every redraw,
+    /// the file itself is only checked when playing it
+    pub fn contains(&self, id: &str) -> bool {
+        self.index.contains_key(id)
+    }
+    pub fn store(&mut self, id: &str, data: &[u8]) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create offline dir {:?}", self.dir))?;
+        let path = self.dir.join(file_name(id));
+        fs::write(&path, data).with_context(|| format!("Failed to write {:?}", path))?;
+        self.index.insert(id.to_string(), path.clone());
+        self.save_index()?;
+        Ok(path)
+    }
+    fn save_index(&self) -> Result<()> {
+        let contents = serde_json::to_string_pretty(&self.index)?;
+        fs::write(self.dir.join("index.json"), contents)
+            .context("Failed to write offline index")?;
+        Ok(())
+    }
+}
+
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
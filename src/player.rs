@@ -1,18 +1,71 @@
+use crate::app::Track;
+use crate::config::DecoderKind;
+#[cfg(feature = "symphonia")]
+use crate::decoder::SymphoniaSource;
+use crate::network::with_retry;
 use anyhow::Result;
-use mpris_server::{Metadata, PlaybackStatus, Time};
+use mpris_server::{Metadata, PlaybackStatus, Time, TrackId};
+use rodio::cpal::traits::HostTrait;
 use rodio::decoder::DecoderBuilder;
-use rodio::{OutputStream, OutputStreamBuilder, Sink};
+use rodio::source::EmptyCallback;
+use rodio::{DeviceTrait, OutputStream, OutputStreamBuilder, Sink, Source, cpal};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use std::{
-    io::Cursor,
-    sync::{Arc, RwLock},
+    io::{self, Cursor, Read, Seek, SeekFrom},
+    sync::{Arc, Mutex, RwLock},
 };
+use tokio::sync::mpsc;
+
+/// Chunks of a live stream buffered ahead of the decoder
+const LIVE_BUFFER_CHUNKS: usize = 64;
+/// Volume changes a play, pause or stop fade is made of
+const FADE_STEPS: u32 = 15;
 
 pub struct Player {
     // _stream: OutputStream,
     stream_handle: OutputStream,
-    sink: Option<Sink>,
+    device: Option<String>, // chosen output by name, None follows the system default
+    sink: Option<Arc<Sink>>, // shared with a running fade
+    track_data: Option<Arc<[u8]>>, // kept to rebuild the sink on another output
+    decoded_duration: Option<Duration>, // length the decoder found, if the format tells
+    live: bool,             // playing an endless stream, which can't seek
     volume: f32,
+    gain: f32,  // replaygain factor for the loaded track
+    boost: f32, // manual factor on top, for tracks that are still too quiet
+    crossfade: Option<Crossfade>,
+    fade_duration: Duration, // of the ramp on play, pause and stop, zero cuts instantly
+    fade: Arc<Mutex<FadeState>>,
+    decoder: DecoderKind,
+    client: reqwest::Client, // without a total timeout, tracks can take long to arrive
+    retries: u32,            // for transient failures while downloading a stream
+    command_tx: mpsc::Sender<PlayerCommand>,
+    // Bumped whenever the current sink is replaced or stopped, so its end callback goes stale
+    generation: Arc<AtomicU64>,
+}
+
+/// The previous track, kept playing while it fades out under the new one
+struct Crossfade {
+    outgoing: Arc<Sink>,
+    outgoing_gain: f32,
+    duration: Duration,
+    started: bool, // set once the incoming track is loaded
+}
+
+/// Shared with the task running a play, pause or stop fade. Holding the lock while acting on
+/// the sink keeps a fade that was overtaken from pausing a sink that was played again since
+struct FadeState {
+    generation: u64, // bumped by every fade and whatever cuts one short
+    fading: bool,
+    level: f32, // volume the sink should end up at, kept up to date while fading in
+}
+
+#[derive(Clone, Copy)]
+enum Fade {
+    In,
+    Pause,
+    Stop,
 }
 
 #[derive(Debug)]
@@ -26,7 +79,18 @@ pub enum PlayerCommand {
     SetVolume(f64), // Seek(Time)
     SeekRelative(i64),
     SeekAbsolute(u64),
-    // TrackFinished,
+    /// The sink of the given load generation played to its end
+    TrackFinished(u64),
+    /// The output device went away, e.g. headphones were unplugged
+    DeviceLost,
+    /// MPRIS TrackList calls, tracks named by their `track_object_path`
+    GoTo(TrackId),
+    RemoveTrack(TrackId),
+    AddTrack {
+        song_id: String,
+        after: TrackId,
+        play: bool,
+    },
 }
 pub struct PlayerState {
     pub status: PlaybackStatus,
@@ -35,52 +99,206 @@ pub struct PlayerState {
     pub can_go_next: bool,
     pub can_go_previous: bool,
     pub position: Time,
+    // The queue as the MPRIS TrackList shows it
+    pub track_list: Vec<Track>,
 }
 
 pub type SharedPlayerState = Arc<RwLock<PlayerState>>;
 
 impl Player {
-    pub fn new() -> Self {
+    pub fn new(
+        client: reqwest::Client,
+        retries: u32,
+        command_tx: mpsc::Sender<PlayerCommand>,
+        device: Option<String>,
+        fade_duration: Duration,
+        decoder: DecoderKind,
+    ) -> Self {
         let stream_handle =
-            OutputStreamBuilder::open_default_stream().expect("open default audio stream");
+            open_output(&command_tx, device.as_deref()).expect("open default audio stream");
+        #[cfg(not(feature = "symphonia"))]
+        if decoder == DecoderKind::Symphonia {
+            log::warn!("Built without the symphonia feature, decoding with rodio instead");
+        }
         Self {
             // _stream: stream,
             stream_handle,
+            device,
             sink: None,
+            track_data: None,
+            decoded_duration: None,
+            live: false,
             volume: 1.0,
+            gain: 1.0,
+            boost: 1.0,
+            crossfade: None,
+            fade_duration,
+            fade: Arc::new(Mutex::new(FadeState {
+                generation: 0,
+                fading: false,
+                level: 1.0,
+            })),
+            decoder,
+            client,
+            retries,
+            command_tx,
+            generation: Arc::new(AtomicU64::new(0)),
         }
     }
 
     pub async fn load_url(&mut self, url: &str) -> Result<()> {
         // stop current playback
+        self.cancel_running_crossfade();
         if let Some(sink) = self.sink.take() {
             sink.stop();
         }
 
-        let resp = reqwest::get(url).await?;
-        let bytes = resp.bytes().await?;
-
-        let data_len = bytes.len();
-        let cursor = Cursor::new(bytes);
+        let (status, content_type, bytes) = with_retry(self.retries, || async {
+            let response = self.client.get(url).send().await?;
+            let status = response.status();
+            let content_type = content_type(&response);
+            Ok((status, content_type, response.bytes().await?))
+        })
+        .await?;
+        ensure_audio(status, content_type.as_deref())?;
+        self.load_data(Arc::from(bytes.as_ref()))
+    }
+    /// Play a track that was downloaded for offline use
+    pub async fn load_file(&mut self, path: &Path) -> Result<()> {
+        self.cancel_running_crossfade();
+        if let Some(sink) = self.sink.take() {
+            sink.stop();
+        }
+        let bytes = tokio::fs::read(path).await?;
+        self.load_data(Arc::from(bytes))
+    }
+    /// Play an endless stream such as internet radio while it arrives, where `load_url` would
+    /// wait for the whole body. The decoder only runs once `url` answered
+    pub async fn load_live_url(&mut self, url: &str) -> Result<()> {
+        self.cancel_running_crossfade();
+        if let Some(sink) = self.sink.take() {
+            sink.stop();
+        }
+        let mut response = self.client.get(url).send().await?;
+        ensure_audio(response.status(), content_type(&response).as_deref())?;
+        let (tx, rx) = mpsc::channel(LIVE_BUFFER_CHUNKS);
+        // Ends when the server closes the stream or the sink reading it is dropped
+        tokio::spawn(async move {
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        if tx.send(chunk.to_vec()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::warn!("Live stream failed: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        let reader = LiveStream {
+            chunks: rx,
+            current: Vec::new(),
+            offset: 0,
+            position: 0,
+        };
+        let decoder = self.decoder;
+        // Probing the format waits on the first chunks, which must not happen on the runtime
+        let source = tokio::task::spawn_blocking(move || -> Result<Box<dyn Source + Send>> {
+            Ok(match decoder {
+                #[cfg(feature = "symphonia")]
+                DecoderKind::Symphonia => {
+                    let reader = symphonia::core::io::ReadOnlySource::new(reader);
+                    Box::new(SymphoniaSource::new(Box::new(reader))?)
+                }
+                _ => Box::new(
+                    DecoderBuilder::new()
+                        .with_data(reader)
+                        .with_seekable(false)
+                        .build()?,
+                ),
+            })
+        })
+        .await??;
+        self.track_data = None;
+        self.decoded_duration = None;
+        self.live = true;
+        self.start_source(source);
+        Ok(())
+    }
+    fn load_data(&mut self, data: Arc<[u8]>) -> Result<()> {
+        let data_len = data.len();
+        self.track_data = Some(data.clone());
+        let cursor = Cursor::new(data);
         // let source = Decoder::try_from(cursor)?;
-        let source = DecoderBuilder::new()
-            .with_data(cursor)
-            .with_seekable(true)
-            .with_byte_len(data_len as u64)
-            .build()?;
-
-        let sink = Sink::connect_new(self.stream_handle.mixer());
-        sink.append(source);
-        sink.set_volume(self.volume);
-        self.sink = Some(sink);
-
+        let source: Box<dyn Source + Send> = match self.decoder {
+            #[cfg(feature = "symphonia")]
+            DecoderKind::Symphonia => Box::new(SymphoniaSource::new(Box::new(cursor))?),
+            _ => Box::new(
+                DecoderBuilder::new()
+                    .with_data(cursor)
+                    .with_seekable(true)
+                    .with_byte_len(data_len as u64)
+                    .build()?,
+            ),
+        };
+        self.decoded_duration = source.total_duration();
+        self.live = false;
+        self.start_source(source);
         Ok(())
     }
-    pub fn is_finished(&self) -> bool {
-        match &self.sink {
-            Some(sink) => sink.empty() && !sink.is_paused(),
-            None => false,
+    fn start_source<S>(&mut self, source: S)
+    where
+        S: Source + Send + 'static,
+    {
+        self.cancel_fade();
+        let sink = Arc::new(Sink::connect_new(self.stream_handle.mixer()));
+        sink.append(source);
+        sink.append(self.end_callback());
+        match &mut self.crossfade {
+            Some(fade) => {
+                // Fade in from silence, tick_crossfade ramps it up
+                sink.set_volume(0.0);
+                fade.started = true;
+            }
+            None if self.fade_duration.is_zero() => sink.set_volume(self.track_volume()),
+            // Silent until `play` ramps it up, a new sink starts out playing
+            None => sink.set_volume(0.0),
         }
+        self.sink = Some(sink);
+    }
+    /// Runs on the audio thread once the track before it is done, including when the sink is
+    /// stopped or dropped, which is why it only reports an end for the generation still current
+    fn end_callback(&self) -> EmptyCallback {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = self.generation.clone();
+        let tx = self.command_tx.clone();
+        EmptyCallback::new(Box::new(move || {
+            if current.load(Ordering::SeqCst) == generation
+                && let Err(e) = tx.try_send(PlayerCommand::TrackFinished(generation))
+            {
+                log::warn!("Failed to report the end of a track: {}", e);
+            }
+        }))
+    }
+    /// Length of the loaded track according to the decoder, for when the server has none
+    pub fn decoded_duration(&self) -> Option<Duration> {
+        self.decoded_duration
+    }
+    /// Forget the current sink's end, e.g. because it was stopped rather than played out
+    fn invalidate_end(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+    /// Whether a `TrackFinished` is about the track that is loaded now
+    pub fn is_current_generation(&self, generation: u64) -> bool {
+        self.sink.is_some() && self.generation.load(Ordering::SeqCst) == generation
+    }
+    /// Whether the sink has nothing left to play
+    pub fn is_drained(&self) -> bool {
+        self.sink.as_ref().is_some_and(|sink| sink.empty())
     }
     pub fn has_track_loaded(&self) -> bool {
         self.sink.is_some()
@@ -93,25 +311,210 @@ impl Player {
             Time::ZERO
         }
     }
+    /// Resume or start the sink, fading in unless a crossfade is already ramping it up
     pub fn play(&self) -> Result<()> {
         if let Some(sink) = &self.sink {
-            sink.play();
+            let crossfading = self.crossfade.as_ref().is_some_and(|f| f.started);
+            if self.fade_duration.is_zero() || crossfading {
+                self.cancel_fade();
+                sink.play();
+            } else {
+                // Cut a fade out short first, so it can't pause the sink after it was played
+                self.cancel_fade();
+                // Picks up from wherever the fade out got to, from silence after a pause
+                if sink.is_paused() {
+                    sink.set_volume(0.0);
+                }
+                sink.play();
+                self.start_fade(sink.clone(), Fade::In);
+            }
+        }
+        if let Some(fade) = &self.crossfade {
+            fade.outgoing.play();
         }
         Ok(())
     }
-    pub fn stop(&self) -> Result<()> {
+    /// Pause at once, without a fade, e.g. for a track loaded to be played later
+    pub fn pause_immediately(&self) {
         if let Some(sink) = &self.sink {
-            sink.stop();
+            self.cancel_fade();
+            sink.pause();
+        }
+    }
+    pub fn stop(&mut self) -> Result<()> {
+        self.invalidate_end();
+        self.cancel_crossfade();
+        if let Some(sink) = &self.sink {
+            if self.fade_duration.is_zero() || sink.is_paused() {
+                self.cancel_fade();
+                sink.stop();
+            } else {
+                self.start_fade(sink.clone(), Fade::Stop);
+            }
         }
         Ok(())
     }
     pub fn pause(&self) -> Result<()> {
         if let Some(sink) = &self.sink {
-            sink.pause();
+            if self.fade_duration.is_zero() {
+                self.cancel_fade();
+                sink.pause();
+            } else if !sink.is_paused() {
+                self.start_fade(sink.clone(), Fade::Pause);
+            }
+        }
+        if let Some(fade) = &self.crossfade {
+            fade.outgoing.pause();
+        }
+        Ok(())
+    }
+    /// Ramp the sink's volume on a blocking task, then pause or stop it for a fade out.
+    /// Starting another fade or cutting this one short leaves the sink to the newer call
+    fn start_fade(&self, sink: Arc<Sink>, fade: Fade) {
+        let generation = {
+            let mut state = self.fade.lock().unwrap();
+            state.generation += 1;
+            state.fading = true;
+            state.level = self.track_volume();
+            state.generation
+        };
+        let shared = self.fade.clone();
+        let step = self.fade_duration / FADE_STEPS;
+        let from = sink.volume();
+        tokio::task::spawn_blocking(move || {
+            for i in 1..=FADE_STEPS {
+                std::thread::sleep(step);
+                let mut state = shared.lock().unwrap();
+                if state.generation != generation {
+                    return;
+                }
+                let progress = i as f32 / FADE_STEPS as f32;
+                let volume = match fade {
+                    Fade::In => from + (state.level - from) * progress,
+                    Fade::Pause | Fade::Stop => from * (1.0 - progress),
+                };
+                sink.set_volume(volume);
+                if i == FADE_STEPS {
+                    match fade {
+                        Fade::In => {}
+                        Fade::Pause => sink.pause(),
+                        Fade::Stop => sink.stop(),
+                    }
+                    state.fading = false;
+                }
+            }
+        });
+    }
+    /// Stop a running fade where it is, the caller sets the volume it wants
+    fn cancel_fade(&self) {
+        let mut state = self.fade.lock().unwrap();
+        state.generation += 1;
+        state.fading = false;
+    }
+    /// Set the sink to the track volume, or let a fade in head for it instead
+    fn apply_volume(&self) {
+        let level = self.track_volume();
+        let mut state = self.fade.lock().unwrap();
+        state.level = level;
+        if !state.fading
+            && let Some(sink) = &self.sink
+        {
+            sink.set_volume(level);
+        }
+    }
+    /// Keep the current track playing so the next loaded track fades in over it
+    pub fn begin_crossfade(&mut self, duration: Duration) {
+        self.cancel_crossfade();
+        self.cancel_fade();
+        self.invalidate_end();
+        if let Some(outgoing) = self.sink.take() {
+            self.crossfade = Some(Crossfade {
+                outgoing,
+                outgoing_gain: self.gain * self.boost,
+                duration,
+                started: false,
+            });
+        }
+    }
+    pub fn is_crossfading(&self) -> bool {
+        self.crossfade.is_some()
+    }
+    /// Ramp the volumes of both tracks, finishing the fade once the incoming one has played for
+    /// the duration. Its position rather than the clock measures it, so a pause holds the fade
+    pub fn tick_crossfade(&mut self) {
+        let Some(fade) = self.crossfade.as_ref().filter(|f| f.started) else {
+            return;
+        };
+        let Some(sink) = &self.sink else {
+            return;
+        };
+        let progress = (sink.get_pos().as_secs_f32() / fade.duration.as_secs_f32()).min(1.0);
+        if progress >= 1.0 || fade.outgoing.empty() {
+            self.cancel_crossfade();
+            return;
+        }
+        fade.outgoing
+            .set_volume(self.volume * fade.outgoing_gain * (1.0 - progress));
+        sink.set_volume(self.track_volume() * progress);
+    }
+    /// Drop the outgoing track and play the current one at full volume
+    pub fn cancel_crossfade(&mut self) {
+        if let Some(fade) = self.crossfade.take() {
+            fade.outgoing.stop();
+        }
+        self.apply_volume();
+    }
+    /// A load during a fade that already has its incoming track is a manual skip
+    fn cancel_running_crossfade(&mut self) {
+        if self.crossfade.as_ref().is_some_and(|f| f.started) {
+            self.cancel_crossfade();
+        }
+    }
+    /// Names of the outputs that can be passed to `set_output_device`
+    pub fn output_devices() -> Result<Vec<String>> {
+        Ok(cpal::default_host()
+            .output_devices()?
+            .filter_map(|d| d.name().ok())
+            .collect())
+    }
+    pub fn output_device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+    /// Play through the named output, or the default one for None, resuming where it was
+    pub fn set_output_device(&mut self, name: Option<&str>) -> Result<()> {
+        self.device = name.map(str::to_string);
+        self.reinit_output()
+    }
+    /// Reopen the output, e.g. after the device changed, and carry on from the same spot
+    pub fn reinit_output(&mut self) -> Result<()> {
+        // A stopped or finished sink has nothing to carry over
+        let resume = self
+            .sink
+            .as_ref()
+            .filter(|sink| !sink.empty())
+            .map(|sink| (sink.get_pos(), sink.is_paused()));
+        self.cancel_crossfade();
+        self.invalidate_end();
+        if let Some(sink) = self.sink.take() {
+            sink.stop();
+        }
+        self.stream_handle = open_output(&self.command_tx, self.device.as_deref())?;
+        if let (Some(data), Some((position, paused))) = (self.track_data.clone(), resume) {
+            self.load_data(data)?;
+            if let Some(sink) = &self.sink {
+                if paused {
+                    sink.pause();
+                }
+                let _ = sink.try_seek(position);
+            }
         }
         Ok(())
     }
-    pub fn seek_relative(&self, delta_sec: i64) -> Result<()> {
+    pub fn seek_relative(&mut self, delta_sec: i64) -> Result<()> {
+        if self.live {
+            return Ok(());
+        }
+        self.cancel_crossfade();
         if let Some(sink) = &self.sink {
             let current_pos = sink.get_pos();
             let target = if delta_sec >= 0 {
@@ -127,7 +530,11 @@ impl Player {
         }
         Ok(())
     }
-    pub fn seek_absolute(&self, seconds: u64) -> Result<()> {
+    pub fn seek_absolute(&mut self, seconds: u64) -> Result<()> {
+        if self.live {
+            return Ok(());
+        }
+        self.cancel_crossfade();
         if let Some(sink) = &self.sink {
             let _ = sink.try_seek(Duration::from_secs(seconds));
         }
@@ -135,12 +542,172 @@ impl Player {
     }
     pub fn set_volume(&mut self, volume: f32) -> Result<()> {
         self.volume = volume.clamp(0.0, 1.0);
-        if let Some(sink) = &self.sink {
-            sink.set_volume(self.volume);
-        }
+        self.apply_volume();
         Ok(())
     }
+    /// Loudness correction applied on top of the user volume
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+        self.apply_volume();
+    }
+    /// Manual boost applied on top of the volume and the replaygain correction
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+        self.apply_volume();
+    }
+    fn track_volume(&self) -> f32 {
+        self.volume * self.gain * self.boost
+    }
     pub fn _get_volume(&self) -> f32 {
         self.volume
     }
 }
+
+fn content_type(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// Turn away an error status or a page where audio was expected before the decoder gets it.
+/// An expired session or a rate limit shows up as one, which would otherwise only fail to decode
+fn ensure_audio(status: reqwest::StatusCode, content_type: Option<&str>) -> Result<()> {
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        anyhow::bail!("server answered {} (check credentials)", status);
+    }
+    if !status.is_success() {
+        anyhow::bail!("server answered {}", status);
+    }
+    let mime = content_type
+        .and_then(|c| c.split(';').next())
+        .map(|m| m.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    if mime == "text/html" || mime == "application/xhtml+xml" {
+        anyhow::bail!("server returned HTML, not audio (check credentials)");
+    }
+    if mime.starts_with("text/") || mime.ends_with("json") || mime.ends_with("xml") {
+        anyhow::bail!("server returned {}, not audio (check credentials)", mime);
+    }
+    Ok(())
+}
+
+/// An http stream read as its chunks arrive. Reads block until the next chunk is there, which
+/// is why it is only ever read off the async runtime, by the decoder and the audio thread
+struct LiveStream {
+    chunks: mpsc::Receiver<Vec<u8>>,
+    current: Vec<u8>,
+    offset: usize, // read so far from `current`
+    position: u64,
+}
+
+impl Read for LiveStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset >= self.current.len() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => {
+                    self.current = chunk;
+                    self.offset = 0;
+                }
+                // The stream ended, which the sink sees as the end of the track
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.current.len() - self.offset);
+        buf[..n].copy_from_slice(&self.current[self.offset..self.offset + n]);
+        self.offset += n;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+/// The decoder is told the stream can't seek, it may still ask where it is
+impl Seek for LiveStream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.position),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "a live stream can't seek",
+            )),
+        }
+    }
+}
+
+/// Open the named output, or the default one when it is None or no longer there. A lost device is
+/// reported through the command channel rather than printed over the UI
+fn open_output(
+    command_tx: &mpsc::Sender<PlayerCommand>,
+    device: Option<&str>,
+) -> Result<OutputStream> {
+    let tx = command_tx.clone();
+    let on_error = move |err: cpal::StreamError| {
+        log::warn!("Audio stream error: {}", err);
+        if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+            let _ = tx.try_send(PlayerCommand::DeviceLost);
+        }
+    };
+    let named = device.and_then(|name| {
+        let found = cpal::default_host()
+            .output_devices()
+            .ok()?
+            .find(|d| d.name().is_ok_and(|n| n == name));
+        if found.is_none() {
+            log::warn!("Output device '{}' not found, using the default", name);
+        }
+        found
+    });
+    let builder = match named {
+        Some(device) => OutputStreamBuilder::from_device(device),
+        None => OutputStreamBuilder::from_default_device(),
+    };
+    let mut stream = builder
+        .and_then(|builder| {
+            builder
+                .with_error_callback(on_error)
+                .open_stream_or_fallback()
+        })
+        // Let rodio try the other devices, without the callback
+        .or_else(|_| OutputStreamBuilder::open_default_stream())?;
+    // Streams are replaced while the UI is up, where the drop message would garble the screen
+    stream.log_on_drop(false);
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn message(status: StatusCode, content_type: Option<&str>) -> String {
+        ensure_audio(status, content_type).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_ensure_audio_rejects_error_statuses() {
+        let unauthorized = message(StatusCode::UNAUTHORIZED, Some("audio/flac"));
+        assert!(unauthorized.contains("401"));
+        assert!(unauthorized.contains("check credentials"));
+
+        let server_error = message(StatusCode::INTERNAL_SERVER_ERROR, Some("audio/flac"));
+        assert!(server_error.contains("500"));
+        assert!(!server_error.contains("credentials"));
+    }
+
+    #[test]
+    fn test_ensure_audio_rejects_pages() {
+        let html = message(StatusCode::OK, Some("text/html; charset=utf-8"));
+        assert!(html.contains("HTML"));
+
+        let json = message(StatusCode::OK, Some("application/json"));
+        assert!(json.contains("application/json"));
+    }
+
+    #[test]
+    fn test_ensure_audio_accepts_audio() {
+        assert!(ensure_audio(StatusCode::OK, Some("audio/flac")).is_ok());
+        // Some servers send no content type at all, the decoder gets to decide
+        assert!(ensure_audio(StatusCode::OK, None).is_ok());
+    }
+}
@@ -1,6 +1,7 @@
-use crate::app::Track;
+use crate::app::{Album, Artist, Track};
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
 
 pub struct SearchEngine {
     matcher: SkimMatcherV2,
@@ -9,11 +10,21 @@ pub struct SearchEngine {
 }
 
 #[derive(Clone, Debug)]
-pub struct SearchResult {
-    pub track: Track,
+pub struct SearchResult<'a> {
+    pub track: &'a Track,
     pub score: i64,
     pub _match_field: MatchField,
 }
+#[derive(Clone, Debug)]
+pub struct AlbumResult {
+    pub album: Album,
+    pub score: i64,
+}
+#[derive(Clone, Debug)]
+pub struct ArtistResult {
+    pub artist: Artist,
+    pub score: i64,
+}
 #[derive(Clone, Debug, PartialEq)]
 pub enum MatchField {
     Title,
@@ -30,7 +41,16 @@ impl SearchEngine {
             max_results,
         }
     }
-    pub fn search(&self, query: &str, tracks: &[Track]) -> Vec<SearchResult> {
+    pub fn threshold(&self) -> i64 {
+        self.threshold
+    }
+    /// Lower scores let looser matches through, see `fuzzy_threshold` in the config
+    pub fn set_threshold(&mut self, threshold: i64) {
+        self.threshold = threshold;
+    }
+    /// Tracks that fuzzy match `query`, best first. A short query can match most of the
+    /// library, so results borrow the tracks and the caller clones the ones it keeps
+    pub fn search<'a>(&self, query: &str, tracks: &'a [Track]) -> Vec<SearchResult<'a>> {
         if query.is_empty() {
             return Vec::new();
         }
@@ -39,18 +59,13 @@ impl SearchEngine {
         for track in tracks {
             let mut best_score: i64 = 0;
             let mut match_field = MatchField::Title;
-            if let Some(score) = self
-                .matcher
-                .fuzzy_match(&track.title.to_lowercase(), &query_formatted)
+            if let Some(score) = self.score(&track.title, &query_formatted)
                 && score > best_score
             {
                 best_score = score;
                 match_field = MatchField::Title;
             }
-            if let Some(score) = self
-                .matcher
-                .fuzzy_match(&track.artist.to_lowercase(), &query_formatted)
-            {
+            if let Some(score) = self.score(&track.artist, &query_formatted) {
                 if score > best_score {
                     best_score = score;
                     match_field = MatchField::Artist;
@@ -58,10 +73,7 @@ impl SearchEngine {
                     match_field = MatchField::Multiple;
                 }
             }
-            if let Some(score) = self
-                .matcher
-                .fuzzy_match(&track.album.to_lowercase(), &query_formatted)
-            {
+            if let Some(score) = self.score(&track.album, &query_formatted) {
                 if score > best_score {
                     best_score = score;
                     match_field = MatchField::Album;
@@ -69,8 +81,11 @@ impl SearchEngine {
                     match_field = MatchField::Multiple;
                 }
             }
-            let combined = format!("{} {}", track.artist, track.title).to_lowercase();
-            if let Some(score) = self.matcher.fuzzy_match(&combined, &query_formatted)
+            if is_subsequence(&query_formatted, [track.artist.as_str(), " ", &track.title])
+                && let Some(score) = self.matcher.fuzzy_match(
+                    &format!("{} {}", track.artist, track.title).to_lowercase(),
+                    &query_formatted,
+                )
                 && score > best_score
             {
                 best_score = score;
@@ -78,18 +93,71 @@ impl SearchEngine {
             }
             if best_score >= self.threshold {
                 results.push(SearchResult {
-                    track: track.clone(),
+                    track,
                     score: best_score,
                     _match_field: match_field,
                 });
             }
         }
-        results.sort_by(|a, b| b.score.cmp(&a.score));
+        results.sort_by_key(|r| std::cmp::Reverse(r.score));
         results.truncate(self.max_results);
         results
     }
+    /// Albums whose name or artist fuzzy matches `query`, best first
+    pub fn search_albums(&self, query: &str, albums: &[Album]) -> Vec<AlbumResult> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let query_formatted = query.to_lowercase();
+        let mut results: Vec<AlbumResult> = albums
+            .iter()
+            .filter_map(|album| {
+                let combined = format!("{} {}", album.artist, album.name);
+                let score = [album.name.as_str(), album.artist.as_str(), &combined]
+                    .iter()
+                    .filter_map(|text| self.score(text, &query_formatted))
+                    .max()?;
+                (score >= self.threshold).then(|| AlbumResult {
+                    album: album.clone(),
+                    score,
+                })
+            })
+            .collect();
+        results.sort_by_key(|r| std::cmp::Reverse(r.score));
+        results.truncate(self.max_results);
+        results
+    }
+    /// Artists whose name fuzzy matches `query`, best first
+    pub fn search_artists(&self, query: &str, artists: &[Artist]) -> Vec<ArtistResult> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let query_formatted = query.to_lowercase();
+        let mut results: Vec<ArtistResult> = artists
+            .iter()
+            .filter_map(|artist| {
+                let score = self.score(&artist.name, &query_formatted)?;
+                (score >= self.threshold).then(|| ArtistResult {
+                    artist: artist.clone(),
+                    score,
+                })
+            })
+            .collect();
+        results.sort_by_key(|r| std::cmp::Reverse(r.score));
+        results.truncate(self.max_results);
+        results
+    }
+    /// Fuzzy score of `text`. Most of a big library can't match at all, those are turned away
+    /// before lowercasing a copy and scoring it
+    fn score(&self, text: &str, query_formatted: &str) -> Option<i64> {
+        if !is_subsequence(query_formatted, [text]) {
+            return None;
+        }
+        self.matcher
+            .fuzzy_match(&text.to_lowercase(), query_formatted)
+    }
     // same as above but is faster at the expense that it isn't fuzzy
-    pub fn _search_exact(&self, query: &str, tracks: &[Track]) -> Vec<SearchResult> {
+    pub fn _search_exact<'a>(&self, query: &str, tracks: &'a [Track]) -> Vec<SearchResult<'a>> {
         if query.is_empty() {
             return Vec::new();
         }
@@ -114,18 +182,47 @@ impl SearchEngine {
                     + if artist_match { 80 } else { 0 }
                     + if album_match { 60 } else { 0 };
                 results.push(SearchResult {
-                    track: track.clone(),
+                    track,
                     score,
                     _match_field: match_field,
                 });
             }
         }
-        results.sort_by(|a, b| b.score.cmp(&a.score));
+        results.sort_by_key(|r| std::cmp::Reverse(r.score));
         results.truncate(self.max_results);
         results
     }
 }
 
+/// Whether the lowercase `query` appears in order within `texts` put together, ignoring case.
+/// The fuzzy matcher never scores text that fails this, so checking first keeps the same results
+fn is_subsequence<'a>(query: &str, texts: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut query = query.chars().peekable();
+    for c in texts.into_iter().flat_map(str::chars) {
+        let Some(&q) = query.peek() else {
+            return true;
+        };
+        if c.is_ascii() {
+            if c.to_ascii_lowercase() == q {
+                query.next();
+            }
+        } else {
+            for lower in c.to_lowercase() {
+                query.next_if_eq(&lower);
+            }
+        }
+    }
+    query.peek().is_none()
+}
+
+/// Lowercase and strip diacritics so that "bjork" matches "Björk"
+pub fn normalize_for_match(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,10 +235,10 @@ mod tests {
             artist,
             album,
             cover_art: Some(String::new()),
-            duration: 0,
             play_count: Some(0),
             track_number: Some(1),
             genres: vec!["Rock".to_string(), "Hip-Hop".to_string()],
+            ..Default::default()
         }
     }
     #[test]
@@ -216,4 +313,146 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results.first().unwrap().track.title, "Rolling in the Deep")
     }
+    #[test]
+    fn test_fuzzy_search_albums_by_name_and_artist() {
+        let engine = SearchEngine::new(50, 100);
+        let album = |name: &str, artist: &str| Album {
+            id: name.to_string(),
+            name: name.to_string(),
+            artist: artist.to_string(),
+            song_count: None,
+            year: None,
+            duration: None,
+            cover_art: None,
+        };
+        let albums = vec![
+            album("A Night at the Opera", "Queen"),
+            album("The Slim LP", "Emienem"),
+            album("21", "Adele"),
+        ];
+        let results = engine.search_albums("opera", &albums);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].album.name, "A Night at the Opera");
+        let results = engine.search_albums("adele", &albums);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].album.name, "21");
+    }
+    #[test]
+    fn test_fuzzy_search_artists() {
+        let engine = SearchEngine::new(50, 100);
+        let artist = |name: &str| Artist {
+            id: name.to_string(),
+            name: name.to_string(),
+            album_count: 1,
+        };
+        let artists = vec![artist("Queen"), artist("Adele"), artist("Emienem")];
+        let results = engine.search_artists("queen", &artists);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].artist.name, "Queen");
+        assert!(engine.search_artists("", &artists).is_empty());
+    }
+    /// Tracks named from a small vocabulary, the same ones on every run
+    fn synthetic_library(count: usize) -> Vec<Track> {
+        const WORDS: [&str; 24] = [
+            "love", "night", "blue", "fire", "dream", "heart", "city", "rain", "gold", "wild",
+            "ocean", "shadow", "summer", "river", "electric", "ghost", "velvet", "echo", "paper",
+            "storm", "neon", "silver", "broken", "garden",
+        ];
+        let mut seed: u64 = 42;
+        let mut name = |words: usize| {
+            (0..words)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    WORDS[(seed >> 33) as usize % WORDS.len()]
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        (0..count)
+            .map(|_| create_test_track(name(3), name(2), name(2)))
+            .collect()
+    }
+    /// Searching as it was before the prefilter, scoring every field of every track and cloning
+    /// every match
+    fn search_without_prefilter(
+        engine: &SearchEngine,
+        query: &str,
+        tracks: &[Track],
+    ) -> Vec<Track> {
+        let mut results: Vec<(i64, Track)> = tracks
+            .iter()
+            .filter_map(|t| {
+                let combined = format!("{} {}", t.artist, t.title).to_lowercase();
+                let score = [
+                    t.title.to_lowercase(),
+                    t.artist.to_lowercase(),
+                    t.album.to_lowercase(),
+                    combined,
+                ]
+                .iter()
+                .filter_map(|text| engine.matcher.fuzzy_match(text, query))
+                .max()?;
+                (score >= engine.threshold).then(|| (score, t.clone()))
+            })
+            .collect();
+        results.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        results.truncate(engine.max_results);
+        results.into_iter().map(|(_, t)| t).collect()
+    }
+    #[test]
+    fn test_prefilter_keeps_results() {
+        let engine = SearchEngine::new(30, usize::MAX);
+        let mut tracks = synthetic_library(2_000);
+        tracks.push(create_test_track(
+            "Jóga".to_string(),
+            "BJÖRK".to_string(),
+            "Homogenic".to_string(),
+        ));
+        for query in ["n", "ni", "night", "blue fire", "zzz", "jöga", "björk jó"] {
+            let titles: Vec<String> = engine
+                .search(query, &tracks)
+                .into_iter()
+                .map(|r| r.track.title.clone())
+                .collect();
+            let expected: Vec<String> = search_without_prefilter(&engine, query, &tracks)
+                .into_iter()
+                .map(|t| t.title)
+                .collect();
+            assert_eq!(titles, expected, "query {query:?}");
+        }
+    }
+    /// Typing a query into a 40k track library, against scoring every track on each keystroke.
+    /// Run with `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_search_large_library() {
+        let engine = SearchEngine::new(30, 30);
+        let tracks = synthetic_library(40_000);
+        let queries = [
+            "e", "el", "ele", "elec", "elect", "electr", "electri", "electric",
+        ];
+        let start = std::time::Instant::now();
+        for query in queries {
+            engine.search(query, &tracks);
+        }
+        let prefiltered = start.elapsed();
+        let start = std::time::Instant::now();
+        for query in queries {
+            search_without_prefilter(&engine, query, &tracks);
+        }
+        let unfiltered = start.elapsed();
+        println!("{prefiltered:?} with the prefilter, {unfiltered:?} without");
+        assert!(prefiltered < unfiltered);
+    }
+    #[test]
+    fn test_normalize_strips_diacritics() {
+        assert_eq!(normalize_for_match("Björk"), "bjork");
+        assert_eq!(normalize_for_match("Beyoncé"), "beyonce");
+        assert_eq!(normalize_for_match("Sigur Rós"), "sigur ros");
+    }
+    #[test]
+    fn test_normalize_leaves_plain_text_lowercased() {
+        assert_eq!(normalize_for_match("The Slim LP"), "the slim lp");
+        assert_eq!(normalize_for_match(""), "");
+    }
 }
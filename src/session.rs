@@ -0,0 +1,42 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::app::{RepeatMode, ShuffleMode, Track};
+
+/// Queue and playback settings saved on `Q`, restored on the next start
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    // Track ids only make sense on the server they came from
+    pub server: String,
+    pub queue: Vec<Track>,
+    pub playing_index: usize,
+    // Seconds into the playing track, sessions saved before it was kept start from the top
+    #[serde(default)]
+    pub position_secs: u64,
+    pub volume: f64,
+    pub repeat: RepeatMode,
+    pub shuffle: ShuffleMode,
+}
+
+impl Session {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sonicrust").join("session.json"))
+    }
+    pub fn load() -> Option<Self> {
+        let contents = fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&contents)
+            .inspect_err(|e| log::warn!("Ignoring unreadable session file: {}", e))
+            .ok()
+    }
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("Could not determine the config directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(())
+    }
+}
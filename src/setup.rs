@@ -0,0 +1,206 @@
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{Terminal, backend::Backend};
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::{
+    config::{Config, ConfigError, KEYRING_PLACEHOLDER, KEYRING_SERVICE},
+    subsonic::SubsonicClient,
+    ui,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupField {
+    ServerUrl,
+    Username,
+    Password,
+}
+impl SetupField {
+    pub const ALL: [SetupField; 3] = [
+        SetupField::ServerUrl,
+        SetupField::Username,
+        SetupField::Password,
+    ];
+    pub fn label(&self) -> &'static str {
+        match self {
+            SetupField::ServerUrl => "Server URL",
+            SetupField::Username => "Username",
+            SetupField::Password => "Password",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SetupForm {
+    pub server_url: String,
+    pub username: String,
+    pub password: String,
+    pub focus: usize,
+    pub status: Option<String>,
+}
+impl SetupForm {
+    pub fn focused(&self) -> SetupField {
+        SetupField::ALL[self.focus]
+    }
+    pub fn value(&self, field: SetupField) -> &str {
+        match field {
+            SetupField::ServerUrl => &self.server_url,
+            SetupField::Username => &self.username,
+            SetupField::Password => &self.password,
+        }
+    }
+    fn value_mut(&mut self) -> &mut String {
+        match self.focused() {
+            SetupField::ServerUrl => &mut self.server_url,
+            SetupField::Username => &mut self.username,
+            SetupField::Password => &mut self.password,
+        }
+    }
+    fn next_field(&mut self) {
+        self.focus = (self.focus + 1) % SetupField::ALL.len();
+    }
+    fn previous_field(&mut self) {
+        self.focus = (self.focus + SetupField::ALL.len() - 1) % SetupField::ALL.len();
+    }
+    fn to_config(&self) -> Config {
+        Config {
+            server_url: self.server_url.trim().trim_end_matches('/').to_string(),
+            username: self.username.trim().to_string(),
+            password: self.password.clone(),
+            ..Config::default()
+        }
+    }
+}
+
+/// Load the config, walking the user through creating one on the first run.
+/// Returns `None` when the user cancels the setup
+pub async fn load_or_setup<B: Backend>(terminal: &mut Terminal<B>) -> Result<Option<Config>> {
+    let config = match Config::load() {
+        Ok(c) => c,
+        Err(ConfigError::NotFound { .. }) => return run_setup(terminal).await,
+        Err(ConfigError::ParseError { path, reason }) => {
+            eprintln!(
+                "Failed to parse config at {}:\n {}\nPlease fix the config and restart.",
+                path.display(),
+                reason
+            );
+            std::process::exit(1);
+        }
+        Err(ConfigError::ValidationError(msg)) => {
+            eprintln!(
+                "Invalid config value:\n {}\nPlease fix the config and restart.",
+                msg
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Config error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    Ok(Some(config))
+}
+
+async fn run_setup<B: Backend>(terminal: &mut Terminal<B>) -> Result<Option<Config>> {
+    let mut form = SetupForm::default();
+    loop {
+        terminal
+            .draw(|f| ui::draw_setup(f, &form))
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Tab | KeyCode::Down => form.next_field(),
+            KeyCode::BackTab | KeyCode::Up => form.previous_field(),
+            KeyCode::Backspace => {
+                form.value_mut().pop();
+            }
+            KeyCode::Char(c) => form.value_mut().push(c),
+            KeyCode::Enter if form.focused() != SetupField::Password => form.next_field(),
+            KeyCode::Enter => {
+                form.status = Some("Connecting...".to_string());
+                terminal
+                    .draw(|f| ui::draw_setup(f, &form))
+                    .map_err(|e| anyhow::anyhow!("{}", e))?;
+                match try_connect(&form).await {
+                    Ok(config) => return Ok(Some(config)),
+                    Err(e) => form.status = Some(e.to_string()),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn try_connect(form: &SetupForm) -> Result<Config> {
+    let config = form.to_config();
+    config.validate()?;
+    let client = SubsonicClient::new(&config)?;
+    client.ping().await?;
+    config.save()?;
+    Ok(config)
+}
+
+/// Ask for the password without echoing it and put it in the system keyring,
+/// for configs that use the keyring placeholder as their password
+pub fn store_password_in_keyring() -> Result<()> {
+    let config = Config::load()?;
+    print!(
+        "Password for '{}' on {}: ",
+        config.username, config.server_url
+    );
+    io::stdout().flush()?;
+    enable_raw_mode()?;
+    let password = read_hidden_line();
+    disable_raw_mode()?;
+    println!();
+    let Some(password) = password? else {
+        println!("Cancelled");
+        return Ok(());
+    };
+    keyring::Entry::new(KEYRING_SERVICE, &config.username)?.set_password(&password)?;
+    println!("Password stored in the system keyring");
+    if config.password != KEYRING_PLACEHOLDER {
+        println!(
+            "Set password = \"{}\" in the config to use it",
+            KEYRING_PLACEHOLDER
+        );
+    }
+    Ok(())
+}
+
+fn read_hidden_line() -> Result<Option<String>> {
+    let mut line = String::new();
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(Some(line)),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Char(c) => line.push(c),
+            _ => {}
+        }
+    }
+}
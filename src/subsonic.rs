@@ -1,18 +1,59 @@
 use std::time::Duration;
 
 use crate::app;
-use crate::app::{Album, Artist, Playlists, Track};
-use crate::config::Config;
+use crate::app::{Album, Artist, FolderEntry, Playlists, PodcastEntry, PodcastEpisode, Track};
+use crate::config::{Config, resolve_password};
+use crate::network::{build_client, build_stream_client, with_retry};
 use anyhow::Result;
 use serde::Deserialize;
 use url::Url;
 
+/// The `type` of a getAlbumList2 request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlbumListType {
+    #[default]
+    AlphabeticalByArtist,
+    Frequent,
+    Newest,
+    Recent,
+}
+impl AlbumListType {
+    fn as_param(&self) -> &'static str {
+        match self {
+            AlbumListType::AlphabeticalByArtist => "alphabeticalByArtist",
+            AlbumListType::Frequent => "frequent",
+            AlbumListType::Newest => "newest",
+            AlbumListType::Recent => "recent",
+        }
+    }
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlbumListType::AlphabeticalByArtist => "All",
+            AlbumListType::Frequent => "Most Played",
+            AlbumListType::Newest => "Recently Added",
+            AlbumListType::Recent => "Recently Played",
+        }
+    }
+    pub fn next(&self) -> AlbumListType {
+        match self {
+            AlbumListType::AlphabeticalByArtist => AlbumListType::Frequent,
+            AlbumListType::Frequent => AlbumListType::Newest,
+            AlbumListType::Newest => AlbumListType::Recent,
+            AlbumListType::Recent => AlbumListType::AlphabeticalByArtist,
+        }
+    }
+}
+
 pub struct SubsonicClient {
     base_url: String,
     username: String,
     password: String,
     secret: String,
+    retries: u32,
+    api_version: String,
+    client_name: String,
     client: reqwest::Client,
+    download_client: reqwest::Client, // without a total timeout, for whole files
 }
 
 #[derive(Deserialize, Debug)]
@@ -24,10 +65,18 @@ struct SubsonicResponse<T> {
 #[derive(Deserialize, Debug)]
 struct SubsonicResponseInner<T> {
     status: String,
+    error: Option<SubsonicError>,
     #[serde(flatten)]
     data: T,
 }
 
+#[derive(Deserialize, Debug)]
+struct SubsonicError {
+    code: i32,
+    #[serde(default)]
+    message: String,
+}
+
 impl<T> SubsonicResponse<T> {
     fn is_ok(&self) -> bool {
         self.subsonic_response.status == "ok"
@@ -36,6 +85,12 @@ impl<T> SubsonicResponse<T> {
         if self.is_ok() {
             Ok(self.subsonic_response.data)
             // .context("Response OK but no data")
+        } else if let Some(err) = self.subsonic_response.error {
+            Err(anyhow::anyhow!(
+                "Subsonic error {}: {}",
+                err.code,
+                err.message
+            ))
         } else {
             Err(anyhow::anyhow!("Subsonic error response"))
         }
@@ -62,6 +117,56 @@ struct PlaylistsData {
 struct PlaylistData {
     playlist: Playlist,
 }
+#[derive(Deserialize, Debug)]
+struct TopSongsData {
+    #[serde(rename = "topSongs")]
+    top_songs: TopSongsWrapper,
+}
+
+#[derive(Deserialize, Debug)]
+struct TopSongsWrapper {
+    #[serde(default)]
+    song: Vec<Song>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BookmarksData {
+    bookmarks: BookmarkWrapper,
+}
+
+#[derive(Deserialize, Debug)]
+struct BookmarkWrapper {
+    #[serde(default)]
+    bookmark: Vec<BookmarkInfo>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BookmarkInfo {
+    position: i64,
+    entry: Song,
+}
+
+#[derive(Deserialize, Debug)]
+struct NowPlayingData {
+    #[serde(rename = "nowPlaying")]
+    now_playing: NowPlayingWrapper,
+}
+
+#[derive(Deserialize, Debug)]
+struct NowPlayingWrapper {
+    #[serde(default)]
+    entry: Vec<NowPlayingEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NowPlayingEntry {
+    #[serde(flatten)]
+    song: Song,
+    username: String,
+    #[serde(rename = "playerName")]
+    player_name: Option<String>,
+}
+
 #[derive(Deserialize, Debug)]
 struct ArtistData {
     artist: Discography,
@@ -124,6 +229,27 @@ struct AlbumInfo {
     id: String,
     name: String,
     artist: String,
+    // Not every server returns these, so keep them optional
+    #[serde(rename = "songCount")]
+    song_count: Option<i32>,
+    year: Option<i32>,
+    duration: Option<i64>,
+    #[serde(rename = "coverArt")]
+    cover_art: Option<String>,
+}
+
+impl AlbumInfo {
+    fn into_album(self) -> Album {
+        Album {
+            id: self.id,
+            name: self.name,
+            artist: self.artist,
+            song_count: self.song_count,
+            year: self.year,
+            duration: self.duration,
+            cover_art: self.cover_art,
+        }
+    }
 }
 #[derive(Deserialize, Debug)]
 struct GetAlbumResponse {
@@ -134,6 +260,158 @@ struct AlbumDetail {
     song: Vec<Song>,
 }
 #[derive(Deserialize, Debug)]
+struct SongData {
+    song: Song,
+}
+#[derive(Deserialize, Debug)]
+struct LyricsListData {
+    #[serde(rename = "lyricsList")]
+    lyrics_list: LyricsList,
+}
+#[derive(Deserialize, Debug)]
+struct LyricsList {
+    #[serde(default, rename = "structuredLyrics")]
+    structured_lyrics: Vec<StructuredLyrics>,
+}
+#[derive(Deserialize, Debug)]
+struct StructuredLyrics {
+    #[serde(default)]
+    synced: bool,
+    // Milliseconds, positive shows every line sooner
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    line: Vec<LyricLineInfo>,
+}
+#[derive(Deserialize, Debug)]
+struct LyricLineInfo {
+    start: Option<i64>,
+    value: String,
+}
+#[derive(Deserialize, Debug)]
+struct LyricsData {
+    lyrics: LyricsInfo,
+}
+#[derive(Deserialize, Debug)]
+struct LyricsInfo {
+    value: Option<String>,
+}
+#[derive(Deserialize, Debug)]
+struct IndexesData {
+    indexes: Indexes,
+}
+#[derive(Deserialize, Debug)]
+struct Indexes {
+    #[serde(default)]
+    index: Vec<FolderIndex>,
+    // Songs that sit directly in a music folder
+    #[serde(default)]
+    child: Vec<serde_json::Value>,
+}
+#[derive(Deserialize, Debug)]
+struct FolderIndex {
+    #[serde(default)]
+    artist: Vec<DirectoryInfo>,
+}
+#[derive(Deserialize, Debug)]
+struct DirectoryInfo {
+    id: String,
+    name: String,
+}
+#[derive(Deserialize, Debug)]
+struct MusicDirectoryData {
+    directory: MusicDirectory,
+}
+#[derive(Deserialize, Debug)]
+struct MusicDirectory {
+    #[serde(default)]
+    child: Vec<serde_json::Value>,
+}
+// A child with isDir set, which has none of the song fields
+#[derive(Deserialize, Debug)]
+struct ChildDirectory {
+    id: String,
+    title: String,
+}
+#[derive(Deserialize, Debug)]
+struct ArtistInfoData {
+    // Left out by some servers when they know nothing about the artist
+    #[serde(default, rename = "artistInfo2")]
+    artist_info: ArtistInfoDetails,
+}
+#[derive(Deserialize, Debug, Default)]
+struct ArtistInfoDetails {
+    #[serde(default)]
+    biography: Option<String>,
+    #[serde(default, rename = "similarArtist")]
+    similar_artist: Vec<SimilarArtistInfo>,
+}
+#[derive(Deserialize, Debug)]
+struct SimilarArtistInfo {
+    #[serde(default)]
+    id: Option<String>,
+    name: String,
+}
+#[derive(Deserialize, Debug)]
+struct RadioStationsData {
+    #[serde(rename = "internetRadioStations")]
+    internet_radio_stations: RadioStationsWrapper,
+}
+#[derive(Deserialize, Debug)]
+struct RadioStationsWrapper {
+    #[serde(default, rename = "internetRadioStation")]
+    internet_radio_station: Vec<RadioStationInfo>,
+}
+#[derive(Deserialize, Debug)]
+struct RadioStationInfo {
+    id: String,
+    name: String,
+    #[serde(rename = "streamUrl")]
+    stream_url: String,
+}
+#[derive(Deserialize, Debug)]
+struct PodcastsData {
+    podcasts: PodcastsWrapper,
+}
+#[derive(Deserialize, Debug)]
+struct PodcastsWrapper {
+    #[serde(default)]
+    channel: Vec<PodcastChannel>,
+}
+#[derive(Deserialize, Debug)]
+struct PodcastChannel {
+    id: String,
+    url: String,
+    // Missing until the server managed to read the feed
+    title: Option<String>,
+    #[serde(default)]
+    episode: Vec<PodcastEpisodeInfo>,
+}
+#[derive(Deserialize, Debug)]
+struct NewestPodcastsData {
+    #[serde(rename = "newestPodcasts")]
+    newest_podcasts: EpisodesWrapper,
+}
+#[derive(Deserialize, Debug)]
+struct EpisodesWrapper {
+    #[serde(default)]
+    episode: Vec<PodcastEpisodeInfo>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PodcastEpisodeInfo {
+    id: String,
+    // Only set once the server downloaded the episode
+    stream_id: Option<String>,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    duration: Option<i64>,
+    cover_art: Option<String>,
+    status: String,
+    publish_date: Option<String>,
+}
+#[derive(Deserialize, Debug)]
 struct Discography {
     album: Vec<AlbumInfo>,
 }
@@ -144,6 +422,10 @@ struct Song {
     title: String,
     artist: String,
     album: String,
+    #[serde(rename = "albumId")]
+    album_id: Option<String>,
+    #[serde(rename = "artistId")]
+    artist_id: Option<String>,
     duration: Option<i64>,
     #[serde(rename = "track")]
     track_number: Option<i32>,
@@ -153,27 +435,83 @@ struct Song {
     display_album_artist: Option<String>,
     #[serde(default)]
     genres: Vec<Genres>,
+    year: Option<i32>,
+    suffix: Option<String>,
+    #[serde(rename = "contentType")]
+    content_type: Option<String>,
+    #[serde(rename = "bitRate")]
+    bit_rate: Option<i32>,
+    #[serde(rename = "replayGain")]
+    replay_gain: Option<ReplayGainInfo>,
+    #[serde(rename = "userRating")]
+    user_rating: Option<i32>,
+    // When the track was starred, missing if it isn't
+    starred: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReplayGainInfo {
+    track_gain: Option<f32>,
+    album_gain: Option<f32>,
+    track_peak: Option<f32>,
+    album_peak: Option<f32>,
 }
 
 impl Song {
-    fn into_track(self, cover_art_url: String) -> Track {
+    /// `cover_art` is left out for results that rarely show it, see `App::cover_art_url`
+    fn into_track(self, cover_art: Option<String>) -> Track {
         Track {
             id: self.id,
             title: self.title,
             artist: self.artist,
             album_artist: self.display_album_artist,
             album: self.album,
-            cover_art: Some(cover_art_url),
+            album_id: self.album_id,
+            artist_id: self.artist_id,
+            cover_art,
             duration: self.duration.unwrap_or(0) * 1_000_000,
             track_number: self.track_number,
             play_count: self.play_count,
             genres: self.genres.iter().map(|f| f.name.clone()).collect(),
+            year: self.year,
+            suffix: self.suffix,
+            content_type: self.content_type,
+            bit_rate: self.bit_rate,
+            replay_gain: self.replay_gain.map(|rg| app::ReplayGain {
+                track_gain: rg.track_gain,
+                album_gain: rg.album_gain,
+                track_peak: rg.track_peak,
+                album_peak: rg.album_peak,
+            }),
+            rating: self.user_rating.filter(|r| *r > 0),
+            starred: self.starred.is_some(),
+        }
+    }
+}
+
+impl PodcastEpisodeInfo {
+    fn into_episode(self, channel: &str, cover_art_url: Option<String>) -> PodcastEpisode {
+        let track = Track {
+            id: self.stream_id.clone().unwrap_or(self.id),
+            title: self.title.unwrap_or_default(),
+            artist: self.artist.unwrap_or_else(|| channel.to_string()),
+            album: self.album.unwrap_or_else(|| channel.to_string()),
+            cover_art: cover_art_url,
+            duration: self.duration.unwrap_or(0) * 1_000_000,
+            ..Default::default()
+        };
+        PodcastEpisode {
+            track,
+            stream_id: self.stream_id,
+            status: self.status,
+            publish_date: self.publish_date,
         }
     }
 }
 
 impl SubsonicClient {
-    fn build_cover_art_url(&self, id: &str) -> Result<String> {
+    pub fn build_cover_art_url(&self, id: &str) -> Result<String> {
         let mut url = Url::parse(&format!("{}/rest/getCoverArt", self.base_url))?;
         let mut params = self.get_auth_params();
         params.push(("id", id.to_string()));
@@ -209,11 +547,23 @@ impl SubsonicClient {
         Ok(Self {
             base_url: config.server_url.clone(),
             username: config.username.clone(),
-            password: config.password.clone(),
+            password: resolve_password(&config.password, &config.username)?,
             secret: config.secret.clone(),
-            client: reqwest::Client::new(),
+            retries: config.network.retries,
+            api_version: config.subsonic.api_version.clone(),
+            client_name: config.subsonic.client_name.clone(),
+            client: build_client(&config.network)?,
+            download_client: build_stream_client(&config.network)?,
         })
     }
+    /// Check that the server is reachable and accepts the credentials
+    pub async fn ping(&self) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Empty {}
+
+        let _: Empty = self.get("ping", vec![]).await?;
+        Ok(())
+    }
     fn get_auth_params(&self) -> Vec<(&str, String)> {
         let salt = &self.secret; // "Secretsaltshaker2000";
         let token = format!("{:x}", md5::compute(format!("{}{}", self.password, salt)));
@@ -221,8 +571,8 @@ impl SubsonicClient {
             ("u", self.username.clone()),
             ("t", token),
             ("s", salt.to_string()),
-            ("v", "1.16.1".to_string()),
-            ("c", "sonicrust".to_string()),
+            ("v", self.api_version.clone()),
+            ("c", self.client_name.clone()),
             ("f", "json".to_string()),
         ]
     }
@@ -237,7 +587,10 @@ impl SubsonicClient {
         for (key, value) in params {
             url.query_pairs_mut().append_pair(key, &value);
         }
-        let res: SubsonicResponse<T> = self.client.get(url).send().await?.json().await?;
+        let res: SubsonicResponse<T> = with_retry(self.retries, || async {
+            self.client.get(url.clone()).send().await?.json().await
+        })
+        .await?;
         res.into_data()
     }
     fn songs_to_tracks(&self, songs: Vec<Song>) -> Result<Vec<Track>> {
@@ -245,10 +598,34 @@ impl SubsonicClient {
             .into_iter()
             .map(|song| {
                 let cover_art = self.build_cover_art_url(&song.id)?;
-                Ok(song.into_track(cover_art))
+                Ok(song.into_track(Some(cover_art)))
             })
             .collect()
     }
+    /// Folders and songs of a directory listing. Files the server couldn't tag are skipped
+    fn children_to_entries(&self, children: Vec<serde_json::Value>) -> Result<Vec<FolderEntry>> {
+        let mut entries = Vec::new();
+        for child in children {
+            if child.get("isDir").and_then(|d| d.as_bool()) == Some(true) {
+                let dir: ChildDirectory = serde_json::from_value(child)?;
+                entries.push(FolderEntry::Directory {
+                    id: dir.id,
+                    name: dir.title,
+                });
+                continue;
+            }
+            match serde_json::from_value::<Song>(child) {
+                Ok(song) => {
+                    let cover_art = self.build_cover_art_url(&song.id)?;
+                    entries.push(FolderEntry::Song(Box::new(
+                        song.into_track(Some(cover_art)),
+                    )));
+                }
+                Err(e) => log::debug!("Skipping an untagged file in a folder: {}", e),
+            }
+        }
+        Ok(entries)
+    }
     pub async fn search(&self, search: &str) -> Result<Vec<Track>> {
         if search.is_empty() {
             return Ok(Vec::new());
@@ -256,50 +633,40 @@ impl SubsonicClient {
         let data: SearchResult3 = self
             .get("search3", vec![("query", search.to_string())])
             .await?;
-        self.songs_to_tracks(data.search_result.song)
+        // Results are mostly read as text, the cover url is built once one is played
+        Ok(data
+            .search_result
+            .song
+            .into_iter()
+            .map(|song| song.into_track(None))
+            .collect())
     }
     pub async fn get_all_albums(&self) -> Result<Vec<Album>> {
         // TODO: Add paginating to help with big libraries
-        let data: GetAlbumListResponse = self
-            .get(
-                "getAlbumList2",
-                vec![
-                    ("type", "alphabeticalByArtist".to_string()),
-                    ("size", 500.to_string()),
-                ],
-            )
-            .await?;
-        let mut albums = Vec::new();
-        for album in data.album_list.album {
-            albums.push(Album {
-                id: album.id,
-                name: album.name,
-                artist: album.artist,
-            });
-        }
-        Ok(albums)
+        self.get_album_list(AlbumListType::default(), 0, 500).await
     }
-    pub async fn get_album_page(&self, offset: usize, limit: usize) -> Result<Vec<Album>> {
+    pub async fn get_album_list(
+        &self,
+        list_type: AlbumListType,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Album>> {
         let data: GetAlbumListResponse = self
             .get(
                 "getAlbumList2",
                 vec![
-                    ("type", "alphabeticalByArtist".to_string()),
+                    ("type", list_type.as_param().to_string()),
                     ("size", limit.to_string()),
                     ("offset", offset.to_string()),
                 ],
             )
             .await?;
-        let mut albums = Vec::new();
-        for a in data.album_list.album {
-            albums.push(Album {
-                id: a.id,
-                name: a.name,
-                artist: a.artist,
-            });
-        }
-
-        Ok(albums)
+        Ok(data
+            .album_list
+            .album
+            .into_iter()
+            .map(AlbumInfo::into_album)
+            .collect())
     }
     pub async fn get_all_favorites(&self) -> Result<Vec<Track>> {
         let data: StarredData = self.get("getStarred2", vec![]).await?;
@@ -333,6 +700,276 @@ impl SubsonicClient {
         }
         Ok(playlists)
     }
+    /// Lyrics of a track, synced ones when the server has them. getLyricsBySongId is an
+    /// OpenSubsonic endpoint, the plain getLyrics lookup by artist and title is the fallback
+    pub async fn get_lyrics(&self, track: &Track) -> Result<Option<app::Lyrics>> {
+        if let Ok(data) = self
+            .get::<LyricsListData>("getLyricsBySongId", vec![("id", track.id.clone())])
+            .await
+        {
+            let mut found = data.lyrics_list.structured_lyrics;
+            found.sort_by_key(|l| !l.synced);
+            if let Some(lyrics) = found.into_iter().find(|l| !l.line.is_empty()) {
+                let offset = lyrics.offset;
+                return Ok(Some(app::Lyrics {
+                    synced: lyrics.synced,
+                    lines: lyrics
+                        .line
+                        .into_iter()
+                        .map(|l| app::LyricLine {
+                            start_ms: l.start.map_or(0, |start| start - offset),
+                            text: l.value,
+                        })
+                        .collect(),
+                }));
+            }
+        }
+        let data: LyricsData = self
+            .get(
+                "getLyrics",
+                vec![
+                    ("artist", track.artist.clone()),
+                    ("title", track.title.clone()),
+                ],
+            )
+            .await?;
+        Ok(data
+            .lyrics
+            .value
+            .filter(|text| !text.trim().is_empty())
+            .map(|text| app::Lyrics {
+                synced: false,
+                lines: text
+                    .lines()
+                    .map(|line| app::LyricLine {
+                        start_ms: 0,
+                        text: line.to_string(),
+                    })
+                    .collect(),
+            }))
+    }
+    /// Most popular songs of an artist, by name. This is an OpenSubsonic/last.fm backed endpoint
+    pub async fn get_top_songs(&self, artist_name: &str, count: usize) -> Result<Vec<Track>> {
+        let data: TopSongsData = self
+            .get(
+                "getTopSongs",
+                vec![
+                    ("artist", artist_name.to_string()),
+                    ("count", count.to_string()),
+                ],
+            )
+            .await?;
+        self.songs_to_tracks(data.top_songs.song)
+    }
+    /// Biography and similar artists, including ones not in the library. getArtistInfo2
+    /// answers with whatever the server found, which may be nothing at all
+    pub async fn get_artist_info(&self, id: &str) -> Result<app::ArtistDetails> {
+        let data: ArtistInfoData = self
+            .get(
+                "getArtistInfo2",
+                vec![
+                    ("id", id.to_string()),
+                    ("includeNotPresent", "true".to_string()),
+                ],
+            )
+            .await?;
+        let info = data.artist_info;
+        Ok(app::ArtistDetails {
+            biography: info
+                .biography
+                .map(|bio| strip_html(&bio))
+                .filter(|bio| !bio.is_empty()),
+            similar: info
+                .similar_artist
+                .into_iter()
+                .map(|a| app::SimilarArtist {
+                    // Servers without the artist give it an empty or negative id
+                    id: a.id.filter(|id| !id.is_empty() && !id.starts_with('-')),
+                    name: a.name,
+                })
+                .collect(),
+        })
+    }
+    /// Save a resume position (in milliseconds) for a track
+    pub async fn create_bookmark(&self, id: &str, position_ms: i64) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Empty {}
+
+        let _: Empty = self
+            .get(
+                "createBookmark",
+                vec![
+                    ("id", id.to_string()),
+                    ("position", position_ms.to_string()),
+                    ("comment", "sonicrust".to_string()),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+    pub async fn get_bookmarks(&self) -> Result<Vec<app::Bookmark>> {
+        let data: BookmarksData = self.get("getBookmarks", vec![]).await?;
+        data.bookmarks
+            .bookmark
+            .into_iter()
+            .map(|b| {
+                let cover_art = self.build_cover_art_url(&b.entry.id)?;
+                Ok(app::Bookmark {
+                    track: b.entry.into_track(Some(cover_art)),
+                    position_ms: b.position,
+                })
+            })
+            .collect()
+    }
+    /// What every client on the server is playing right now, including this one
+    pub async fn get_now_playing(&self) -> Result<Vec<app::NowPlaying>> {
+        let data: NowPlayingData = self.get("getNowPlaying", vec![]).await?;
+        data.now_playing
+            .entry
+            .into_iter()
+            .map(|e| {
+                let cover_art = self.build_cover_art_url(&e.song.id)?;
+                Ok(app::NowPlaying {
+                    track: e.song.into_track(Some(cover_art)),
+                    username: e.username,
+                    player_name: e.player_name,
+                })
+            })
+            .collect()
+    }
+    pub async fn create_playlist(&self, name: &str, song_ids: &[String]) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Empty {}
+
+        let mut params = vec![("name", name.to_string())];
+        params.extend(song_ids.iter().map(|id| ("songId", id.clone())));
+        let _: Empty = self.get("createPlaylist", params).await?;
+        Ok(())
+    }
+    pub async fn update_playlist(
+        &self,
+        playlist_id: &str,
+        add_ids: &[String],
+        remove_indices: &[usize],
+    ) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Empty {}
+
+        let mut params = vec![("playlistId", playlist_id.to_string())];
+        params.extend(add_ids.iter().map(|id| ("songIdToAdd", id.clone())));
+        params.extend(
+            remove_indices
+                .iter()
+                .map(|i| ("songIndexToRemove", i.to_string())),
+        );
+        let _: Empty = self.get("updatePlaylist", params).await?;
+        Ok(())
+    }
+    pub async fn delete_playlist(&self, id: &str) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Empty {}
+
+        let _: Empty = self
+            .get("deletePlaylist", vec![("id", id.to_string())])
+            .await?;
+        Ok(())
+    }
+    /// Top of the folder tree, the directories of every music folder
+    pub async fn get_indexes(&self) -> Result<Vec<FolderEntry>> {
+        let data: IndexesData = self.get("getIndexes", vec![]).await?;
+        let mut entries: Vec<FolderEntry> = data
+            .indexes
+            .index
+            .into_iter()
+            .flat_map(|i| i.artist)
+            .map(|d| FolderEntry::Directory {
+                id: d.id,
+                name: d.name,
+            })
+            .collect();
+        entries.extend(self.children_to_entries(data.indexes.child)?);
+        Ok(entries)
+    }
+    pub async fn get_music_directory(&self, id: &str) -> Result<Vec<FolderEntry>> {
+        let data: MusicDirectoryData = self
+            .get("getMusicDirectory", vec![("id", id.to_string())])
+            .await?;
+        self.children_to_entries(data.directory.child)
+    }
+    /// Episodes as Podcasts tab rows, `channel` names them when the server leaves it out
+    fn episodes_to_entries(
+        &self,
+        episodes: Vec<PodcastEpisodeInfo>,
+        channel: &str,
+    ) -> Result<Vec<PodcastEntry>> {
+        episodes
+            .into_iter()
+            .map(|e| {
+                let cover_art = match &e.cover_art {
+                    Some(id) => Some(self.build_cover_art_url(id)?),
+                    None => None,
+                };
+                Ok(PodcastEntry::Episode(Box::new(
+                    e.into_episode(channel, cover_art),
+                )))
+            })
+            .collect()
+    }
+    pub async fn get_radio_stations(&self) -> Result<Vec<app::RadioStation>> {
+        let data: RadioStationsData = self.get("getInternetRadioStations", vec![]).await?;
+        Ok(data
+            .internet_radio_stations
+            .internet_radio_station
+            .into_iter()
+            .map(|s| app::RadioStation {
+                id: s.id,
+                name: s.name,
+                stream_url: s.stream_url,
+            })
+            .collect())
+    }
+    /// The podcast channels the server follows, without their episodes
+    pub async fn get_podcasts(&self) -> Result<Vec<PodcastEntry>> {
+        let data: PodcastsData = self
+            .get(
+                "getPodcasts",
+                vec![("includeEpisodes", "false".to_string())],
+            )
+            .await?;
+        Ok(data
+            .podcasts
+            .channel
+            .into_iter()
+            .map(|c| PodcastEntry::Channel {
+                id: c.id,
+                title: c.title.unwrap_or(c.url),
+            })
+            .collect())
+    }
+    pub async fn get_podcast_episodes(&self, channel_id: &str) -> Result<Vec<PodcastEntry>> {
+        let data: PodcastsData = self
+            .get(
+                "getPodcasts",
+                vec![
+                    ("id", channel_id.to_string()),
+                    ("includeEpisodes", "true".to_string()),
+                ],
+            )
+            .await?;
+        let mut entries = Vec::new();
+        for channel in data.podcasts.channel {
+            let title = channel.title.unwrap_or(channel.url);
+            entries.extend(self.episodes_to_entries(channel.episode, &title)?);
+        }
+        Ok(entries)
+    }
+    /// The latest episodes across every channel
+    pub async fn get_newest_podcasts(&self, count: usize) -> Result<Vec<PodcastEntry>> {
+        let data: NewestPodcastsData = self
+            .get("getNewestPodcasts", vec![("count", count.to_string())])
+            .await?;
+        self.episodes_to_entries(data.newest_podcasts.episode, "Podcast")
+    }
     pub async fn get_songs_from_playlist(&self, playlist: &Playlists) -> Result<Vec<Track>> {
         let data: PlaylistData = self
             .get("getPlaylist", vec![("id", playlist.id.clone())])
@@ -343,17 +980,12 @@ impl SubsonicClient {
         let data: ArtistData = self
             .get("getArtist", vec![("id", artist.id.clone())])
             .await?;
-        let mut albums: Vec<Album> = Vec::new();
-        let disc = data.artist.album;
-        for album in disc {
-            albums.push(Album {
-                name: album.name,
-                id: album.id,
-                artist: album.artist,
-            });
-        }
-
-        Ok(albums)
+        Ok(data
+            .artist
+            .album
+            .into_iter()
+            .map(AlbumInfo::into_album)
+            .collect())
     }
     pub async fn scrobble(&self, track: &Track, submission: bool) -> Result<()> {
         #[derive(Deserialize)]
@@ -381,19 +1013,32 @@ impl SubsonicClient {
         let _: Empty = self.get(endpoint, vec![("id", track.id.clone())]).await?;
         Ok(())
     }
+    pub async fn get_song(&self, id: &str) -> Result<Track> {
+        let data: SongData = self.get("getSong", vec![("id", id.to_string())]).await?;
+        let cover_art = self.build_cover_art_url(&data.song.id)?;
+        Ok(data.song.into_track(Some(cover_art)))
+    }
+    /// Rate a track from 1 to 5 stars, 0 removes the rating
+    pub async fn set_rating(&self, id: &str, rating: u8) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Empty {}
+
+        let _: Empty = self
+            .get(
+                "setRating",
+                vec![("id", id.to_string()), ("rating", rating.to_string())],
+            )
+            .await?;
+        Ok(())
+    }
     pub async fn get_songs_in_album(&self, album: &Album) -> Result<Vec<Track>> {
-        let data: GetAlbumResponse = self.get("getAlbum", vec![("id", album.id.clone())]).await?;
-        self.songs_to_tracks(data.album.song)
+        self.get_album_songs(&album.id).await
     }
-    pub async fn get_all_songs(&self) -> Result<Vec<Track>> {
-        let albums = self.get_all_albums().await?;
-        let futures = albums.iter().map(|a| self.get_songs_in_album(a));
-        let results = futures::future::join_all(futures).await;
-        let a = results
-            .into_iter()
-            .flat_map(|r| r.unwrap_or_default())
-            .collect::<Vec<_>>();
-        Ok(a)
+    pub async fn get_album_songs(&self, album_id: &str) -> Result<Vec<Track>> {
+        let data: GetAlbumResponse = self
+            .get("getAlbum", vec![("id", album_id.to_string())])
+            .await?;
+        self.songs_to_tracks(data.album.song)
     }
     pub async fn get_stream_url_with_retry(&self, id: &str, timeout_secs: u64) -> Result<String> {
         let deadline = std::time::Instant::now() + Duration::from_secs(timeout_secs);
@@ -424,6 +1069,35 @@ impl SubsonicClient {
             tokio::time::sleep(Duration::from_millis(500)).await;
         }
     }
+    /// Fetch the original file for a track, used to keep it around for offline playback
+    pub async fn download(&self, id: &str) -> Result<Vec<u8>> {
+        let mut url = Url::parse(&format!("{}/rest/download", self.base_url))?;
+        let mut params = self.get_auth_params();
+        params.push(("id", id.to_string()));
+        for (key, value) in params {
+            url.query_pairs_mut().append_pair(key, &value);
+        }
+        let resp = with_retry(self.retries, || async {
+            self.download_client
+                .get(url.clone())
+                .send()
+                .await?
+                .error_for_status()
+        })
+        .await?;
+        let is_json = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("json"));
+        if is_json {
+            // Subsonic reports errors (e.g. downloads disabled for the user) as a json body
+            let res: SubsonicResponse<serde_json::Value> = resp.json().await?;
+            res.into_data()?;
+            anyhow::bail!("Server did not return a file");
+        }
+        Ok(resp.bytes().await?.to_vec())
+    }
     pub fn get_stream_url(&self, id: &str) -> Result<String> {
         let mut url = Url::parse(&format!("{}/rest/stream", self.base_url))?;
         let mut params = self.get_auth_params();
@@ -434,3 +1108,24 @@ impl SubsonicClient {
         Ok(url.to_string())
     }
 }
+
+/// Biographies come from last.fm as html, usually ending in a "Read more" link
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .trim()
+        .to_string()
+}
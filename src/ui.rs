@@ -1,15 +1,32 @@
 use crate::{
-    app::{ActiveSection, ActiveTab, App, InputMode, RepeatMode, ShuffleMode, Track},
-    theme::ResolvedTheme,
+    app::{
+        ActiveSection, ActiveTab, App, FolderEntry, InputMode, PodcastEntry, RepeatMode,
+        ShuffleMode, Track, rating::stars_label,
+    },
+    config::{ProgressLabel, SearchMode},
+    offline::OfflineCache,
+    setup::{SetupField, SetupForm},
+    subsonic::AlbumListType,
+    theme::{ResolvedTheme, Theme},
 };
+use image::{DynamicImage, imageops::FilterType};
 use ratatui::{
     Frame,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Tabs},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Tabs, Wrap},
 };
 use ratatui_image::StatefulImage;
+use std::collections::HashSet;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Cells an album thumbnail takes at the start of its row
+const THUMBNAIL_WIDTH: u16 = 2;
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const HIGHLIGHT_SYMBOL: &str = ">> ";
+/// Narrower lists leave the duration column out
+const MIN_DURATION_ROW_WIDTH: usize = 40;
 
 struct StatefulListConfig<'a> {
     items: Vec<ListItem<'a>>,
@@ -29,10 +46,18 @@ fn build_list_block<'a>(title: &'a str, border_style: Style) -> Block<'a> {
         .title(title)
 }
 fn active_title(label: &str, count: usize, is_active: bool) -> String {
+    active_title_with_detail(label, count, "", is_active)
+}
+fn active_title_with_detail(label: &str, count: usize, detail: &str, is_active: bool) -> String {
+    let base = if detail.is_empty() {
+        format!("{} ({})", label, count)
+    } else {
+        format!("{} ({} • {})", label, count, detail)
+    };
     if is_active {
-        format!("{} ({}) [ACTIVE]", label, count)
+        format!("{} [ACTIVE]", base)
     } else {
-        format!("{} ({})", label, count)
+        base
     }
 }
 
@@ -45,7 +70,7 @@ fn render_stateful_list(f: &mut Frame, config: StatefulListConfig) {
                 .fg(config.theme.highlight_fg)
                 .add_modifier(Modifier::BOLD),
         )
-        .highlight_symbol(">> ");
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
     if config.is_active && config.total != 0 {
         config
             .state
@@ -54,6 +79,153 @@ fn render_stateful_list(f: &mut Frame, config: StatefulListConfig) {
     f.render_stateful_widget(list, config.area, config.state);
 }
 
+/// Columns a row of a bordered list has for its text, the highlight symbol taken off
+fn list_row_width(area: Rect) -> usize {
+    area.width.saturating_sub(2 + HIGHLIGHT_SYMBOL.len() as u16) as usize
+}
+
+/// Pad a track row so its length sits against the right edge of the list. A row that would
+/// need the space for its text goes without, as do all rows of a narrow list
+fn with_duration<'a>(
+    mut line: Line<'a>,
+    track: &Track,
+    width: usize,
+    theme: &ResolvedTheme,
+) -> Line<'a> {
+    if width < MIN_DURATION_ROW_WIDTH {
+        return line;
+    }
+    let duration = if track.duration > 0 {
+        format_duration(track.duration / 1_000_000)
+    } else {
+        "--:--".to_string()
+    };
+    let used = line.width();
+    if used + 1 + duration.len() <= width {
+        line.spans
+            .push(Span::raw(" ".repeat(width - used - duration.len())));
+        line.spans.push(Span::styled(duration, theme.muted_color));
+    }
+    line
+}
+
+/// Cut `text` to `width` columns, ending in "…" when anything was cut off
+fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width > width - 1 {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Shorten a row to `width` columns so it can't run into the border. The rightmost spans are
+/// cut first, which keeps the title ahead of the album or artist that follows it
+fn fit_line(mut line: Line<'_>, width: usize) -> Line<'_> {
+    let mut excess = line.width().saturating_sub(width);
+    for span in line.spans.iter_mut().rev() {
+        if excess == 0 {
+            break;
+        }
+        let span_width = span.content.width();
+        if span_width == 0 {
+            continue;
+        }
+        let truncated = truncate_to_width(&span.content, span_width.saturating_sub(excess));
+        excess -= span_width - truncated.width();
+        span.content = truncated.into();
+    }
+    line
+}
+
+fn offline_marker(cache: &OfflineCache, track: &Track, theme: &ResolvedTheme) -> Span<'static> {
+    if cache.contains(&track.id) {
+        Span::styled("↓ ", theme.accent)
+    } else {
+        Span::raw("")
+    }
+}
+
+fn star_marker(track: &Track, theme: &ResolvedTheme) -> Span<'static> {
+    if track.starred {
+        Span::styled("★ ", theme.accent)
+    } else {
+        Span::styled("☆ ", theme.muted_color)
+    }
+}
+
+/// As many of `genres` as fit in `width` columns, the first one cut short if need be
+fn genre_label(genres: &[String], width: usize) -> String {
+    let mut label = String::new();
+    for genre in genres {
+        let next = if label.is_empty() {
+            genre.clone()
+        } else {
+            format!("{}, {}", label, genre)
+        };
+        if next.chars().count() > width {
+            if label.is_empty() && width > 1 {
+                label = genre.chars().take(width - 1).chain(['…']).collect();
+            }
+            break;
+        }
+        label = next;
+    }
+    label
+}
+
+/// File type and bitrate of a track, e.g. "FLAC • 960kbps", when the server told either
+fn format_label(track: &Track) -> Option<String> {
+    let format = track
+        .suffix
+        .clone()
+        .or_else(|| {
+            // "audio/flac", "audio/mpeg"
+            let content_type = track.content_type.as_deref()?;
+            content_type.split_once('/').map(|(_, sub)| sub.to_string())
+        })
+        .map(|format| format.to_uppercase());
+    let bit_rate = track
+        .bit_rate
+        .filter(|&kbps| kbps > 0)
+        .map(|kbps| format!("{}kbps", kbps));
+    match (format, bit_rate) {
+        (Some(format), Some(bit_rate)) => Some(format!("{} • {}", format, bit_rate)),
+        (format, bit_rate) => format.or(bit_rate),
+    }
+}
+
+/// The replaygain correction of the playing track and the manual boost, when either is in use
+fn gain_label(app: &App) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(rg) = app
+        .current_track
+        .as_ref()
+        .and_then(|t| t.replay_gain.as_ref())
+    {
+        let db = 20.0 * rg.factor(&app.config.playback.replay_gain).log10();
+        if db.abs() >= 0.1 {
+            parts.push(format!("RG {:+.1} dB", db));
+        }
+    }
+    if app.boost_db > 0.0 {
+        parts.push(format!("Boost +{:.1} dB", app.boost_db));
+    }
+    (!parts.is_empty()).then(|| format!(" {} ", parts.join(" • ")))
+}
+
 fn active_border_style(is_active: bool, theme: &ResolvedTheme) -> Style {
     if is_active {
         Style::default().fg(theme.border_active)
@@ -64,8 +236,10 @@ fn active_border_style(is_active: bool, theme: &ResolvedTheme) -> Style {
 fn build_list_items<'a, T>(
     items: &'a [T],
     selected_index: usize,
+    marked: &HashSet<usize>,
     is_active: bool,
     theme: &ResolvedTheme,
+    width: usize,
     render_item: impl Fn(usize, &'a T) -> Line<'a>,
 ) -> Vec<ListItem<'a>> {
     items
@@ -73,43 +247,514 @@ fn build_list_items<'a, T>(
         .enumerate()
         .map(|(i, item)| {
             let is_selected = is_active && i == selected_index;
-            let style = if is_selected {
+            let style = row_style(is_selected, marked.contains(&i), theme);
+            ListItem::new(vec![fit_line(render_item(i, item), width)]).style(style)
+        })
+        .collect()
+}
+/// Rows marked in visual mode stay reversed, and underlined while the cursor is on them
+fn row_style(is_selected: bool, is_marked: bool, theme: &ResolvedTheme) -> Style {
+    match (is_selected, is_marked) {
+        (true, marked) => {
+            let style = Style::default()
+                .bg(theme.highlight_bg)
+                .fg(theme.highlight_fg)
+                .add_modifier(Modifier::BOLD);
+            if marked {
+                style.add_modifier(Modifier::UNDERLINED)
+            } else {
+                style
+            }
+        }
+        (false, true) => Style::default().add_modifier(Modifier::REVERSED),
+        (false, false) => Style::default(),
+    }
+}
+pub fn draw(f: &mut Frame, app: &mut App) {
+    let theme = app.config.theme.resolve();
+    if app.lyrics_view {
+        draw_lyrics_view(f, app, f.area(), &theme);
+    } else if app.compact {
+        let main_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(f.area());
+        draw_compact_header(f, app, main_chunks[0], &theme);
+        draw_tabs(f, app, main_chunks[1], &theme);
+        draw_split_content(f, app, main_chunks[2], &theme);
+    } else {
+        let main_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(6),
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ])
+            .split(f.area());
+        draw_playback_header(f, app, main_chunks[0], &theme);
+        draw_tabs(f, app, main_chunks[1], &theme);
+        draw_split_content(f, app, main_chunks[2], &theme);
+        // draw_track_list(f, "Queue", app, main_chunks[3]);
+        draw_player_controls(f, app, main_chunks[3], &theme);
+    }
+    if app.stats_view && !app.lyrics_view {
+        draw_stats(f, app, &theme);
+    }
+    if app.artist_info.is_some() && !app.lyrics_view {
+        draw_artist_info(f, app, &theme);
+    }
+    match app.input_mode {
+        InputMode::Prompt => draw_prompt(f, app, &theme),
+        InputMode::Picker => draw_picker(f, app, &theme),
+        InputMode::Confirm => draw_confirm(f, app, &theme),
+        _ => {}
+    }
+}
+
+/// Full screen lyrics. Synced lines keep the one being sung in the middle with the rest dimmed,
+/// unsynced lyrics are a plain list scrolled with j/k
+fn draw_lyrics_view(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme) {
+    let title = match &app.current_track {
+        Some(track) => format!(" Lyrics: {} – {} ", track.artist, track.title),
+        None => " Lyrics ".to_string(),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border_active))
+        .title(title)
+        .title_bottom(
+            Line::from(Span::styled(" Esc to return ", theme.muted_color)).right_aligned(),
+        )
+        .style(Style::default().bg(theme.bg));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let Some(lyrics) = app.lyrics.as_ref().filter(|_| app.current_track.is_some()) else {
+        let message = if app.current_track.is_none() {
+            "Nothing is playing"
+        } else if app.offline {
+            "Lyrics can't be fetched while offline"
+        } else if app.lyrics_rx.is_some() {
+            "Loading lyrics..."
+        } else {
+            "No lyrics found for this track"
+        };
+        f.render_widget(
+            Paragraph::new(Span::styled(message, theme.muted_color)).alignment(Alignment::Center),
+            centered_rect(inner.width, 1, inner),
+        );
+        return;
+    };
+    let (lines, scroll) = if lyrics.synced {
+        let position_ms = app
+            .shared_state
+            .read()
+            .map_or(0, |s| s.position.as_millis());
+        let current = lyrics.current_line(position_ms);
+        // Blank lines above the first lyric let it sit in the middle too
+        let pad = inner.height / 2;
+        let mut lines = vec![Line::from(""); pad as usize];
+        lines.extend(lyrics.lines.iter().enumerate().map(|(i, line)| {
+            let style = if Some(i) == current {
                 Style::default()
-                    .bg(theme.highlight_bg)
-                    .fg(theme.highlight_fg)
+                    .fg(theme.accent)
                     .add_modifier(Modifier::BOLD)
             } else {
+                Style::default().fg(theme.muted_color)
+            };
+            Line::from(Span::styled(line.text.clone(), style))
+        }));
+        (lines, current.unwrap_or(0) as u16)
+    } else {
+        let lines = lyrics
+            .lines
+            .iter()
+            .map(|line| Line::from(Span::styled(line.text.clone(), theme.fg)))
+            .collect();
+        let last = lyrics.lines.len().saturating_sub(1) as u16;
+        (lines, app.lyrics_scroll.min(last))
+    };
+    f.render_widget(
+        Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .scroll((scroll, 0)),
+        inner,
+    );
+}
+
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+fn draw_prompt(f: &mut Frame, app: &App, theme: &ResolvedTheme) {
+    let Some(kind) = &app.prompt else {
+        return;
+    };
+    let area = centered_rect(60, 3, f.area());
+    f.render_widget(Clear, area);
+    let border_style = Style::default().fg(theme.accent);
+    let prompt = Paragraph::new(format!("{}: {}█", kind.label(), app.prompt_input))
+        .style(Style::default().fg(theme.fg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(Span::styled(
+                    format!("{} [Enter=Confirm Esc=Cancel]", kind.title()),
+                    border_style,
+                )),
+        );
+    f.render_widget(prompt, area);
+}
+pub fn draw_setup(f: &mut Frame, form: &SetupForm) {
+    let theme = Theme::default().resolve();
+    f.render_widget(
+        Block::default().style(Style::default().bg(theme.bg)),
+        f.area(),
+    );
+    let area = centered_rect(64, 9, f.area());
+    let border_style = Style::default().fg(theme.accent);
+    let mut lines: Vec<Line> = SetupField::ALL
+        .iter()
+        .map(|&field| {
+            let focused = field == form.focused();
+            let value = match field {
+                SetupField::Password => "*".repeat(form.value(field).chars().count()),
+                _ => form.value(field).to_string(),
+            };
+            let label_style = if focused {
                 Style::default()
+                    .fg(theme.highlight_fg)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.fg)
             };
-            ListItem::new(vec![render_item(i, item)]).style(style)
+            Line::from(vec![
+                Span::styled(format!("{:>11}: ", field.label()), label_style),
+                Span::styled(value, Style::default().fg(theme.fg)),
+                Span::raw(if focused { "█" } else { "" }),
+            ])
         })
-        .collect()
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        form.status.clone().unwrap_or_default(),
+        Style::default().fg(theme.accent),
+    )));
+    lines.push(Line::from(Span::styled(
+        "Tab=Next field  Enter=Connect  Esc=Quit",
+        Style::default().fg(theme.muted_color),
+    )));
+    let setup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style)
+            .title(Span::styled(" Connect to a Subsonic server ", border_style)),
+    );
+    f.render_widget(setup, area);
 }
-pub fn draw(f: &mut Frame, app: &mut App) {
-    let main_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([
-            Constraint::Length(6),
-            Constraint::Length(3),
-            Constraint::Min(0),
-            Constraint::Length(3),
+fn draw_confirm(f: &mut Frame, app: &App, theme: &ResolvedTheme) {
+    let Some(action) = &app.confirm else {
+        return;
+    };
+    let area = centered_rect(50, 3, f.area());
+    f.render_widget(Clear, area);
+    let border_style = Style::default().fg(theme.accent);
+    let confirm = Paragraph::new(action.message())
+        .style(Style::default().fg(theme.fg))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(Span::styled("Confirm [y=Yes n=No]", border_style)),
+        );
+    f.render_widget(confirm, area);
+}
+fn draw_stats(f: &mut Frame, app: &App, theme: &ResolvedTheme) {
+    let stats = app.library_stats();
+    let total = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:>10}: ", label), theme.muted_color),
+            Span::styled(value, theme.fg),
         ])
-        .split(f.area());
-    let theme = app.config.theme.resolve();
-    draw_playback_header(f, app, main_chunks[0], &theme);
-    draw_tabs(f, app, main_chunks[1], &theme);
-    draw_split_content(f, app, main_chunks[2], &theme);
-    // draw_track_list(f, "Queue", app, main_chunks[3]);
-    draw_player_controls(f, app, main_chunks[3], &theme);
+    };
+    let mut lines = vec![
+        total("Artists", stats.artists.to_string()),
+        total("Albums", stats.albums.to_string()),
+        total("Songs", stats.songs.to_string()),
+        total("Playtime", format_duration(stats.total_secs)),
+    ];
+    if !stats.most_played.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Most played",
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.extend(stats.most_played.iter().map(|track| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>5}x ", track.play_count.unwrap_or(0)),
+                    theme.muted_color,
+                ),
+                Span::styled(track.title.clone(), theme.fg),
+                Span::styled(format!(" - {}", track.artist), theme.muted_color),
+            ])
+        }));
+    }
+    let area = centered_rect(60, lines.len() as u16 + 2, f.area());
+    f.render_widget(Clear, area);
+    let border_style = Style::default().fg(theme.accent);
+    let stats = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style)
+            .title(Span::styled("Library [N/Esc=Close]", border_style)),
+    );
+    f.render_widget(stats, area);
+}
+/// Biography on top, wrapped and cut to at most half the overlay, similar artists below it
+fn draw_artist_info(f: &mut Frame, app: &mut App, theme: &ResolvedTheme) {
+    let Some(view) = app.artist_info.as_ref() else {
+        return;
+    };
+    let in_library: Vec<bool> = view
+        .similar
+        .data
+        .iter()
+        .map(|artist| app.library_artist_position(artist).is_some())
+        .collect();
+    let Some(view) = app.artist_info.as_mut() else {
+        return;
+    };
+    let area = centered_rect(70, f.area().height.saturating_sub(4), f.area());
+    f.render_widget(Clear, area);
+    let border_style = Style::default().fg(theme.accent);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(Span::styled(
+            format!("{} [Enter=Go to/Search w/Esc=Close]", view.name),
+            border_style,
+        ));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let bio = view
+        .biography
+        .as_deref()
+        .unwrap_or("No biography on the server");
+    // Rows the biography takes once wrapped, roughly as the paragraph breaks it
+    let width = inner.width.max(1) as usize;
+    let bio_rows: usize = bio.lines().map(|l| l.width().div_ceil(width).max(1)).sum();
+    let bio_height = if view.similar.data.is_empty() {
+        inner.height
+    } else {
+        (bio_rows as u16).min(inner.height / 2)
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(bio_height), Constraint::Min(0)])
+        .split(inner);
+    let bio_style = if view.biography.is_some() {
+        theme.fg
+    } else {
+        theme.muted_color
+    };
+    f.render_widget(
+        Paragraph::new(Span::styled(bio, bio_style)).wrap(Wrap { trim: true }),
+        chunks[0],
+    );
+    if view.similar.data.is_empty() {
+        return;
+    }
+    let items: Vec<ListItem> = view
+        .similar
+        .data
+        .iter()
+        .zip(&in_library)
+        .map(|(artist, &found)| {
+            let mut spans = vec![Span::styled(artist.name.as_str(), theme.fg)];
+            if !found {
+                spans.push(Span::styled(" (not in library)", theme.muted_color));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::TOP)
+                .border_style(border_style)
+                .title(Span::styled("Similar artists", border_style)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight_bg)
+                .fg(theme.highlight_fg)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
+    f.render_stateful_widget(list, chunks[1], &mut view.similar.state);
+}
+fn draw_picker(f: &mut Frame, app: &mut App, theme: &ResolvedTheme) {
+    let Some(picker) = app.picker.as_mut() else {
+        return;
+    };
+    let height = (picker.list.len() as u16).saturating_add(2).clamp(3, 15);
+    let area = centered_rect(60, height, f.area());
+    f.render_widget(Clear, area);
+    let border_style = Style::default().fg(theme.accent);
+    let items: Vec<ListItem> = picker
+        .list
+        .data
+        .iter()
+        .map(|label| ListItem::new(Line::from(Span::styled(label.as_str(), theme.fg))))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(Span::styled(
+                    format!("{} [Enter=Select Esc=Cancel]", picker.title),
+                    border_style,
+                )),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight_bg)
+                .fg(theme.highlight_fg)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
+    f.render_stateful_widget(list, area, &mut picker.list.state);
 }
 
+/// One line standing in for both the header and the controls bar, so notifications and the
+/// find bar take the place of the track while they are active
+fn draw_compact_header(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme) {
+    let line = if app.widget_notification.is_some()
+        || matches!(app.input_mode, InputMode::InlineSearch | InputMode::Filter)
+    {
+        let (text, style, _) = controls_text(app, theme);
+        Line::from(Span::styled(text, style.fg(theme.accent)))
+    } else if let Some(track) = &app.current_track {
+        let status_icon = if app.loading_track {
+            SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()]
+        } else if app.is_playing {
+            "▶"
+        } else {
+            "⏸"
+        };
+        let position = app
+            .shared_state
+            .read()
+            .map(|state| state.position.as_secs())
+            .unwrap_or(0);
+        let total = track.duration / 1_000_000;
+        let percent = if total > 0 {
+            (position * 100 / total).min(100)
+        } else {
+            0
+        };
+        Line::from(vec![
+            Span::styled(
+                format!("{} ", status_icon),
+                Style::default().fg(theme.playing_color),
+            ),
+            Span::styled(&track.title, Style::default().fg(theme.fg)),
+            Span::styled(" • ", Style::default().fg(theme.muted_color)),
+            Span::styled(&track.artist, Style::default().fg(theme.artist_color)),
+            Span::styled(
+                format!(" • {}%", percent),
+                Style::default().fg(theme.muted_color),
+            ),
+        ])
+    } else {
+        Line::from(Span::styled(
+            "♪ No track playing",
+            Style::default().fg(theme.muted_color),
+        ))
+    };
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(line), area);
+}
 fn draw_playback_header(f: &mut Frame, app: &mut App, area: Rect, theme: &ResolvedTheme) {
     // TODO:Add custom styling form the config file
     f.render_widget(Clear, area);
-    let block = Block::default()
+    let mut block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(theme.border_inactive));
+    if app.offline {
+        block = block.title(Span::styled(
+            " Offline: server unreachable, retrying… ",
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ));
+    } else if app.refreshing {
+        let frame = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
+        block = block.title(Span::styled(
+            format!(" {} Refreshing library… ", frame),
+            Style::default().fg(theme.accent),
+        ));
+    }
+    if let Some(left) = app.sleep_remaining() {
+        let label = if left.is_zero() {
+            " Sleep: stopping… ".to_string()
+        } else {
+            format!(" Sleep in {} ", format_duration(left.as_secs() as i64 + 1))
+        };
+        block = block.title(
+            Line::from(Span::styled(label, Style::default().fg(theme.accent))).right_aligned(),
+        );
+    }
+    if let Some(label) = gain_label(app) {
+        block = block.title(
+            Line::from(Span::styled(label, Style::default().fg(theme.muted_color))).right_aligned(),
+        );
+    }
+    if app.verbose_logging {
+        block = block.title(
+            Line::from(Span::styled(
+                " Verbose log ",
+                Style::default().fg(theme.muted_color),
+            ))
+            .right_aligned(),
+        );
+    }
+    if app.stop_after_current_group {
+        block = block.title(
+            Line::from(Span::styled(
+                " Stop after album ",
+                Style::default().fg(theme.accent),
+            ))
+            .right_aligned(),
+        );
+    }
+    if let Some(other) = app.now_playing.first() {
+        block = block.title_bottom(
+            Line::from(Span::styled(
+                format!(" Also playing: {} – {} ", other.username, other.track.title),
+                Style::default().fg(theme.muted_color),
+            ))
+            .right_aligned(),
+        );
+    }
     f.render_widget(block.clone(), area);
     let inner_area = block.inner(area);
     // let inner_area = Rect {
@@ -126,11 +771,12 @@ fn draw_playback_header(f: &mut Frame, app: &mut App, area: Rect, theme: &Resolv
                 Constraint::Length(1), //Progress bar
             ])
             .split(inner_area);
+        let cover_width = square_cells(font_size(app), header_chunks[0].height).0;
         let player_info_chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
-                Constraint::Length(8), //Cover art area
-                Constraint::Min(0),    //Track info
+                Constraint::Length(cover_width), //Cover art area
+                Constraint::Min(0),              //Track info
             ])
             .split(header_chunks[0]);
         draw_cover_art(f, app, track, player_info_chunks[0]);
@@ -168,15 +814,95 @@ fn draw_cover_art(f: &mut Frame, app: &mut App, track: &Track, area: Rect) {
         .as_ref()
         .map(|url| !url.is_empty())
         .unwrap_or(false);
-    if has_valid_cover && let Some(ref mut protocol) = app.cover_art_protocol {
+    let area = centered_square(area, font_size(app));
+    if has_valid_cover
+        && app.images_supported
+        && let Some(ref mut protocol) = app.cover_art_protocol
+    {
         let image_widget = StatefulImage::default();
         f.render_stateful_widget(image_widget, area, protocol);
         return;
     }
-    draw_cover_placeholder(f, area);
+    if has_valid_cover
+        && !app.images_supported
+        && let Some(img) = &app.cover_art_image
+    {
+        let size = (area.width, area.height);
+        if app
+            .cover_art_blocks
+            .as_ref()
+            .is_none_or(|(s, _)| *s != size)
+        {
+            app.cover_art_blocks = Some((size, cover_to_blocks(img, size.0, size.1)));
+        }
+        if let Some((_, lines)) = &app.cover_art_blocks {
+            f.render_widget(Paragraph::new(lines.clone()), area);
+        }
+        return;
+    }
+    draw_cover_placeholder(f, area, app.cover_art_failed);
 }
-fn draw_cover_placeholder(f: &mut Frame, area: Rect) {
-    let placeholder = Paragraph::new("♪")
+/// Pixel size of a terminal cell. Without a graphics protocol assume the usual 1:2 cell,
+/// which is also what makes half blocks square
+fn font_size(app: &App) -> (u16, u16) {
+    app.image_picker
+        .as_ref()
+        .map(|picker| picker.font_size())
+        .filter(|(w, h)| *w > 0 && *h > 0)
+        .unwrap_or((8, 16))
+}
+/// Columns and rows of the largest square that fits in `rows` rows
+fn square_cells((font_w, font_h): (u16, u16), rows: u16) -> (u16, u16) {
+    let cols = (rows as u32 * font_h as u32).div_ceil(font_w as u32);
+    (cols.min(u16::MAX as u32) as u16, rows)
+}
+/// The largest square, in pixels, that fits in `area`, centered in it
+fn centered_square(area: Rect, font: (u16, u16)) -> Rect {
+    let (cols, rows) = square_cells(font, area.height);
+    let (cols, rows) = if cols <= area.width {
+        (cols, rows)
+    } else {
+        // Too narrow, fit the width instead
+        let rows = (area.width as u32 * font.0 as u32 / font.1 as u32).max(1) as u16;
+        (area.width, rows.min(area.height))
+    };
+    Rect {
+        x: area.x + (area.width - cols) / 2,
+        y: area.y + (area.height - rows) / 2,
+        width: cols,
+        height: rows,
+    }
+}
+/// Cover art as upper half blocks, two pixels per cell, for terminals without graphics
+fn cover_to_blocks(img: &DynamicImage, width: u16, height: u16) -> Vec<Line<'static>> {
+    let pixels = img
+        .resize_exact(width as u32, height as u32 * 2, FilterType::Triangle)
+        .to_rgb8();
+    (0..height as u32)
+        .map(|row| {
+            let spans: Vec<Span> = (0..width as u32)
+                .map(|x| {
+                    let [tr, tg, tb] = pixels.get_pixel(x, row * 2).0;
+                    let [br, bg, bb] = pixels.get_pixel(x, row * 2 + 1).0;
+                    Span::styled(
+                        "▀",
+                        Style::default()
+                            .fg(Color::Rgb(tr, tg, tb))
+                            .bg(Color::Rgb(br, bg, bb)),
+                    )
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
+fn draw_cover_placeholder(f: &mut Frame, area: Rect, failed: bool) {
+    let text = if failed {
+        vec![Line::from("♪"), Line::from("Y retries")]
+    } else {
+        vec![Line::from("♪")]
+    };
+    let placeholder = Paragraph::new(text)
         .style(Style::default().fg(Color::DarkGray))
         .alignment(Alignment::Center)
         .block(
@@ -192,8 +918,14 @@ fn draw_track_info(f: &mut Frame, app: &App, track: &Track, area: Rect, theme: &
     } else {
         Modifier::empty()
     };
-    let status_icon = if app.is_playing { "▶" } else { "⏸" };
-    let status_color = if app.is_playing {
+    let status_icon = if app.loading_track {
+        SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()]
+    } else if app.is_playing {
+        "▶"
+    } else {
+        "⏸"
+    };
+    let status_color = if app.is_playing || app.loading_track {
         theme.playing_color
     } else {
         Color::LightYellow
@@ -208,18 +940,35 @@ fn draw_track_info(f: &mut Frame, app: &App, track: &Track, area: Rect, theme: &
         ShuffleMode::On => Span::styled("shuffle: on", Style::default().fg(theme.accent)),
             ShuffleMode::Off =>Span::styled("shuffle: off", Style::default().fg(theme.muted_color))
     };
+    let year = track.year.map(|y| format!(" ({})", y)).unwrap_or_default();
+    let rating = track
+        .rating
+        .map(|r| format!("  {}", stars_label(r)))
+        .unwrap_or_default();
+    // Genres take what is left of the album line
+    let used = 2 + track.album.chars().count() + year.len() + rating.chars().count() + 3;
+    let genres = genre_label(&track.genres, (area.width as usize).saturating_sub(used));
     let info_lines = vec![
         Line::from(vec![
             Span::styled(
                 format!("{} ", status_icon),
                 Style::default().fg(status_color).add_modifier(bold_mod),
             ),
+            star_marker(track, theme),
             Span::styled(
                 &track.title,
                 Style::default().fg(theme.fg).add_modifier(bold_mod),
             ),
             Span::styled(" — ", Style::default().fg(theme.muted_color)),
             Span::styled(&track.artist, Style::default().fg(theme.artist_color)),
+            Span::styled(
+                if app.loading_track {
+                    "  Buffering…"
+                } else {
+                    ""
+                },
+                Style::default().fg(theme.accent),
+            ),
         ]),
         Line::from(vec![
             Span::styled("  ", Style::default()), // Indent to align with title
@@ -230,6 +979,16 @@ fn draw_track_info(f: &mut Frame, app: &App, track: &Track, area: Rect, theme: &
                     .fg(theme.album_color)
                     .add_modifier(Modifier::ITALIC),
             ),
+            Span::styled(year, Style::default().fg(theme.muted_color)),
+            Span::styled(
+                if genres.is_empty() {
+                    String::new()
+                } else {
+                    format!(" · {}", genres)
+                },
+                Style::default().fg(theme.muted_color),
+            ),
+            Span::styled(rating, Style::default().fg(theme.accent)),
         ]),
         Line::from(vec![
             Span::styled("  ", Style::default()), // Indent
@@ -242,6 +1001,12 @@ fn draw_track_info(f: &mut Frame, app: &App, track: &Track, area: Rect, theme: &
             repeat_indicator,
             Span::styled("  ", Style::default()), // Indent
             shuffle_indicator,
+            Span::styled(
+                format_label(track)
+                    .map(|label| format!("  {}", label))
+                    .unwrap_or_default(),
+                Style::default().fg(theme.muted_color),
+            ),
         ]),
     ];
     let track_info = Paragraph::new(info_lines);
@@ -261,8 +1026,26 @@ fn draw_progress_bar(f: &mut Frame, app: &App, track: &Track, area: Rect, theme:
     };
 
     let current_time = format_duration(current_pos);
-    let total_time = format_duration(total_duration);
-    let time_display = format!("{}/{}", current_time, total_time);
+    let mut time_display = match app.progress_label {
+        // A station has no end, so there is nothing to measure against
+        _ if app.radio.is_some() => format!("LIVE {}", current_time),
+        // Without a length only the elapsed time means anything
+        _ if total_duration <= 0 => format!("{}/--:--", current_time),
+        ProgressLabel::Elapsed => {
+            format!("{}/{}", current_time, format_duration(total_duration))
+        }
+        ProgressLabel::Remaining => {
+            format!(
+                "-{}",
+                format_duration((total_duration - current_pos).max(0))
+            )
+        }
+        ProgressLabel::Percent => format!("{:.0}%", progress_ratio * 100.0),
+    };
+    // On a narrow terminal drop the total rather than cut the label off
+    if time_display.len() > area.width as usize {
+        time_display = current_time;
+    }
 
     let gauge = Gauge::default()
         .gauge_style(
@@ -282,10 +1065,10 @@ fn draw_progress_bar(f: &mut Frame, app: &App, track: &Track, area: Rect, theme:
 }
 fn format_duration(sec: i64) -> String {
     let hours = sec / 3600;
-    let mins = sec / 60;
+    let mins = (sec % 3600) / 60;
     let secs = sec % 60;
     if hours > 0 {
-        format!("{}:{:02}:{:02}", hours, mins, sec)
+        format!("{}:{:02}:{:02}", hours, mins, secs)
     } else {
         format!("{}:{:02}", mins, secs)
     }
@@ -297,6 +1080,8 @@ fn draw_tabs(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme) {
         Line::from("Album"),
         Line::from("Playlist"),
         Line::from("Favorites"),
+        Line::from("Folders"),
+        Line::from("Podcasts"),
     ];
     let selected_tab_index = match app.active_tab {
         ActiveTab::Songs => 0,
@@ -304,7 +1089,9 @@ fn draw_tabs(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme) {
         ActiveTab::Albums => 2,
         ActiveTab::Playlist => 3,
         ActiveTab::Favorites => 4,
-        ActiveTab::Search => 5,
+        ActiveTab::Folders => 5,
+        ActiveTab::Podcasts => 6,
+        ActiveTab::Search => 7,
     };
     let tabs = Tabs::new(tab_titles)
         .block(
@@ -353,6 +1140,10 @@ fn draw_content_area_with_border(
             draw_favorite_list_styled(f, app, area, border_style, is_active, theme)
         }
         ActiveTab::Search => draw_search_tab_styled(f, app, area, border_style, is_active, theme),
+        ActiveTab::Folders => draw_folder_list_styled(f, app, area, border_style, is_active, theme),
+        ActiveTab::Podcasts => {
+            draw_podcast_list_styled(f, app, area, border_style, is_active, theme)
+        }
     }
 }
 
@@ -364,10 +1155,27 @@ fn draw_queue_with_border(
     theme: &ResolvedTheme,
 ) {
     let border_style = active_border_style(is_active, theme);
+    // Durations are in microseconds; skip() keeps an out of range playing_index harmless
+    let remaining_secs: i64 = app
+        .queue_tab
+        .data
+        .iter()
+        .skip(app.playing_index)
+        .map(|t| t.duration / 1_000_000)
+        .sum();
+    let count = if app.queue_tab.data.is_empty() {
+        "0".to_string()
+    } else {
+        format!(
+            "{} • {} left",
+            app.queue_tab.len(),
+            format_duration(remaining_secs)
+        )
+    };
     let title = if is_active {
-        format!("Queue ({}) [ACTIVE]", app.queue_tab.len())
+        format!("Queue ({}) [ACTIVE]", count)
     } else {
-        format!("Queue ({})", app.queue_tab.len())
+        format!("Queue ({})", count)
     };
     if app.queue_tab.data.is_empty() {
         let empty_message =
@@ -389,6 +1197,7 @@ fn draw_queue_with_border(
         }
         app.queue_tab.current();
     }
+    let row_width = list_row_width(area);
     let tracks: Vec<ListItem> = app
         .queue_tab
         .data
@@ -402,7 +1211,7 @@ fn draw_queue_with_border(
             } else {
                 " "
             };
-            let content = vec![Line::from(vec![
+            let content = Line::from(vec![
                 Span::styled(
                     playing_indicator,
                     Style::default().fg(if is_playing {
@@ -411,6 +1220,8 @@ fn draw_queue_with_border(
                         theme.muted_color
                     }),
                 ),
+                offline_marker(&app.offline_cache, track, theme),
+                star_marker(track, theme),
                 Span::styled(
                     format!("{:03}. ", i + 1),
                     Style::default().fg(theme.muted_color),
@@ -427,7 +1238,8 @@ fn draw_queue_with_border(
                     format!(" - {}", track.artist),
                     Style::default().fg(theme.artist_color),
                 ),
-            ])];
+            ]);
+            let content = fit_line(with_duration(content, track, row_width, theme), row_width);
             let style = if is_selected {
                 Style::default()
                     .bg(theme.highlight_bg)
@@ -457,7 +1269,7 @@ fn draw_queue_with_border(
                 .fg(theme.highlight_fg)
                 .add_modifier(Modifier::BOLD),
         )
-        .highlight_symbol(">> ");
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
     f.render_stateful_widget(queue_list, area, &mut app.queue_tab.state);
 }
 
@@ -480,6 +1292,7 @@ fn draw_favorite_list_styled(
         }
         app.favorite_tab.current();
     }
+    let row_width = list_row_width(area);
     let tracks: Vec<ListItem> = app
         .favorite_tab
         .data
@@ -487,7 +1300,8 @@ fn draw_favorite_list_styled(
         .enumerate()
         .map(|(i, track)| {
             let is_selected = is_active && i == app.favorite_tab.index;
-            let content = vec![Line::from(vec![
+            let content = Line::from(vec![
+                offline_marker(&app.offline_cache, track, theme),
                 Span::styled(
                     format!("{:03}. {} - ", i + 1, track.artist),
                     Style::default().fg(theme.artist_color),
@@ -497,15 +1311,9 @@ fn draw_favorite_list_styled(
                     format!(" ({}) ", track.album),
                     Style::default().fg(theme.muted_color),
                 ),
-            ])];
-            let style = if is_selected {
-                Style::default()
-                    .bg(theme.highlight_bg)
-                    .fg(theme.highlight_fg)
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default()
-            };
+            ]);
+            let content = fit_line(with_duration(content, track, row_width, theme), row_width);
+            let style = row_style(is_selected, app.favorite_tab.marked.contains(&i), theme);
             ListItem::new(content).style(style)
         })
         .collect();
@@ -521,7 +1329,7 @@ fn draw_favorite_list_styled(
                 .bg(theme.highlight_bg)
                 .fg(theme.highlight_fg),
         )
-        .highlight_symbol(">> ");
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
     f.render_stateful_widget(track_list, area, &mut app.favorite_tab.state);
 }
 fn draw_song_list_styled(
@@ -532,24 +1340,48 @@ fn draw_song_list_styled(
     is_active: bool,
     theme: &ResolvedTheme,
 ) {
+    if app.songs_grouped {
+        draw_grouped_song_list(f, app, area, border_style, is_active, theme);
+        return;
+    }
+    let row_width = list_row_width(area);
     let items = build_list_items(
         &app.tracks_tab.data,
         app.tracks_tab.index,
+        &app.tracks_tab.marked,
         is_active,
         theme,
+        row_width,
         |i, track| {
-            Line::from(vec![
+            let line = Line::from(vec![
+                offline_marker(&app.offline_cache, track, theme),
+                star_marker(track, theme),
                 Span::styled(
                     format!("{:03}. {} - ", i + 1, track.artist),
                     theme.artist_color,
                 ),
                 Span::styled(&track.title, theme.fg),
                 Span::styled(format!(" ({}) ", track.album), theme.muted_color),
-            ])
+            ]);
+            with_duration(line, track, row_width, theme)
         },
     );
     let total = app.tracks_tab.len();
-    let title = active_title("Songs", total, is_active);
+    let total_secs: i64 = app
+        .tracks_tab
+        .data
+        .iter()
+        .map(|t| t.duration / 1_000_000)
+        .sum();
+    let label = match app.song_sort {
+        Some(sort) => format!("Songs by {}", sort.label()),
+        None => "Songs".to_string(),
+    };
+    let label = match app.genre_filter_label() {
+        Some(genres) => format!("{} [{}]", label, genres),
+        None => label,
+    };
+    let title = active_title_with_detail(&label, total, &format_duration(total_secs), is_active);
     render_stateful_list(
         f,
         StatefulListConfig {
@@ -566,6 +1398,96 @@ fn draw_song_list_styled(
     );
 }
 
+/// Songs under a header line per album. Headers only exist here, so navigation never lands on them
+fn draw_grouped_song_list(
+    f: &mut Frame,
+    app: &mut App,
+    area: Rect,
+    border_style: Style,
+    is_active: bool,
+    theme: &ResolvedTheme,
+) {
+    let mut items: Vec<ListItem> = Vec::new();
+    let mut selected_row = 0;
+    let mut previous: Option<&Track> = None;
+    let row_width = list_row_width(area);
+    for (i, track) in app.tracks_tab.data.iter().enumerate() {
+        if previous.is_none_or(|p| !p.same_album(track)) {
+            previous = Some(track);
+            let header = Line::from(vec![
+                Span::styled(
+                    format!("── {}", track.album),
+                    Style::default()
+                        .fg(theme.album_color)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!(
+                        " • {}",
+                        track.album_artist.as_deref().unwrap_or(&track.artist)
+                    ),
+                    theme.muted_color,
+                ),
+            ]);
+            items.push(ListItem::new(fit_line(header, row_width)));
+        }
+        if i == app.tracks_tab.index {
+            selected_row = items.len();
+        }
+        let style = row_style(
+            is_active && i == app.tracks_tab.index,
+            app.tracks_tab.marked.contains(&i),
+            theme,
+        );
+        let line = Line::from(vec![
+            offline_marker(&app.offline_cache, track, theme),
+            star_marker(track, theme),
+            Span::styled(
+                format!("  {:02}. ", track.track_number.unwrap_or(0)),
+                theme.muted_color,
+            ),
+            Span::styled(&track.title, theme.fg),
+            Span::styled(format!(" - {}", track.artist), theme.artist_color),
+        ]);
+        let line = fit_line(with_duration(line, track, row_width, theme), row_width);
+        items.push(ListItem::new(line).style(style));
+    }
+    let total = app.tracks_tab.len();
+    let total_secs: i64 = app
+        .tracks_tab
+        .data
+        .iter()
+        .map(|t| t.duration / 1_000_000)
+        .sum();
+    let label = match app.song_sort {
+        Some(sort) => format!("Songs grouped by album, by {}", sort.label()),
+        None => "Songs grouped by album".to_string(),
+    };
+    let label = match app.genre_filter_label() {
+        Some(genres) => format!("{} [{}]", label, genres),
+        None => label,
+    };
+    let title = active_title_with_detail(&label, total, &format_duration(total_secs), is_active);
+    let rows = items.len();
+    if !is_active {
+        app.grouped_songs_state.select(None);
+    }
+    render_stateful_list(
+        f,
+        StatefulListConfig {
+            items,
+            area,
+            border_style,
+            title,
+            state: &mut app.grouped_songs_state,
+            selected_index: selected_row,
+            total: rows,
+            is_active,
+            theme,
+        },
+    );
+}
+
 fn draw_search_tab_styled(
     f: &mut Frame,
     app: &mut App,
@@ -584,7 +1506,56 @@ fn draw_search_tab_styled(
         .split(area);
 
     draw_search_input(f, app, chunks[0], theme);
-    draw_search_results_styled(f, app, chunks[1], border_style, is_active, theme);
+    if app.search_albums.is_empty() && app.search_artists.is_empty() {
+        draw_search_results_styled(f, app, chunks[1], border_style, is_active, theme);
+        return;
+    }
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+        .split(chunks[1]);
+    draw_search_results_styled(f, app, columns[0], border_style, is_active, theme);
+    draw_search_matches(f, app, columns[1], theme);
+}
+/// Albums and artists a local search matched besides the songs, best first
+fn draw_search_matches(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme) {
+    let header = |label: &str, count: usize| {
+        Line::from(Span::styled(
+            format!("{} ({})", label, count),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ))
+    };
+    let mut lines = Vec::new();
+    if !app.search_albums.is_empty() {
+        lines.push(header("Albums", app.search_albums.len()));
+        lines.extend(app.search_albums.iter().map(|album| {
+            Line::from(vec![
+                Span::styled(format!("  {}", album.name), theme.album_color),
+                Span::styled(format!(" - {}", album.artist), theme.muted_color),
+            ])
+        }));
+    }
+    if !app.search_artists.is_empty() {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        lines.push(header("Artists", app.search_artists.len()));
+        lines.extend(app.search_artists.iter().map(|artist| {
+            Line::from(Span::styled(
+                format!("  {}", artist.name),
+                theme.artist_color,
+            ))
+        }));
+    }
+    let matches = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border_inactive))
+            .title("Albums & Artists"),
+    );
+    f.render_widget(matches, area);
 }
 fn draw_search_input(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme) {
     let (border_style, _cursor_style) = if app.input_mode == InputMode::Search {
@@ -609,11 +1580,19 @@ fn draw_search_input(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme
     };
 
     let mode_indicator = match app.input_mode {
-        InputMode::Search => " [SEARCH MODE - Press Esc to exit] ",
+        InputMode::Search => " [SEARCH MODE - Esc to exit, Ctrl+t to switch] ",
         InputMode::Normal => " [Press 's' to search] ",
-        InputMode::InlineSearch => "",
+        InputMode::InlineSearch
+        | InputMode::Filter
+        | InputMode::Prompt
+        | InputMode::Picker
+        | InputMode::Confirm => "",
     };
 
+    let backend = match app.search_mode {
+        SearchMode::Local => format!("Local, threshold {}", app.search_engine.threshold()),
+        SearchMode::Remote => "Server".to_string(),
+    };
     let search_input = Paragraph::new(input_text)
         .style(Style::default().fg(theme.fg))
         .block(
@@ -621,7 +1600,7 @@ fn draw_search_input(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme
                 .borders(Borders::ALL)
                 .border_style(border_style)
                 .title(Span::styled(
-                    format!("Search{}", mode_indicator),
+                    format!("Search ({}){}", backend, mode_indicator),
                     border_style,
                 )),
         );
@@ -637,24 +1616,33 @@ fn draw_search_results_styled(
     is_active: bool,
     theme: &ResolvedTheme,
 ) {
+    let row_width = list_row_width(area);
     let items = build_list_items(
         &app.search_tab.data,
         app.search_tab.index,
+        &app.search_tab.marked,
         is_active,
         theme,
+        row_width,
         |i, track| {
-            Line::from(vec![
+            let line = Line::from(vec![
+                offline_marker(&app.offline_cache, track, theme),
                 Span::styled(
                     format!("{:03}. {} - ", i + 1, track.artist),
                     theme.artist_color,
                 ),
                 Span::styled(&track.title, theme.fg),
                 Span::styled(format!(" ({}) ", track.album), theme.muted_color),
-            ])
+            ]);
+            with_duration(line, track, row_width, theme)
         },
     );
     let total = app.search_tab.len();
-    let title = active_title("Search", total, is_active);
+    let title = active_title(
+        app.search_label.as_deref().unwrap_or("Search"),
+        total,
+        is_active,
+    );
     render_stateful_list(
         f,
         StatefulListConfig {
@@ -681,16 +1669,21 @@ fn draw_playlist_styled(
     let items = build_list_items(
         &app.playlist_tab.data,
         app.playlist_tab.index,
+        &app.playlist_tab.marked,
         is_active,
         theme,
+        list_row_width(area),
         |i, playlist| {
             Line::from(vec![
                 Span::styled(
                     format!("{:03}. {} - ", i + 1, playlist.name),
                     theme.artist_color,
                 ),
-                Span::styled(format!("{}", &playlist.song_count), theme.fg),
-                Span::styled(format!(" ({}) ", playlist.duration), theme.muted_color),
+                Span::styled(format!("{} songs", &playlist.song_count), theme.fg),
+                Span::styled(
+                    format!(" ({}) ", format_duration(playlist.duration)),
+                    theme.muted_color,
+                ),
             ])
         },
     );
@@ -711,6 +1704,138 @@ fn draw_playlist_styled(
         },
     );
 }
+fn draw_folder_list_styled(
+    f: &mut Frame,
+    app: &mut App,
+    area: Rect,
+    border_style: Style,
+    is_active: bool,
+    theme: &ResolvedTheme,
+) {
+    let items = build_list_items(
+        &app.folder_tab.data,
+        app.folder_tab.index,
+        &app.folder_tab.marked,
+        is_active,
+        theme,
+        list_row_width(area),
+        |i, entry| match entry {
+            FolderEntry::Directory { name, .. } => Line::from(vec![Span::styled(
+                format!("{:03}. {}/", i + 1, name),
+                theme.artist_color,
+            )]),
+            FolderEntry::Song(track) => Line::from(vec![
+                Span::styled(format!("{:03}. {}", i + 1, track.title), theme.fg),
+                Span::styled(format!(" - {}", track.artist), theme.muted_color),
+                Span::styled(
+                    format!(" ({}) ", format_duration(track.duration / 1_000_000)),
+                    theme.muted_color,
+                ),
+            ]),
+        },
+    );
+    let total = app.folder_tab.len();
+    // The path below the music folders, e.g. "Folders: Artist / Album"
+    let label = if app.folder_path.is_empty() {
+        "Folders".to_string()
+    } else {
+        let path: Vec<&str> = app.folder_path.iter().map(|l| l.name.as_str()).collect();
+        format!("Folders: {}", path.join(" / "))
+    };
+    let title = active_title(&label, total, is_active);
+    render_stateful_list(
+        f,
+        StatefulListConfig {
+            items,
+            area,
+            border_style,
+            title,
+            state: &mut app.folder_tab.state,
+            selected_index: app.folder_tab.index,
+            total,
+            is_active,
+            theme,
+        },
+    );
+}
+fn draw_podcast_list_styled(
+    f: &mut Frame,
+    app: &mut App,
+    area: Rect,
+    border_style: Style,
+    is_active: bool,
+    theme: &ResolvedTheme,
+) {
+    let items = build_list_items(
+        &app.podcast_tab.data,
+        app.podcast_tab.index,
+        &app.podcast_tab.marked,
+        is_active,
+        theme,
+        list_row_width(area),
+        |i, entry| match entry {
+            PodcastEntry::Newest => Line::from(vec![Span::styled(
+                format!("{:03}. Newest episodes/", i + 1),
+                theme.accent,
+            )]),
+            PodcastEntry::Channel { title, .. } => Line::from(vec![Span::styled(
+                format!("{:03}. {}/", i + 1, title),
+                theme.artist_color,
+            )]),
+            PodcastEntry::Episode(episode) => {
+                // Episodes the server hasn't downloaded can't be streamed, so they are dimmed
+                let title_color = if episode.is_downloaded() {
+                    theme.fg
+                } else {
+                    theme.muted_color
+                };
+                let date = episode
+                    .publish_date
+                    .as_deref()
+                    .map(|d| format!(" {}", d.get(..10).unwrap_or(d)))
+                    .unwrap_or_default();
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:03}. {}", i + 1, episode.track.title),
+                        title_color,
+                    ),
+                    Span::styled(
+                        format!(" - {}{}", episode.track.album, date),
+                        theme.muted_color,
+                    ),
+                    Span::styled(
+                        format!(
+                            " ({}) ",
+                            format_duration(episode.track.duration / 1_000_000)
+                        ),
+                        theme.muted_color,
+                    ),
+                    Span::styled(format!("[{}]", episode.status_label()), theme.muted_color),
+                ])
+            }
+        },
+    );
+    let total = app.podcast_tab.len();
+    let label = match &app.podcast_channel {
+        Some(level) => format!("Podcasts: {}", level.name),
+        None => "Podcasts".to_string(),
+    };
+    let title = active_title(&label, total, is_active);
+    render_stateful_list(
+        f,
+        StatefulListConfig {
+            items,
+            area,
+            border_style,
+            title,
+            state: &mut app.podcast_tab.state,
+            selected_index: app.podcast_tab.index,
+            total,
+            is_active,
+            theme,
+        },
+    );
+}
 fn draw_album_list_styled(
     f: &mut Frame,
     app: &mut App,
@@ -719,23 +1844,59 @@ fn draw_album_list_styled(
     is_active: bool,
     theme: &ResolvedTheme,
 ) {
+    let thumbnail_gap = if app.album_thumbnails {
+        " ".repeat(THUMBNAIL_WIDTH as usize + 1)
+    } else {
+        String::new()
+    };
     let items = build_list_items(
         &app.album_tab.data,
         app.album_tab.index,
+        &app.album_tab.marked,
         is_active,
         theme,
+        list_row_width(area),
         |i, album| {
+            let mut details = Vec::new();
+            if let Some(year) = album.year {
+                details.push(year.to_string());
+            }
+            if let Some(count) = album.song_count {
+                details.push(format!("{} songs", count));
+            }
+            if let Some(duration) = album.duration {
+                details.push(format_duration(duration));
+            }
+            let details = if details.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", details.join(" • "))
+            };
             Line::from(vec![
+                Span::raw(thumbnail_gap.clone()),
                 Span::styled(
                     format!("{:03}. {} - ", i + 1, album.name),
                     theme.album_color,
                 ),
                 Span::styled(&album.artist, theme.artist_color),
+                Span::styled(details, theme.muted_color),
             ])
         },
     );
     let total = app.album_tab.len();
-    let title = active_title("Albums", total, is_active);
+    let total_songs: i32 = app.album_tab.data.iter().filter_map(|a| a.song_count).sum();
+    let total_secs: i64 = app.album_tab.data.iter().filter_map(|a| a.duration).sum();
+    let detail = if total_songs > 0 {
+        format!("{} songs • {}", total_songs, format_duration(total_secs))
+    } else {
+        String::new()
+    };
+    let label = match (app.album_sort, app.album_list_type) {
+        (_, list) if list != AlbumListType::default() => format!("Albums • {}", list.label()),
+        (Some(sort), _) => format!("Albums by {}", sort.label()),
+        (None, _) => "Albums".to_string(),
+    };
+    let title = active_title_with_detail(&label, total, &detail, is_active);
     render_stateful_list(
         f,
         StatefulListConfig {
@@ -750,6 +1911,42 @@ fn draw_album_list_styled(
             theme,
         },
     );
+    if app.album_thumbnails {
+        draw_album_thumbnails(f, app, area);
+    }
+}
+/// Draw cached covers over the gap left at the start of each visible album row
+fn draw_album_thumbnails(f: &mut Frame, app: &mut App, area: Rect) {
+    let inner = area.inner(Margin::new(1, 1));
+    // The list indents every row by the highlight symbol once something is selected
+    let indent = if app.album_tab.state.selected().is_some() {
+        HIGHLIGHT_SYMBOL.len() as u16
+    } else {
+        0
+    };
+    if inner.width < indent + THUMBNAIL_WIDTH {
+        return;
+    }
+    let offset = app.album_tab.state.offset();
+    for row in 0..inner.height {
+        let Some(album) = app.album_tab.data.get(offset + row as usize) else {
+            break;
+        };
+        let Some(protocol) = album
+            .cover_art
+            .as_ref()
+            .and_then(|id| app.thumbnail_cache.get_mut(id))
+        else {
+            continue;
+        };
+        let cell = Rect {
+            x: inner.x + indent,
+            y: inner.y + row,
+            width: THUMBNAIL_WIDTH,
+            height: 1,
+        };
+        f.render_stateful_widget(StatefulImage::default(), cell, protocol);
+    }
 }
 fn draw_artist_list_styled(
     f: &mut Frame,
@@ -762,15 +1959,17 @@ fn draw_artist_list_styled(
     let items = build_list_items(
         &app.artist_tab.data,
         app.artist_tab.index,
+        &app.artist_tab.marked,
         is_active,
         theme,
+        list_row_width(area),
         |i, artist| {
             Line::from(vec![
                 Span::styled(
                     format!("{:03}. {} - ", i + 1, artist.name),
                     theme.artist_color,
                 ),
-                Span::styled(format!(" {} ", &artist.album_count), theme.fg),
+                Span::styled(format!(" {} albums ", &artist.album_count), theme.fg),
             ])
         },
     );
@@ -792,13 +1991,27 @@ fn draw_artist_list_styled(
     );
 }
 fn draw_player_controls(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTheme) {
+    let (controls, border_style, title) = controls_text(app, theme);
+    let typing = matches!(app.input_mode, InputMode::InlineSearch | InputMode::Filter);
+    let controls_widget = Paragraph::new(controls)
+        .style(Style::default().fg(if typing { theme.accent } else { theme.fg }))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(title),
+        );
+    f.render_widget(controls_widget, area);
+}
+/// What the controls bar shows: a notification, the find bar, or the key hints
+fn controls_text(app: &App, theme: &ResolvedTheme) -> (String, Style, &'static str) {
     let section_indicator = match app.active_section {
         ActiveSection::Queue => "[Queue]",
         ActiveSection::Others => "[Library]",
     };
 
     // Show notification if active, other show normal controls
-    let (controls, border_style, title) = if let Some((msg, _)) = &app.widget_notification {
+    if let Some((msg, _)) = &app.widget_notification {
         (
             format!("* {}", msg),
             Style::default().fg(theme.accent),
@@ -806,10 +2019,28 @@ fn draw_player_controls(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTh
         )
     } else if app.input_mode == InputMode::InlineSearch {
         (
-            format!("/ {}█  [Enter/Esc to exit inline search]", app.search_query),
+            format!(
+                "/ {}█ {} [Ctrl+n/Ctrl+p=Next/Prev match Enter/Esc to exit inline search]",
+                app.search_query,
+                app.inline_match_position()
+                    .map(|(current, total)| format!("({}/{})", current, total))
+                    .unwrap_or_default()
+            ),
             Style::default().fg(theme.accent),
             "Find",
         )
+    } else if app.input_mode == InputMode::Filter {
+        (
+            format!(
+                "\\ {}█ {} [Enter=Keep the filtered list Esc=Show everything]",
+                app.filter_query,
+                app.filter_counts()
+                    .map(|(shown, total)| format!("({}/{})", shown, total))
+                    .unwrap_or_default()
+            ),
+            Style::default().fg(theme.accent),
+            "Filter",
+        )
     } else {
         (
             format!(
@@ -819,20 +2050,5 @@ fn draw_player_controls(f: &mut Frame, app: &App, area: Rect, theme: &ResolvedTh
             Style::default(),
             "Controls",
         )
-    };
-    let controls_widget = Paragraph::new(controls)
-        .style(
-            Style::default().fg(if app.input_mode == InputMode::InlineSearch {
-                theme.accent
-            } else {
-                theme.fg
-            }),
-        )
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(border_style)
-                .title(title),
-        );
-    f.render_widget(controls_widget, area);
+    }
 }